@@ -5,6 +5,7 @@ use challonge::Challonge;
 use challonge::ParticipantCreate;
 use chrono::*;
 
+#[allow(deprecated)]
 fn main() {
     let c = Challonge::new("myusername", "myapi_key");
     let i = c.tournament_index(
@@ -101,14 +102,8 @@ fn main() {
         )
     );
 
-    let pc = ParticipantCreate {
-        // name: "username".to_owned(),
-        name: None,
-        challonge_username: None,
-        email: "mail@themail.com".to_owned(),
-        seed: 1,
-        misc: "PIZDEC ON KRASAVCHIK".to_owned(),
-    };
+    let mut pc = ParticipantCreate::new();
+    pc.email("mail@themail.com").misc("PIZDEC ON KRASAVCHIK");
     println!(
         "Participant result: {:?}",
         c.create_participant(
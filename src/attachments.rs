@@ -23,18 +23,16 @@ pub struct Asset {
 }
 impl Asset {
     /// Decodes `Asset` from `Attachment`'s JSON
-    pub fn decode(mut map: &mut serde_json::Map<String, Value>) -> Result<Asset, Error> {
+    pub fn decode(map: &mut serde_json::Map<String, Value>) -> Result<Asset, Error> {
         Ok(Asset {
-            file_name: remove(&mut map, "asset_file_name")?
+            file_name: remove(map, "asset_file_name")?
                 .as_str()
                 .map(|f| f.to_owned()),
-            content_type: remove(&mut map, "asset_content_type")?
-                .as_str()
-                .map(|f| f.to_owned()),
-            file_size: remove(&mut map, "asset_file_size")?.as_u64(),
-            url: remove(&mut map, "asset_url")?
+            content_type: remove(map, "asset_content_type")?
                 .as_str()
                 .map(|f| f.to_owned()),
+            file_size: remove(map, "asset_file_size")?.as_u64(),
+            url: remove(map, "asset_url")?.as_str().map(|f| f.to_owned()),
         })
     }
 }
@@ -74,8 +72,9 @@ impl Default for AttachmentCreate {
 }
 
 /// Unique attachment id
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct AttachmentId(pub u64);
+numeric_id!(AttachmentId);
 
 /// Challonge `Attachment` definition.
 #[derive(Debug, Clone)]
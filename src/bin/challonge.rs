@@ -0,0 +1,675 @@
+//! `challonge` command-line tool for day-to-day tournament operations.
+//!
+//! Credentials are read from the `CHALLONGE_USERNAME` and `CHALLONGE_API_KEY` environment
+//! variables, or from a config file of `key = value` lines (`username`, `api_key`) at
+//! `$CHALLONGE_CONFIG` or `~/.config/challonge/config`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde_json::{json, Value};
+
+use challonge::{
+    Challonge, Match, MatchScores, MatchState, MatchUpdate, Participant, ParticipantCreate,
+    ParticipantId, Tournament, TournamentCreate, TournamentId, TournamentIncludes, TournamentState,
+    TournamentType,
+};
+
+const USAGE: &str = "\
+Usage: challonge [--json] <command> [arguments] [options]
+
+Tournaments:
+  tournaments [--state all|pending|in_progress|ended] [--type TYPE]
+              [--subdomain SUBDOMAIN] [--created-after DATE] [--created-before DATE]
+  show <tournament>
+  create <name> <url> [--type TYPE] [--subdomain SUBDOMAIN] [--description TEXT]
+  start <tournament>
+  finalize <tournament>
+  reset <tournament>
+
+Participants:
+  participants <tournament>
+  add <tournament> <name> [--email EMAIL] [--username USERNAME] [--seed SEED] [--misc TEXT]
+  check-in <tournament> <participant-id>
+  remove <tournament> <participant-id>
+
+Matches:
+  matches <tournament> [--state open|pending|complete|all]
+  report <tournament> <match-id> <scores> [--winner PARTICIPANT-ID|tie]
+
+A tournament is its id, its url (\"subdomain-url\" for organization tournaments) or
+its Challonge link. TYPE is single_elimination, double_elimination, round_robin
+or swiss; tournaments of every type are listed unless --type is given, and created
+ones are single_elimination by default. Scores are comma-separated sets, e.g.
+\"3-1,2-3\"; the winner is taken from the scores unless given.
+
+Output is a table unless --json is given.
+";
+
+/// Parsed command line: `--json`, `--key value` options and positional arguments.
+struct Args {
+    json: bool,
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+        let mut parsed = Args {
+            json: false,
+            positional: Vec::new(),
+            options: HashMap::new(),
+        };
+        while let Some(arg) = args.next() {
+            if arg == "--json" {
+                parsed.json = true;
+            } else if arg == "--help" || arg == "-h" {
+                parsed.positional.insert(0, "help".to_owned());
+            } else if let Some(key) = arg.strip_prefix("--") {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value of --{}", key))?;
+                parsed.options.insert(key.to_owned(), value);
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn arg(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(|s| s.as_str())
+            .ok_or_else(|| format!("missing <{}>\n\n{}", name, USAGE))
+    }
+
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(|s| s.as_str())
+    }
+
+    fn tournament(&self) -> Result<TournamentId, String> {
+        let arg = self.arg(1, "tournament")?;
+        TournamentId::from_str(arg).map_err(|_| format!("invalid tournament {:?}", arg))
+    }
+
+    fn participant(&self) -> Result<ParticipantId, String> {
+        let arg = self.arg(2, "participant-id")?;
+        arg.parse()
+            .map_err(|_| format!("invalid participant id {:?}", arg))
+    }
+}
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    };
+    match args.positional.first().map(|s| s.as_str()) {
+        None | Some("help") => {
+            print!("{}", USAGE);
+            return;
+        }
+        _ => {}
+    }
+    let result = credentials().and_then(|(username, api_key)| {
+        let c = Challonge::new(username, api_key);
+        run(&c, &args)
+    });
+    match result {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Reads the credentials from the environment, falling back to the config file.
+fn credentials() -> Result<(String, String), String> {
+    let mut username = env::var("CHALLONGE_USERNAME").ok();
+    let mut api_key = env::var("CHALLONGE_API_KEY").ok();
+    if username.is_none() || api_key.is_none() {
+        let path = env::var_os("CHALLONGE_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/challonge/config"))
+            });
+        if let Some(config) = path.and_then(|p| fs::read_to_string(p).ok()) {
+            for line in config.lines() {
+                let line = line.trim();
+                if line.starts_with('#') {
+                    continue;
+                }
+                if let Some((key, value)) = line.split_once('=') {
+                    let value = value.trim().trim_matches('"').to_owned();
+                    match key.trim() {
+                        "username" => username = username.or(Some(value)),
+                        "api_key" => api_key = api_key.or(Some(value)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    match (username, api_key) {
+        (Some(u), Some(k)) => Ok((u, k)),
+        _ => Err(
+            "no credentials: set CHALLONGE_USERNAME and CHALLONGE_API_KEY \
+                  or write them to ~/.config/challonge/config"
+                .to_owned(),
+        ),
+    }
+}
+
+fn run(c: &Challonge, args: &Args) -> Result<String, String> {
+    let api = |e: challonge::error::Error| e.to_string();
+    let command = args.arg(0, "command")?;
+    match command {
+        "tournaments" => {
+            let index = list_tournaments(c, args)?;
+            Ok(tournaments_output(&index.0, args.json))
+        }
+        "show" => {
+            let t = c
+                .get_tournament(&args.tournament()?, &TournamentIncludes::Participants)
+                .map_err(api)?;
+            Ok(tournaments_output(&[t], args.json))
+        }
+        "create" => {
+            let mut tc = TournamentCreate::new();
+            tc.name(args.arg(1, "name")?).url(args.arg(2, "url")?);
+            if let Some(t) = args.option("type") {
+                tc.tournament_type(tournament_type(t)?);
+            }
+            if let Some(s) = args.option("subdomain") {
+                tc.subdomain(s);
+            }
+            if let Some(d) = args.option("description") {
+                tc.description(d);
+            }
+            let t = c.create_tournament(&tc).map_err(api)?;
+            Ok(tournaments_output(&[t], args.json))
+        }
+        "start" | "finalize" | "reset" => {
+            let id = args.tournament()?;
+            let includes = TournamentIncludes::Matches;
+            match command {
+                "start" => c.tournament_start(&id, &includes),
+                "finalize" => c.tournament_finalize(&id, &includes),
+                _ => c.tournament_reset(&id, &includes),
+            }
+            .map_err(api)?;
+            Ok(done(command, &id.to_string(), args.json))
+        }
+        "participants" => {
+            let index = c.participant_index(&args.tournament()?).map_err(api)?;
+            Ok(participants_output(&index.0, args.json))
+        }
+        "add" => {
+            let mut pc = ParticipantCreate::new();
+            pc.name(args.arg(2, "name")?);
+            if let Some(e) = args.option("email") {
+                pc.email(e);
+            }
+            if let Some(u) = args.option("username") {
+                pc.challonge_username(u);
+            }
+            if let Some(s) = args.option("seed") {
+                pc.seed(s.parse().map_err(|_| format!("invalid seed {:?}", s))?);
+            }
+            if let Some(m) = args.option("misc") {
+                pc.misc(m);
+            }
+            let p = c
+                .create_participant(&args.tournament()?, &pc)
+                .map_err(api)?;
+            Ok(participants_output(&[p], args.json))
+        }
+        "check-in" => {
+            let p = args.participant()?;
+            c.check_in_participant(&args.tournament()?, &p)
+                .map_err(api)?;
+            Ok(done("checked in", &p.to_string(), args.json))
+        }
+        "remove" => {
+            let p = args.participant()?;
+            c.delete_participant(&args.tournament()?, &p).map_err(api)?;
+            Ok(done("removed", &p.to_string(), args.json))
+        }
+        "matches" => {
+            let id = args.tournament()?;
+            let state = match args.option("state").unwrap_or("open") {
+                "all" => None,
+                s => Some(MatchState::from_str(s).map_err(|_| format!("invalid state {:?}", s))?),
+            };
+            let matches = c.match_index(&id, state, None).map_err(api)?;
+            let participants = c.participant_index(&id).map_err(api)?;
+            Ok(matches_output(&matches.0, &participants.0, args.json))
+        }
+        "report" => {
+            let id = args.tournament()?;
+            let match_arg = args.arg(2, "match-id")?;
+            let match_id = match_arg
+                .parse()
+                .map_err(|_| format!("invalid match id {:?}", match_arg))?;
+            let scores_arg = args.arg(3, "scores")?;
+            let scores = MatchScores::from_str(scores_arg).map_err(api)?;
+            let m = c.get_match(&id, &match_id, false).map_err(api)?;
+            let t = c
+                .get_tournament(&id, &TournamentIncludes::Participants)
+                .map_err(api)?;
+            let mut update =
+                MatchUpdate::from_scores(&m, scores, &t.tournament_type).map_err(api)?;
+            match args.option("winner") {
+                Some("tie") => {
+                    update.tie();
+                }
+                Some(w) => {
+                    update.winner_id(w.parse().map_err(|_| format!("invalid winner {:?}", w))?);
+                }
+                None => {}
+            }
+            update.validate(&m, &t.tournament_type).map_err(api)?;
+            let m = c.update_match(&id, &match_id, &update).map_err(api)?;
+            let participants = t.participants.map(|p| p.0).unwrap_or_default();
+            Ok(matches_output(&[m], &participants, args.json))
+        }
+        _ => Err(format!("unknown command {:?}\n\n{}", command, USAGE)),
+    }
+}
+
+/// The filters of the `tournaments` command.
+#[allow(deprecated)]
+#[derive(Debug)]
+struct TournamentQuery {
+    state: TournamentState,
+    tournament_type: Option<TournamentType>,
+    created_after: chrono::Date<Local>,
+    created_before: chrono::Date<Local>,
+    subdomain: String,
+}
+impl TournamentQuery {
+    #[allow(deprecated)]
+    fn parse(args: &Args) -> Result<TournamentQuery, String> {
+        let state = match args.option("state").unwrap_or("all") {
+            "all" => TournamentState::All,
+            "pending" => TournamentState::Pending,
+            "in_progress" => TournamentState::InProgress,
+            "ended" => TournamentState::Ended,
+            s => return Err(format!("invalid state {:?}", s)),
+        };
+        let tournament_type = match args.option("type") {
+            Some(t) => Some(tournament_type(t)?),
+            None => None,
+        };
+        let date = |key: &str, default: chrono::Date<Local>| match args.option(key) {
+            Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| Local.from_local_date(&d).single())
+                .ok_or_else(|| format!("invalid date {:?}, expected YYYY-MM-DD", d)),
+            None => Ok(default),
+        };
+        Ok(TournamentQuery {
+            state,
+            tournament_type,
+            created_after: date("created-after", Local.ymd(2000, 1, 1))?,
+            created_before: date("created-before", Local::today().succ())?,
+            subdomain: args.option("subdomain").unwrap_or("").to_owned(),
+        })
+    }
+}
+
+fn list_tournaments(c: &Challonge, args: &Args) -> Result<challonge::TournamentIndex, String> {
+    let q = TournamentQuery::parse(args)?;
+    let (after, before) = (&q.created_after, &q.created_before);
+    match &q.tournament_type {
+        Some(t) => c.tournament_index(&q.state, t, after, before, &q.subdomain),
+        None => c.tournament_index_any_type(&q.state, after, before, &q.subdomain),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn tournament_type(s: &str) -> Result<TournamentType, String> {
+    TournamentType::from_str(s).map_err(|_| format!("invalid tournament type {:?}", s))
+}
+
+fn done(action: &str, what: &str, json: bool) -> String {
+    if json {
+        format!("{}\n", json!({ "ok": true, "action": action, "id": what }))
+    } else {
+        format!("{}: {}\n", action, what)
+    }
+}
+
+fn pretty(value: Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap() + "\n"
+}
+
+/// Formats the rows as a table with left-aligned columns.
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<String>| -> String {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{:<w$}", c, w = w))
+            .collect();
+        padded.join("  ").trim_end().to_owned() + "\n"
+    };
+    let mut out = line(header.iter().map(|h| (*h).to_owned()).collect());
+    for row in rows {
+        out.push_str(&line(row));
+    }
+    out
+}
+
+fn tournaments_output(tournaments: &[Tournament], json: bool) -> String {
+    if json {
+        return pretty(Value::Array(
+            tournaments
+                .iter()
+                .map(|t| {
+                    json!({
+                        "id": t.id.to_string(),
+                        "name": t.name,
+                        "url": t.full_challonge_url,
+                        "tournament_type": t.tournament_type.to_get_param(),
+                        "game_name": t.game_name,
+                        "participants_count": t.participants_count,
+                        "progress_meter": t.progress_meter,
+                        "started_at": t.started_at.map(|s| s.to_rfc3339()),
+                        "created_at": t.created_at.to_rfc3339(),
+                    })
+                })
+                .collect(),
+        ));
+    }
+    table(
+        &["ID", "NAME", "TYPE", "PARTICIPANTS", "PROGRESS", "URL"],
+        tournaments
+            .iter()
+            .map(|t| {
+                vec![
+                    t.id.to_string(),
+                    t.name.clone(),
+                    t.tournament_type.to_string(),
+                    t.participants_count.to_string(),
+                    format!("{}%", t.progress_meter),
+                    t.full_challonge_url.clone(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn participants_output(participants: &[Participant], json: bool) -> String {
+    let mut sorted: Vec<&Participant> = participants.iter().collect();
+    sorted.sort_by_key(|p| p.seed);
+    if json {
+        return pretty(Value::Array(
+            sorted
+                .iter()
+                .map(|p| {
+                    json!({
+                        "id": p.id.0,
+                        "seed": p.seed,
+                        "name": p.name,
+                        "challonge_username": p.challonge_username,
+                        "active": p.active,
+                        "checked_in": p.checked_in,
+                        "on_waiting_list": p.on_waiting_list,
+                        "final_rank": p.final_rank,
+                        "misc": p.misc,
+                    })
+                })
+                .collect(),
+        ));
+    }
+    table(
+        &["ID", "SEED", "NAME", "USERNAME", "CHECKED IN", "RANK"],
+        sorted
+            .iter()
+            .map(|p| {
+                vec![
+                    p.id.to_string(),
+                    p.seed.to_string(),
+                    p.name.clone(),
+                    p.challonge_username.clone(),
+                    if p.checked_in { "yes" } else { "no" }.to_owned(),
+                    p.final_rank.map(|r| r.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn matches_output(matches: &[Match], participants: &[Participant], json: bool) -> String {
+    let name = |id: Option<ParticipantId>| -> String {
+        participants
+            .iter()
+            .find(|p| Some(p.id) == id)
+            .map(|p| p.name.clone())
+            .unwrap_or_default()
+    };
+    let mut sorted: Vec<&Match> = matches.iter().collect();
+    sorted.sort_by_key(|m| (m.suggested_play_order, m.id));
+    if json {
+        return pretty(Value::Array(
+            sorted
+                .iter()
+                .map(|m| {
+                    json!({
+                        "id": m.id.0,
+                        "identifier": m.identifier,
+                        "round": m.round,
+                        "state": m.state.to_string(),
+                        "player1_id": m.player1.id.map(|p| p.0),
+                        "player1": name(m.player1.id),
+                        "player2_id": m.player2.id.map(|p| p.0),
+                        "player2": name(m.player2.id),
+                        "scores_csv": m.scores_csv.to_string(),
+                        "winner_id": m.winner_id.map(|w| w.0),
+                        "location": m.location,
+                    })
+                })
+                .collect(),
+        ));
+    }
+    table(
+        &[
+            "ID", "MATCH", "ROUND", "STATE", "PLAYER 1", "PLAYER 2", "SCORES", "WINNER",
+        ],
+        sorted
+            .iter()
+            .map(|m| {
+                vec![
+                    m.id.to_string(),
+                    m.identifier.clone(),
+                    m.round.to_string(),
+                    m.state.to_string(),
+                    name(m.player1.id),
+                    name(m.player2.id),
+                    m.scores_csv.to_string(),
+                    name(m.winner_id),
+                ]
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &[&str]) -> Result<Args, String> {
+        Args::parse(line.iter().map(|s| (*s).to_owned()))
+    }
+
+    fn participant(id: u64, name: &str, seed: u64, checked_in: bool) -> Participant {
+        Participant::decode(json!({
+            "participant": {
+                "active": true,
+                "checked_in_at": null,
+                "created_at": "2015-01-19T16:54:40-05:00",
+                "final_rank": null,
+                "group_id": null,
+                "icon": null,
+                "id": id,
+                "invitation_id": null,
+                "invite_email": null,
+                "misc": null,
+                "name": name,
+                "on_waiting_list": false,
+                "seed": seed,
+                "tournament_id": 1086875,
+                "updated_at": "2015-01-19T16:54:40-05:00",
+                "challonge_username": null,
+                "challonge_email_address_verified": null,
+                "removable": true,
+                "participatable_or_invitation_attached": false,
+                "confirm_remove": true,
+                "invitation_pending": false,
+                "display_name_with_invitation_email_address": name,
+                "email_hash": null,
+                "username": null,
+                "attached_participatable_portrait_url": null,
+                "can_check_in": false,
+                "checked_in": checked_in,
+                "reactivatable": false
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_args_parse() {
+        let a = args(&["add", "--json", "10", "Alice", "--seed", "3"]).unwrap();
+        assert!(a.json);
+        assert_eq!(a.positional, vec!["add", "10", "Alice"]);
+        assert_eq!(a.option("seed"), Some("3"));
+        assert_eq!(a.option("email"), None);
+        assert_eq!(a.arg(2, "name"), Ok("Alice"));
+        assert!(a
+            .arg(3, "other")
+            .unwrap_err()
+            .starts_with("missing <other>"));
+        assert_eq!(
+            a.participant(),
+            Err("invalid participant id \"Alice\"".to_owned())
+        );
+
+        let a = args(&["check-in", "10", "--help"]).unwrap();
+        assert_eq!(a.positional, vec!["help", "check-in", "10"]);
+
+        assert_eq!(
+            args(&["tournaments", "--state"]).err(),
+            Some("missing value of --state".to_owned())
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_tournament_query() {
+        let q = TournamentQuery::parse(&args(&["tournaments"]).unwrap()).unwrap();
+        assert_eq!(q.state.to_string(), "all");
+        assert_eq!(q.tournament_type, None);
+        assert_eq!(q.created_after, Local.ymd(2000, 1, 1));
+        assert_eq!(q.created_before, Local::today().succ());
+        assert_eq!(q.subdomain, "");
+
+        let q = TournamentQuery::parse(
+            &args(&[
+                "tournaments",
+                "--type",
+                "swiss",
+                "--state",
+                "in_progress",
+                "--created-after",
+                "2020-02-29",
+                "--subdomain",
+                "club",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(q.state.to_string(), "in_progress");
+        assert_eq!(q.tournament_type, Some(TournamentType::Swiss));
+        assert_eq!(q.created_after, Local.ymd(2020, 2, 29));
+        assert_eq!(q.subdomain, "club");
+
+        let err = |line: &[&str]| TournamentQuery::parse(&args(line).unwrap()).unwrap_err();
+        assert_eq!(
+            err(&["tournaments", "--type", "ladder"]),
+            "invalid tournament type \"ladder\""
+        );
+        assert_eq!(
+            err(&["tournaments", "--state", "open"]),
+            "invalid state \"open\""
+        );
+        assert_eq!(
+            err(&["tournaments", "--created-before", "2020-02-30"]),
+            "invalid date \"2020-02-30\", expected YYYY-MM-DD"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let out = table(
+            &["ID", "NAME"],
+            vec![
+                vec!["1".to_owned(), "Alice".to_owned()],
+                vec!["100".to_owned(), "".to_owned()],
+            ],
+        );
+        assert_eq!(out, "ID   NAME\n1    Alice\n100\n");
+    }
+
+    #[test]
+    fn test_done() {
+        assert_eq!(done("start", "10", false), "start: 10\n");
+        let v: Value = serde_json::from_str(&done("start", "10", true)).unwrap();
+        assert_eq!(v, json!({ "ok": true, "action": "start", "id": "10" }));
+    }
+
+    #[test]
+    fn test_participants_output() {
+        let participants = vec![
+            participant(2, "Bob", 2, false),
+            participant(1, "Alice", 1, true),
+        ];
+        assert_eq!(
+            participants_output(&participants, false),
+            "ID  SEED  NAME   USERNAME  CHECKED IN  RANK\n\
+             1   1     Alice            yes\n\
+             2   2     Bob              no\n"
+        );
+        let v: Value = serde_json::from_str(&participants_output(&participants, true)).unwrap();
+        assert_eq!(v[0]["name"], "Alice");
+        assert_eq!(v[1]["id"], 2);
+        assert_eq!(v[1]["checked_in"], false);
+    }
+
+    #[test]
+    fn test_api_error_display() {
+        let e = challonge::error::Error::Api(
+            "The plan was made for a different number of participants",
+        );
+        assert_eq!(
+            e.to_string(),
+            "The plan was made for a different number of participants"
+        );
+    }
+}
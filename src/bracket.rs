@@ -0,0 +1,434 @@
+//! Bracket graph built from the tournament's matches.
+//!
+//! Challonge describes the bracket by the matches' rounds and the prerequisite match
+//! of each player slot. `Bracket` interprets them offline: it tells where the winner
+//! and the loser of a match go, which matches can be played and the path of a participant.
+
+use std::collections::BTreeMap;
+
+use crate::matches::{Index, Match, MatchId, MatchState, Player};
+use crate::participants::ParticipantId;
+
+/// A side of the bracket a match belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Winners bracket (or the only bracket of single elimination, round robin and swiss).
+    Winners,
+
+    /// Losers bracket of double elimination, its matches have negative rounds.
+    Losers,
+
+    /// Grand finals between winners and losers bracket finalists, including the reset match.
+    GrandFinals,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    m: Match,
+    side: Side,
+    depth: u64,
+    winner_to: Option<MatchId>,
+    loser_to: Option<MatchId>,
+}
+
+/// A graph of the tournament's matches linked by their prerequisites.
+#[derive(Debug, Clone)]
+pub struct Bracket {
+    nodes: BTreeMap<MatchId, Node>,
+}
+impl Bracket {
+    /// Builds the bracket from the tournament's match list.
+    pub fn new(index: &Index) -> Bracket {
+        let mut nodes: BTreeMap<MatchId, Node> = index
+            .0
+            .iter()
+            .map(|m| {
+                let node = Node {
+                    m: m.clone(),
+                    side: if m.round < 0 {
+                        Side::Losers
+                    } else {
+                        Side::Winners
+                    },
+                    depth: 0,
+                    winner_to: None,
+                    loser_to: None,
+                };
+                (m.id, node)
+            })
+            .collect();
+
+        let links: Vec<(MatchId, MatchId, bool)> = nodes
+            .values()
+            .flat_map(|n| {
+                [&n.m.player1, &n.m.player2]
+                    .iter()
+                    .filter_map(|p| {
+                        p.prereq_match_id
+                            .map(|pre| (pre, n.m.id, p.is_prereq_match_loser))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        for (from, to, loser) in links {
+            if let Some(node) = nodes.get_mut(&from) {
+                if loser {
+                    node.loser_to = Some(to);
+                } else {
+                    node.winner_to = Some(to);
+                }
+            }
+        }
+
+        // Prerequisites always precede the match, so a match's depth and side are
+        // known once all of its prerequisites are processed.
+        let mut order: Vec<MatchId> = Vec::new();
+        let mut pending: Vec<MatchId> = nodes.keys().cloned().collect();
+        while !pending.is_empty() {
+            let before = pending.len();
+            pending.retain(|id| {
+                let ready = prereqs(&nodes[id].m)
+                    .iter()
+                    .all(|pre| !nodes.contains_key(pre) || order.contains(pre));
+                if ready {
+                    order.push(*id);
+                }
+                !ready
+            });
+            if pending.len() == before {
+                // A cycle can't come from Challonge, keep the remaining matches as they are.
+                break;
+            }
+        }
+        for id in order {
+            let (depth, grand_finals) = prereqs(&nodes[&id].m)
+                .iter()
+                .filter_map(|pre| nodes.get(pre))
+                .fold((0, false), |(depth, gf), pre| {
+                    (depth.max(pre.depth + 1), gf || pre.side != Side::Winners)
+                });
+            let node = nodes.get_mut(&id).unwrap();
+            node.depth = depth;
+            if node.side == Side::Winners && grand_finals {
+                node.side = Side::GrandFinals;
+            }
+        }
+
+        Bracket { nodes }
+    }
+
+    /// Returns the match with the given id.
+    pub fn get(&self, id: MatchId) -> Option<&Match> {
+        self.nodes.get(&id).map(|n| &n.m)
+    }
+
+    /// Returns all the matches of the bracket ordered by their ids.
+    pub fn matches(&self) -> impl Iterator<Item = &Match> {
+        self.nodes.values().map(|n| &n.m)
+    }
+
+    /// Returns the matches of the given side of the bracket.
+    pub fn side_matches(&self, side: Side) -> Vec<&Match> {
+        self.nodes
+            .values()
+            .filter(|n| n.side == side)
+            .map(|n| &n.m)
+            .collect()
+    }
+
+    /// Returns the side of the bracket the match belongs to.
+    pub fn side(&self, id: MatchId) -> Option<Side> {
+        self.nodes.get(&id).map(|n| n.side)
+    }
+
+    /// Returns the length of the longest chain of prerequisite matches before the match.
+    /// First round matches have zero depth.
+    pub fn depth(&self, id: MatchId) -> Option<u64> {
+        self.nodes.get(&id).map(|n| n.depth)
+    }
+
+    /// Returns the matches which must be completed before the match.
+    pub fn prerequisites(&self, id: MatchId) -> Vec<&Match> {
+        self.nodes
+            .get(&id)
+            .map(|n| {
+                prereqs(&n.m)
+                    .iter()
+                    .filter_map(|pre| self.get(*pre))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the match the winner of the match goes to.
+    pub fn winner_goes_to(&self, id: MatchId) -> Option<&Match> {
+        self.nodes
+            .get(&id)
+            .and_then(|n| n.winner_to)
+            .and_then(|to| self.get(to))
+    }
+
+    /// Returns the match the loser of the match goes to.
+    /// There is none in single elimination (except the third place match) and for losers bracket matches.
+    pub fn loser_goes_to(&self, id: MatchId) -> Option<&Match> {
+        self.nodes
+            .get(&id)
+            .and_then(|n| n.loser_to)
+            .and_then(|to| self.get(to))
+    }
+
+    /// Returns the participant who plays (or will play) in the player slot of a match.
+    /// If the slot is not filled yet, the participant is taken from the completed prerequisite match.
+    pub fn player(&self, player: &Player) -> Option<ParticipantId> {
+        if player.id.is_some() {
+            return player.id;
+        }
+        let pre = self.get(player.prereq_match_id?)?;
+        if pre.state != MatchState::Complete {
+            return None;
+        }
+        if player.is_prereq_match_loser {
+            pre.loser_id
+        } else {
+            pre.winner_id
+        }
+    }
+
+    /// Returns the matches which are not completed and whose both players are known,
+    /// in the order Challonge suggests to play them.
+    pub fn playable(&self) -> Vec<&Match> {
+        let mut playable: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|n| {
+                n.m.state != MatchState::Complete
+                    && self.player(&n.m.player1).is_some()
+                    && self.player(&n.m.player2).is_some()
+            })
+            .collect();
+        playable.sort_by_key(|n| (n.m.suggested_play_order, n.depth, n.m.id));
+        playable.into_iter().map(|n| &n.m).collect()
+    }
+
+    /// Returns the matches the participant has played and is known to play, in the order of play.
+    pub fn path(&self, participant: ParticipantId) -> Vec<&Match> {
+        let mut path: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|n| {
+                self.player(&n.m.player1) == Some(participant)
+                    || self.player(&n.m.player2) == Some(participant)
+            })
+            .collect();
+        path.sort_by_key(|n| (n.depth, n.m.suggested_play_order, n.m.id));
+        path.into_iter().map(|n| &n.m).collect()
+    }
+}
+
+fn prereqs(m: &Match) -> Vec<MatchId> {
+    let mut ids: Vec<MatchId> = [&m.player1, &m.player2]
+        .iter()
+        .filter_map(|p| p.prereq_match_id)
+        .collect();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::bracket::{Bracket, Side};
+    use crate::matches::{Index, Match, MatchId};
+    use crate::participants::ParticipantId;
+
+    /// Player slot: participant id and prerequisite match (id, is loser).
+    pub type Slot = (Option<u64>, Option<(u64, bool)>);
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_match(
+        id: u64,
+        identifier: &str,
+        round: i64,
+        player1: Slot,
+        player2: Slot,
+        state: &str,
+        winner: Option<u64>,
+        scores: &str,
+    ) -> Match {
+        let loser = winner.and_then(|w| {
+            if player1.0 == Some(w) {
+                player2.0
+            } else {
+                player1.0
+            }
+        });
+        let prereqs: Vec<String> = [player1.1, player2.1]
+            .iter()
+            .filter_map(|p| p.map(|(id, _)| id.to_string()))
+            .collect();
+        let json = serde_json::json!({
+            "match": {
+                "attachment_count": null,
+                "created_at": "2015-01-19T16:57:17-05:00",
+                "group_id": null,
+                "has_attachment": false,
+                "id": id,
+                "identifier": identifier,
+                "location": null,
+                "loser_id": loser,
+                "player1_id": player1.0,
+                "player1_is_prereq_match_loser": player1.1.map(|p| p.1).unwrap_or(false),
+                "player1_prereq_match_id": player1.1.map(|p| p.0),
+                "player1_votes": null,
+                "player2_id": player2.0,
+                "player2_is_prereq_match_loser": player2.1.map(|p| p.1).unwrap_or(false),
+                "player2_prereq_match_id": player2.1.map(|p| p.0),
+                "player2_votes": null,
+                "round": round,
+                "scheduled_time": null,
+                "started_at": null,
+                "state": state,
+                "suggested_play_order": id,
+                "tournament_id": 1086875,
+                "underway_at": null,
+                "updated_at": "2015-01-19T16:57:17-05:00",
+                "winner_id": winner,
+                "prerequisite_match_ids_csv": prereqs.join(","),
+                "scores_csv": scores
+            }
+        });
+        Match::decode(json).unwrap()
+    }
+
+    /// Double elimination bracket of 4 participants (ids 1-4 by seed):
+    /// A and B are completed, winners final C and losers round 1 D are open.
+    pub fn double_elimination() -> Index {
+        Index(vec![
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(4), None),
+                "complete",
+                Some(1),
+                "2-0",
+            ),
+            make_match(
+                2,
+                "B",
+                1,
+                (Some(2), None),
+                (Some(3), None),
+                "complete",
+                Some(3),
+                "1-2",
+            ),
+            make_match(
+                3,
+                "C",
+                2,
+                (Some(1), Some((1, false))),
+                (Some(3), Some((2, false))),
+                "open",
+                None,
+                "",
+            ),
+            make_match(
+                4,
+                "D",
+                -1,
+                (Some(4), Some((1, true))),
+                (Some(2), Some((2, true))),
+                "open",
+                None,
+                "",
+            ),
+            make_match(
+                5,
+                "E",
+                -2,
+                (None, Some((3, true))),
+                (None, Some((4, false))),
+                "pending",
+                None,
+                "",
+            ),
+            make_match(
+                6,
+                "F",
+                3,
+                (None, Some((3, false))),
+                (None, Some((5, false))),
+                "pending",
+                None,
+                "",
+            ),
+            make_match(
+                7,
+                "G",
+                3,
+                (None, Some((6, false))),
+                (None, Some((6, true))),
+                "pending",
+                None,
+                "",
+            ),
+        ])
+    }
+
+    fn ids(matches: Vec<&Match>) -> Vec<u64> {
+        matches.iter().map(|m| m.id.0).collect()
+    }
+
+    #[test]
+    fn test_bracket_sides() {
+        let b = Bracket::new(&double_elimination());
+        assert_eq!(ids(b.side_matches(Side::Winners)), vec![1, 2, 3]);
+        assert_eq!(ids(b.side_matches(Side::Losers)), vec![4, 5]);
+        assert_eq!(ids(b.side_matches(Side::GrandFinals)), vec![6, 7]);
+        assert_eq!(b.depth(MatchId(1)), Some(0));
+        assert_eq!(b.depth(MatchId(5)), Some(2));
+        assert_eq!(b.depth(MatchId(7)), Some(4));
+        assert_eq!(ids(b.prerequisites(MatchId(6))), vec![3, 5]);
+    }
+
+    #[test]
+    fn test_bracket_progression() {
+        let b = Bracket::new(&double_elimination());
+        assert_eq!(b.winner_goes_to(MatchId(1)).map(|m| m.id.0), Some(3));
+        assert_eq!(b.loser_goes_to(MatchId(1)).map(|m| m.id.0), Some(4));
+        assert_eq!(b.winner_goes_to(MatchId(3)).map(|m| m.id.0), Some(6));
+        assert_eq!(b.loser_goes_to(MatchId(3)).map(|m| m.id.0), Some(5));
+        assert_eq!(b.winner_goes_to(MatchId(5)).map(|m| m.id.0), Some(6));
+        assert!(b.loser_goes_to(MatchId(5)).is_none());
+        assert_eq!(b.winner_goes_to(MatchId(6)).map(|m| m.id.0), Some(7));
+        assert!(b.winner_goes_to(MatchId(7)).is_none());
+    }
+
+    #[test]
+    fn test_bracket_playable_and_path() {
+        let mut index = double_elimination();
+        assert_eq!(ids(Bracket::new(&index).playable()), vec![3, 4]);
+
+        // Report the winners final locally: the losers final gets its first player.
+        index.0[2] = make_match(
+            3,
+            "C",
+            2,
+            (Some(1), Some((1, false))),
+            (Some(3), Some((2, false))),
+            "complete",
+            Some(1),
+            "2-1",
+        );
+        let b = Bracket::new(&index);
+        assert_eq!(ids(b.playable()), vec![4]);
+        assert_eq!(
+            b.player(&b.get(MatchId(5)).unwrap().player1),
+            Some(ParticipantId(3))
+        );
+        assert_eq!(ids(b.path(ParticipantId(3))), vec![2, 3, 5]);
+        assert_eq!(ids(b.path(ParticipantId(1))), vec![1, 3, 6]);
+        assert_eq!(ids(b.path(ParticipantId(4))), vec![1, 4]);
+    }
+}
@@ -0,0 +1,379 @@
+//! Participant check-in.
+//!
+//! The check-in window of a tournament opens `check_in_duration` minutes before its
+//! `start_at` and closes at `start_at`. When the check-in is processed, participants who
+//! haven't checked in are deactivated and checked in participants on the waiting list are
+//! promoted to the freed slots. `CheckIn` tells beforehand who is missing and who would be
+//! promoted, `AutoStart` closes the check-in and starts the tournament, reporting what
+//! happened.
+
+use std::fmt;
+use std::thread;
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+
+use crate::error::Error;
+use crate::lifecycle::{Action, Lifecycle};
+use crate::matches::Index as MatchIndex;
+use crate::participants::{Index as ParticipantIndex, Participant, ParticipantId};
+use crate::tournament::{Tournament, TournamentId, TournamentIncludes, TournamentStatus};
+use crate::Challonge;
+
+/// The check-in window of a tournament.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Window {
+    /// When the check-in opens
+    pub opens_at: DateTime<FixedOffset>,
+    /// When the check-in closes (the planned start of the tournament)
+    pub closes_at: DateTime<FixedOffset>,
+}
+impl Window {
+    /// The window of the tournament, if it has a start time and a check-in duration.
+    pub fn of(tournament: &Tournament) -> Option<Window> {
+        let closes_at = tournament.start_at?;
+        let duration = tournament.check_in_duration?;
+        Some(Window {
+            opens_at: closes_at - Duration::minutes(duration as i64),
+            closes_at,
+        })
+    }
+
+    /// Returns whether the window is open at the time.
+    pub fn is_open(&self, now: DateTime<FixedOffset>) -> bool {
+        self.opens_at <= now && now < self.closes_at
+    }
+}
+
+/// The check-in status of a tournament.
+#[derive(Debug, Clone)]
+pub struct CheckIn {
+    /// The check-in window, if known
+    pub window: Option<Window>,
+    /// Active participants who checked in
+    pub checked_in: Vec<Participant>,
+    /// Active participants who haven't checked in, they will be deactivated
+    pub missing: Vec<Participant>,
+    /// Checked in participants on the waiting list who will be promoted
+    pub promoted: Vec<Participant>,
+    /// Participants who will stay on the waiting list
+    pub waiting: Vec<Participant>,
+}
+impl CheckIn {
+    /// Computes the check-in status of the tournament with its participants.
+    ///
+    /// The waiting list is promoted by seed up to `signup_cap`, or to fill the slots of the
+    /// missing participants when the tournament has no cap.
+    pub fn new(tournament: &Tournament, participants: &ParticipantIndex) -> CheckIn {
+        let mut sorted: Vec<&Participant> = participants.0.iter().collect();
+        sorted.sort_by_key(|p| (p.seed, p.id));
+
+        let (mut checked_in, mut missing, mut candidates, mut waiting) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for p in sorted {
+            if p.on_waiting_list {
+                if p.checked_in {
+                    candidates.push(p.clone());
+                } else {
+                    waiting.push(p.clone());
+                }
+            } else if p.active {
+                if p.checked_in {
+                    checked_in.push(p.clone());
+                } else {
+                    missing.push(p.clone());
+                }
+            }
+        }
+
+        let slots = match tournament.signup_cap {
+            Some(cap) => (cap as usize).saturating_sub(checked_in.len()),
+            None => missing.len(),
+        };
+        let promoted: Vec<Participant> = candidates.drain(..slots.min(candidates.len())).collect();
+        candidates.extend(waiting);
+        candidates.sort_by_key(|p| (p.seed, p.id));
+
+        CheckIn {
+            window: Window::of(tournament),
+            checked_in,
+            missing,
+            promoted,
+            waiting: candidates,
+        }
+    }
+}
+
+/// What `AutoStart` did.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The check-in status before it was closed
+    pub before: CheckIn,
+    /// Participants deactivated for not checking in
+    pub deactivated: Vec<Participant>,
+    /// Participants promoted from the waiting list
+    pub promoted: Vec<Participant>,
+    /// Whether the tournament was started
+    pub started: bool,
+    /// The state of the tournament at the end
+    pub state: TournamentStatus,
+}
+impl fmt::Display for Report {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let names = |ps: &[Participant]| {
+            ps.iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(fmt, "checked in: {}", self.before.checked_in.len())?;
+        writeln!(
+            fmt,
+            "deactivated: {} {}",
+            self.deactivated.len(),
+            names(&self.deactivated)
+        )?;
+        writeln!(
+            fmt,
+            "promoted: {} {}",
+            self.promoted.len(),
+            names(&self.promoted)
+        )?;
+        writeln!(
+            fmt,
+            "started: {}, state: {}",
+            if self.started { "yes" } else { "no" },
+            self.state
+        )
+    }
+}
+
+/// Closes the check-in of a tournament and starts it.
+#[derive(Debug, Clone)]
+pub struct AutoStart {
+    id: TournamentId,
+    wait: bool,
+    start: bool,
+}
+impl AutoStart {
+    /// Creates the sequence for the tournament.
+    pub fn new(id: TournamentId) -> AutoStart {
+        AutoStart {
+            id,
+            wait: false,
+            start: true,
+        }
+    }
+
+    /// Waits for the check-in window to close before processing the check-ins.
+    pub fn wait(&mut self, wait: bool) -> &mut Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Starts the tournament after processing the check-ins (the default).
+    pub fn start(&mut self, start: bool) -> &mut Self {
+        self.start = start;
+        self
+    }
+
+    /// Processes the check-ins (if the tournament is checking in) and starts the
+    /// tournament. Both actions are checked with `lifecycle::Lifecycle` first.
+    ///
+    /// When the tournament can't be started after the check-ins were processed, the report
+    /// of the check-ins is returned with `started` unset.
+    pub fn run(&self, c: &Challonge) -> Result<Report, Error> {
+        let includes = TournamentIncludes::Participants;
+        let mut tournament = c.get_tournament(&self.id, &includes)?;
+        if self.wait {
+            if let Some(window) = Window::of(&tournament) {
+                let now: DateTime<FixedOffset> = Utc::now().into();
+                if let Ok(left) = (window.closes_at - now).to_std() {
+                    thread::sleep(left);
+                }
+                tournament = c.get_tournament(&self.id, &includes)?;
+            }
+        }
+        let participants = included_participants(&tournament)?;
+        let before = CheckIn::new(&tournament, participants);
+        let matches = MatchIndex(Vec::new());
+
+        let processed = if tournament.state == TournamentStatus::CheckingIn {
+            Lifecycle::new(&tournament, participants, &matches)
+                .check(Action::ProcessCheckins)
+                .map_err(Error::Refused)?;
+            c.tournament_process_checkins(&self.id, &includes)?;
+            Some(c.get_tournament(&self.id, &includes)?)
+        } else {
+            None
+        };
+        let mut current = processed.as_ref().unwrap_or(&tournament);
+        let started;
+        if self.start {
+            let lifecycle = Lifecycle::new(current, included_participants(current)?, &matches);
+            match lifecycle.check(Action::Start) {
+                Ok(_) => {
+                    c.tournament_start(&self.id, &includes)?;
+                    started = c.get_tournament(&self.id, &includes)?;
+                    current = &started;
+                }
+                Err(refusal) if processed.is_some() => log::warn!("{}", refusal),
+                Err(refusal) => return Err(Error::Refused(refusal)),
+            }
+        }
+        Report::new(before, participants, &tournament.state, current)
+    }
+}
+
+impl Report {
+    /// Compares the participants before and after the check-ins were processed.
+    fn new(
+        before: CheckIn,
+        participants: &ParticipantIndex,
+        state_before: &TournamentStatus,
+        after: &Tournament,
+    ) -> Result<Report, Error> {
+        let after_participants = included_participants(after)?;
+        let find = |id: ParticipantId| after_participants.0.iter().find(|p| p.id == id);
+        let deactivated = before
+            .missing
+            .iter()
+            .filter(|p| matches!(find(p.id), Some(a) if !a.active))
+            .cloned()
+            .collect();
+        let promoted = participants
+            .0
+            .iter()
+            .filter(|p| p.on_waiting_list)
+            .filter(|p| matches!(find(p.id), Some(a) if !a.on_waiting_list))
+            .cloned()
+            .collect();
+        Ok(Report {
+            before,
+            deactivated,
+            promoted,
+            started: !is_underway(state_before) && is_underway(&after.state),
+            state: after.state.clone(),
+        })
+    }
+}
+
+fn is_underway(state: &TournamentStatus) -> bool {
+    matches!(
+        state,
+        TournamentStatus::Underway | TournamentStatus::GroupStagesUnderway
+    )
+}
+
+/// The participants fetched with the tournament.
+fn included_participants(tournament: &Tournament) -> Result<&ParticipantIndex, Error> {
+    tournament.participants.as_ref().ok_or(Error::Api(
+        "The participants weren't included with the tournament",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkin::{CheckIn, Report, Window};
+    use crate::participants::tests::make_participant;
+    use crate::participants::{Index as ParticipantIndex, Participant};
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::TournamentStatus;
+    use chrono::{DateTime, Duration};
+
+    fn names(ps: &[Participant]) -> Vec<&str> {
+        ps.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_window() {
+        let mut t = sample_tournament();
+        assert_eq!(Window::of(&t), None);
+        let start = DateTime::parse_from_rfc3339("2015-01-20T18:00:00-05:00").unwrap();
+        t.start_at = Some(start);
+        t.check_in_duration = Some(30);
+        let w = Window::of(&t).unwrap();
+        assert_eq!(w.opens_at.to_rfc3339(), "2015-01-20T17:30:00-05:00");
+        assert!(!w.is_open(start - Duration::minutes(31)));
+        assert!(w.is_open(start - Duration::minutes(30)));
+        assert!(!w.is_open(start));
+    }
+
+    #[test]
+    fn test_check_in_status() {
+        let mut t = sample_tournament();
+        let mut participants = vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+            make_participant(4, "Dave", 4),
+            make_participant(5, "Eve", 5),
+            make_participant(6, "Frank", 6),
+        ];
+        participants[0].checked_in = true;
+        participants[2].checked_in = true;
+        for p in &mut participants[3..] {
+            p.on_waiting_list = true;
+        }
+        participants[4].checked_in = true;
+        participants[5].checked_in = true;
+        let participants = ParticipantIndex(participants);
+
+        // Bob doesn't show up, one slot is freed.
+        let status = CheckIn::new(&t, &participants);
+        assert_eq!(names(&status.checked_in), vec!["Alice", "Carol"]);
+        assert_eq!(names(&status.missing), vec!["Bob"]);
+        assert_eq!(names(&status.promoted), vec!["Eve"]);
+        assert_eq!(names(&status.waiting), vec!["Dave", "Frank"]);
+
+        t.signup_cap = Some(4);
+        let status = CheckIn::new(&t, &participants);
+        assert_eq!(names(&status.promoted), vec!["Eve", "Frank"]);
+        assert_eq!(names(&status.waiting), vec!["Dave"]);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut t = sample_tournament();
+        t.state = TournamentStatus::CheckingIn;
+        let mut participants = vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+        ];
+        participants[0].checked_in = true;
+        participants[2].checked_in = true;
+        participants[2].on_waiting_list = true;
+        let participants = ParticipantIndex(participants);
+        let before = CheckIn::new(&t, &participants);
+
+        // Processed, but the tournament couldn't be started.
+        let mut after = participants.clone();
+        after.0[1].active = false;
+        after.0[2].on_waiting_list = false;
+        t.state = TournamentStatus::CheckedIn;
+        t.participants = Some(after);
+        let report = Report::new(
+            before.clone(),
+            &participants,
+            &TournamentStatus::CheckingIn,
+            &t,
+        )
+        .unwrap();
+        assert_eq!(names(&report.deactivated), vec!["Bob"]);
+        assert_eq!(names(&report.promoted), vec!["Carol"]);
+        assert!(!report.started);
+        assert_eq!(report.state, TournamentStatus::CheckedIn);
+
+        t.state = TournamentStatus::Underway;
+        let report = Report::new(
+            before.clone(),
+            &participants,
+            &TournamentStatus::CheckingIn,
+            &t,
+        )
+        .unwrap();
+        assert!(report.started);
+        let report = Report::new(before, &participants, &TournamentStatus::Underway, &t).unwrap();
+        assert!(!report.started);
+    }
+}
@@ -1,9 +1,15 @@
 //! Challonge REST API error type.
 
+use std::fmt;
+
 use serde_json::Error as JsonError;
 
 /// Challonge REST API error type.
+///
+/// Some variants only exist with the features wrapping other crates, so matches on it need
+/// a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// A `hyper` crate error
     Reqwest(reqwest::Error),
@@ -16,6 +22,58 @@ pub enum Error {
 
     /// Challonge-rs error.
     Api(&'static str),
+
+    /// An I/O error
+    Io(std::io::Error),
+
+    /// A tournament action was refused by `lifecycle::Checked`
+    Refused(crate::lifecycle::Refusal),
+
+    /// A `rusqlite` crate error
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+
+    /// A `toml` crate decoding error
+    #[cfg(feature = "toml-templates")]
+    TomlDecode(toml::de::Error),
+
+    /// A `toml` crate encoding error
+    #[cfg(feature = "toml-templates")]
+    TomlEncode(toml::ser::Error),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Reqwest(e) => write!(fmt, "request failed: {}", e),
+            Error::Json(e) => write!(fmt, "invalid json: {}", e),
+            Error::Decode(what, value) => write!(fmt, "{}: {}", what, value),
+            Error::Api(what) => fmt.write_str(what),
+            Error::Io(e) => write!(fmt, "I/O error: {}", e),
+            Error::Refused(refusal) => refusal.fmt(fmt),
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => write!(fmt, "sqlite error: {}", e),
+            #[cfg(feature = "toml-templates")]
+            Error::TomlDecode(e) => write!(fmt, "invalid toml: {}", e),
+            #[cfg(feature = "toml-templates")]
+            Error::TomlEncode(e) => write!(fmt, "toml encoding failed: {}", e),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Reqwest(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => Some(e),
+            #[cfg(feature = "toml-templates")]
+            Error::TomlDecode(e) => Some(e),
+            #[cfg(feature = "toml-templates")]
+            Error::TomlEncode(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Error {
@@ -27,3 +85,26 @@ impl From<JsonError> for Error {
         Error::Json(err)
     }
 }
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Error {
+        Error::Sqlite(err)
+    }
+}
+#[cfg(feature = "toml-templates")]
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Error {
+        Error::TomlDecode(err)
+    }
+}
+#[cfg(feature = "toml-templates")]
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Error {
+        Error::TomlEncode(err)
+    }
+}
@@ -0,0 +1,351 @@
+//! Export of tournament results to CSV and JSON.
+//!
+//! A `Report` collects the participants' final ranks, the results of the matches and a
+//! summary of the tournament. Columns always come in the same order (and JSON keys are
+//! sorted), so reports of the same tournament can be diffed.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::matches::{Index as MatchIndex, MatchState};
+use crate::participants::{Index as ParticipantIndex, ParticipantId};
+use crate::tournament::Tournament;
+
+/// Columns of the final ranks table.
+pub const RANK_COLUMNS: &[&str] = &[
+    "rank",
+    "seed",
+    "participant_id",
+    "name",
+    "challonge_username",
+    "misc",
+];
+
+/// Columns of the match results table.
+pub const MATCH_COLUMNS: &[&str] = &[
+    "match_id",
+    "identifier",
+    "round",
+    "state",
+    "player1_id",
+    "player1",
+    "player2_id",
+    "player2",
+    "scores",
+    "winner_id",
+    "winner",
+    "completed_at",
+];
+
+/// Fields of the summary.
+pub const SUMMARY_FIELDS: &[&str] = &[
+    "tournament_id",
+    "name",
+    "url",
+    "tournament_type",
+    "game_name",
+    "started_at",
+    "participants",
+    "matches",
+    "completed_matches",
+    "champion",
+];
+
+type Row = Map<String, Value>;
+
+/// Tournament results ready to be written as CSV or JSON.
+#[derive(Debug, Clone)]
+pub struct Report {
+    summary: Row,
+    ranks: Vec<Row>,
+    matches: Vec<Row>,
+}
+impl Report {
+    /// Collects the results of the tournament.
+    ///
+    /// Participants are listed by `final_rank` (known once the tournament is finalized),
+    /// unranked ones last, and by seed within the same rank. Matches are listed by id.
+    pub fn new(
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+    ) -> Report {
+        let names: HashMap<ParticipantId, &str> = participants
+            .0
+            .iter()
+            .map(|p| (p.id, p.name.as_str()))
+            .collect();
+        let name = |id: Option<ParticipantId>| -> Value {
+            id.and_then(|id| names.get(&id))
+                .map_or(Value::Null, |n| Value::from(*n))
+        };
+        let id = |id: Option<ParticipantId>| -> Value {
+            id.filter(|id| id.0 != 0)
+                .map_or(Value::Null, |id| id.0.into())
+        };
+
+        let mut ranked: Vec<_> = participants.0.iter().collect();
+        ranked.sort_by_key(|p| (p.final_rank.is_none(), p.final_rank, p.seed));
+        let ranks = ranked
+            .iter()
+            .map(|p| {
+                row(
+                    RANK_COLUMNS,
+                    vec![
+                        p.final_rank.into(),
+                        p.seed.into(),
+                        p.id.0.into(),
+                        p.name.clone().into(),
+                        non_empty(&p.challonge_username),
+                        non_empty(&p.misc),
+                    ],
+                )
+            })
+            .collect();
+
+        let mut sorted: Vec<_> = matches.0.iter().collect();
+        sorted.sort_by_key(|m| m.id);
+        let match_rows = sorted
+            .iter()
+            .map(|m| {
+                row(
+                    MATCH_COLUMNS,
+                    vec![
+                        m.id.0.into(),
+                        m.identifier.clone().into(),
+                        m.round.into(),
+                        m.state.to_string().into(),
+                        id(m.player1.id),
+                        name(m.player1.id),
+                        id(m.player2.id),
+                        name(m.player2.id),
+                        m.scores_csv.to_string().into(),
+                        id(m.winner_id),
+                        name(m.winner_id),
+                        m.completed_at
+                            .map_or(Value::Null, |t| t.to_rfc3339().into()),
+                    ],
+                )
+            })
+            .collect();
+
+        let champion = participants
+            .0
+            .iter()
+            .filter(|p| p.final_rank == Some(1))
+            .min_by_key(|p| p.seed)
+            .map_or(Value::Null, |p| p.name.clone().into());
+        let summary = row(
+            SUMMARY_FIELDS,
+            vec![
+                tournament.id.to_string().into(),
+                tournament.name.clone().into(),
+                tournament.full_challonge_url.clone().into(),
+                tournament.tournament_type.to_get_param().into(),
+                non_empty(&tournament.game_name),
+                tournament
+                    .started_at
+                    .map_or(Value::Null, |t| t.to_rfc3339().into()),
+                participants
+                    .0
+                    .iter()
+                    .filter(|p| p.active && !p.on_waiting_list)
+                    .count()
+                    .into(),
+                matches.0.len().into(),
+                matches
+                    .0
+                    .iter()
+                    .filter(|m| m.state == MatchState::Complete)
+                    .count()
+                    .into(),
+                champion,
+            ],
+        );
+
+        Report {
+            summary,
+            ranks,
+            matches: match_rows,
+        }
+    }
+
+    /// Writes the final ranks as CSV with a header row of `RANK_COLUMNS`.
+    pub fn ranks_csv(&self) -> String {
+        table_csv(RANK_COLUMNS, &self.ranks)
+    }
+
+    /// Writes the match results as CSV with a header row of `MATCH_COLUMNS`.
+    pub fn matches_csv(&self) -> String {
+        table_csv(MATCH_COLUMNS, &self.matches)
+    }
+
+    /// Writes the summary as CSV of `field,value` rows.
+    pub fn summary_csv(&self) -> String {
+        let mut out = String::from("field,value\n");
+        for field in SUMMARY_FIELDS {
+            out.push_str(&csv_line(&[
+                (*field).to_owned(),
+                cell(&self.summary[*field]),
+            ]));
+        }
+        out
+    }
+
+    /// Writes the whole report as pretty JSON: an object with `summary`, `ranks`
+    /// and `matches` keys.
+    pub fn to_json(&self) -> String {
+        let mut report = Map::new();
+        report.insert("summary".to_owned(), Value::Object(self.summary.clone()));
+        report.insert(
+            "ranks".to_owned(),
+            Value::Array(self.ranks.iter().cloned().map(Value::Object).collect()),
+        );
+        report.insert(
+            "matches".to_owned(),
+            Value::Array(self.matches.iter().cloned().map(Value::Object).collect()),
+        );
+        // Serializing a `Value` can't fail.
+        serde_json::to_string_pretty(&Value::Object(report)).unwrap()
+    }
+}
+
+fn row(columns: &[&str], values: Vec<Value>) -> Row {
+    columns
+        .iter()
+        .map(|c| (*c).to_owned())
+        .zip(values)
+        .collect()
+}
+
+fn non_empty(s: &str) -> Value {
+    if s.is_empty() {
+        Value::Null
+    } else {
+        Value::from(s)
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match *value {
+        Value::Null => String::new(),
+        Value::String(ref s) => s.clone(),
+        ref v => v.to_string(),
+    }
+}
+
+fn csv_line(cells: &[String]) -> String {
+    let escaped: Vec<String> = cells
+        .iter()
+        .map(|c| {
+            if c.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", c.replace('"', "\"\""))
+            } else {
+                c.clone()
+            }
+        })
+        .collect();
+    escaped.join(",") + "\n"
+}
+
+fn table_csv(columns: &[&str], rows: &[Row]) -> String {
+    let header: Vec<String> = columns.iter().map(|c| (*c).to_owned()).collect();
+    let mut out = csv_line(&header);
+    for r in rows {
+        let cells: Vec<String> = columns.iter().map(|c| cell(&r[*c])).collect();
+        out.push_str(&csv_line(&cells));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::export::Report;
+    use crate::matches::Index as MatchIndex;
+    use crate::participants::tests::make_participant;
+    use crate::participants::Index as ParticipantIndex;
+    use crate::tournament::tests::sample_tournament;
+
+    fn report() -> Report {
+        let mut alice = make_participant(1, "Alice", 1);
+        let mut bob = make_participant(2, "Bob, Jr.", 2);
+        let carol = make_participant(3, "Carol", 3);
+        alice.final_rank = Some(2);
+        bob.final_rank = Some(1);
+        let matches = MatchIndex(vec![
+            make_match(
+                2,
+                "B",
+                2,
+                (Some(2), None),
+                (Some(1), None),
+                "complete",
+                Some(2),
+                "3-1,2-3,3-0",
+            ),
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(3), None),
+                "complete",
+                Some(1),
+                "2-0",
+            ),
+        ]);
+        Report::new(
+            &sample_tournament(),
+            &ParticipantIndex(vec![carol, alice, bob]),
+            &matches,
+        )
+    }
+
+    #[test]
+    fn test_ranks_csv() {
+        assert_eq!(
+            report().ranks_csv(),
+            concat!(
+                "rank,seed,participant_id,name,challonge_username,misc\n",
+                "1,2,2,\"Bob, Jr.\",,\n",
+                "2,1,1,Alice,,\n",
+                ",3,3,Carol,,\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_matches_csv() {
+        let csv = report().matches_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "match_id,identifier,round,state,player1_id,player1,player2_id,player2,scores,winner_id,winner,completed_at"
+        );
+        assert_eq!(lines[1], "1,A,1,complete,1,Alice,3,Carol,2-0,1,Alice,");
+        assert_eq!(
+            lines[2],
+            "2,B,2,complete,2,\"Bob, Jr.\",1,Alice,\"3-1,2-3,3-0\",2,\"Bob, Jr.\","
+        );
+    }
+
+    #[test]
+    fn test_summary_and_json() {
+        let r = report();
+        let summary = r.summary_csv();
+        assert!(
+            summary.starts_with("field,value\ntournament_id,1086875\nname,Sample Tournament 1\n")
+        );
+        assert!(summary.contains("\nmatches,2\ncompleted_matches,2\nchampion,\"Bob, Jr.\"\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&r.to_json()).unwrap();
+        assert_eq!(json["summary"]["champion"], "Bob, Jr.");
+        assert_eq!(json["summary"]["participants"], 3);
+        assert_eq!(json["ranks"][0]["participant_id"], 2);
+        assert_eq!(json["matches"][1]["scores"], "3-1,2-3,3-0");
+        assert!(json["matches"][0]["completed_at"].is_null());
+        assert_eq!(r.to_json(), report().to_json());
+    }
+}
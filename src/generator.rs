@@ -0,0 +1,613 @@
+//! Local bracket generator.
+//!
+//! Approximates the matches Challonge creates when a tournament is started, so a bracket can
+//! be previewed (or served by a fake server) before calling `tournament_start`. Matches get
+//! sequential ids starting from 1, identifiers, rounds and prerequisite links.
+//!
+//! Elimination brackets follow Challonge's seeding rules: seed 1 meets the lowest seed, byes
+//! go to the top seeds, and with `sequential_pairings` the participants are paired going
+//! straight down the list (the byes still going to the top seeds). Losers of the winners
+//! bracket drop into the losers bracket in alternating order to postpone rematches, and the
+//! third place match is played in the round of the final.
+//!
+//! The generated brackets have only been compared with hand-written brackets, not with
+//! exports of real Challonge tournaments. The losers bracket drop order, the rounds of the
+//! third place match and the identifiers in larger brackets may differ from Challonge's.
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::matches::{Index as MatchIndex, Match, MatchId, MatchScores, MatchState, Player};
+use crate::participants::{Index as ParticipantIndex, Participant, ParticipantId};
+use crate::swiss::Pairings;
+use crate::tournament::{TournamentCreate, TournamentId, TournamentType};
+
+/// Generates the matches of the tournament for the participants.
+///
+/// Only active participants which are not on the waiting list take part, ordered by their seeds.
+/// For Swiss tournaments only the first round can be generated.
+/// The matches are stamped with `created_at` (the time the tournament starts), so the same
+/// input always generates the same matches.
+pub fn generate(
+    participants: &[Participant],
+    tournament: &TournamentCreate,
+    created_at: DateTime<FixedOffset>,
+) -> MatchIndex {
+    let mut players: Vec<&Participant> = participants
+        .iter()
+        .filter(|p| p.active && !p.on_waiting_list)
+        .collect();
+    players.sort_by_key(|p| p.seed);
+    let ids: Vec<ParticipantId> = players.iter().map(|p| p.id).collect();
+
+    let mut g = Generator::new(tournament, created_at);
+    match tournament.tournament_type {
+        TournamentType::SingleElimination => {
+            g.elimination(&ids, tournament, false);
+        }
+        TournamentType::DoubleElimination => {
+            g.elimination(&ids, tournament, true);
+        }
+        TournamentType::RoundRobin => g.round_robin(&ids),
+        TournamentType::Swiss => {
+            let first_round = Pairings::new(
+                &ParticipantIndex(players.into_iter().cloned().collect()),
+                &MatchIndex(Vec::new()),
+                &tournament.swiss_points,
+            );
+            for (a, b) in first_round.matches {
+                g.pair(1, Slot::Player(a), Slot::Player(b), false);
+            }
+        }
+    }
+    g.finish()
+}
+
+/// Returns the identifier of the match by its index: `A`-`Z`, then `AA`, `AB` and so on.
+fn identifier(mut index: usize) -> String {
+    let mut id = Vec::new();
+    loop {
+        id.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    id.reverse();
+    String::from_utf8(id).unwrap()
+}
+
+/// Returns the bracket positions of the seeds (1-based, `None` for a bye) for a bracket
+/// of `size` positions.
+pub(crate) fn seed_order(size: usize, participants: usize, sequential: bool) -> Vec<Option<usize>> {
+    if sequential {
+        let byes = size - participants;
+        let mut order = Vec::with_capacity(size);
+        for seed in 1..=byes {
+            order.push(Some(seed));
+            order.push(None);
+        }
+        order.extend((byes + 1..=participants).map(Some));
+        return order;
+    }
+    let mut order = vec![1];
+    while order.len() < size {
+        let sum = order.len() * 2 + 1;
+        order = order.iter().flat_map(|&s| vec![s, sum - s]).collect();
+    }
+    order
+        .into_iter()
+        .map(|s| if s <= participants { Some(s) } else { None })
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Slot {
+    Empty,
+    Player(ParticipantId),
+    Winner(MatchId),
+    Loser(MatchId),
+}
+
+struct Generator {
+    matches: Vec<Match>,
+    depths: Vec<u64>,
+    tournament_id: TournamentId,
+    created_at: DateTime<FixedOffset>,
+}
+impl Generator {
+    fn new(tournament: &TournamentCreate, created_at: DateTime<FixedOffset>) -> Generator {
+        Generator {
+            matches: Vec::new(),
+            depths: Vec::new(),
+            tournament_id: TournamentId::Url(tournament.subdomain.clone(), tournament.url.clone()),
+            created_at,
+        }
+    }
+
+    /// Creates a match between the slots if both of them are filled, otherwise the filled
+    /// slot advances with a bye. Returns the slots of the winner and the loser.
+    fn pair(&mut self, round: i64, a: Slot, b: Slot, optional: bool) -> (Slot, Slot) {
+        match (a, b) {
+            (Slot::Empty, s) | (s, Slot::Empty) => return (s, Slot::Empty),
+            _ => {}
+        }
+        let id = MatchId(self.matches.len() as u64 + 1);
+        let depth = [a, b]
+            .iter()
+            .filter_map(|s| match *s {
+                Slot::Winner(m) | Slot::Loser(m) => Some(self.depths[m.0 as usize - 1] + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let player1 = player(a);
+        let player2 = player(b);
+        let state = if player1.id.is_some() && player2.id.is_some() {
+            MatchState::Open
+        } else {
+            MatchState::Pending
+        };
+        self.matches.push(Match {
+            attachment_count: 0,
+            created_at: self.created_at,
+            completed_at: None,
+            forfeited: false,
+            group_id: None,
+            has_attachment: false,
+            id,
+            identifier: identifier(self.matches.len()),
+            location: None,
+            loser_id: None,
+            optional,
+            prerequisite_match_ids: [&player1, &player2]
+                .iter()
+                .filter_map(|p| p.prereq_match_id)
+                .collect(),
+            player1,
+            player2,
+            round,
+            scheduled_time: None,
+            started_at: None,
+            state,
+            suggested_play_order: None,
+            tournament_id: self.tournament_id.clone(),
+            underway_at: None,
+            updated_at: self.created_at,
+            winner_id: None,
+            scores_csv: MatchScores(Vec::new()),
+        });
+        self.depths.push(depth);
+        (Slot::Winner(id), Slot::Loser(id))
+    }
+
+    /// Pairs the consecutive slots, a single last slot advances as is.
+    fn pair_round(&mut self, round: i64, slots: &[Slot]) -> (Vec<Slot>, Vec<Slot>) {
+        slots
+            .chunks(2)
+            .map(|pair| match *pair {
+                [a, b] => self.pair(round, a, b, false),
+                [a] => (a, Slot::Empty),
+                _ => unreachable!(),
+            })
+            .unzip()
+    }
+
+    fn elimination(&mut self, ids: &[ParticipantId], tournament: &TournamentCreate, double: bool) {
+        if ids.len() < 2 {
+            return;
+        }
+        let size = ids.len().next_power_of_two();
+        let mut slots: Vec<Slot> = seed_order(size, ids.len(), tournament.sequential_pairings)
+            .into_iter()
+            .map(|s| s.map_or(Slot::Empty, |s| Slot::Player(ids[s - 1])))
+            .collect();
+        let mut losers = Vec::new();
+        let mut round = 0;
+        while slots.len() > 1 {
+            round += 1;
+            let (winners, lost) = self.pair_round(round, &slots);
+            losers.push(lost);
+            slots = winners;
+        }
+        let champion = slots[0];
+
+        if !double {
+            if tournament.hold_third_place_match && losers.len() >= 2 {
+                let semifinals = &losers[losers.len() - 2];
+                self.pair(round, semifinals[0], semifinals[1], false);
+            }
+            return;
+        }
+
+        let first_losers_match = self.matches.len();
+        let mut losers_round = -1;
+        let (mut survivors, _) = self.pair_round(losers_round, &losers[0]);
+        for (k, drops) in losers.iter().enumerate().skip(1) {
+            let mut drops = drops.clone();
+            if k % 2 == 1 {
+                drops.reverse();
+            }
+            losers_round -= 1;
+            survivors = survivors
+                .iter()
+                .zip(drops)
+                .map(|(&a, b)| self.pair(losers_round, a, b, false).0)
+                .collect();
+            if k + 1 < losers.len() {
+                losers_round -= 1;
+                survivors = self.pair_round(losers_round, &survivors).0;
+            }
+        }
+        self.compress_losers_rounds(first_losers_match);
+
+        match tournament
+            .grand_finals_modifier
+            .as_ref()
+            .map(|m| m.replace('_', " "))
+            .as_deref()
+        {
+            Some("skip") => {}
+            Some("single match") => {
+                self.pair(round + 1, champion, survivors[0], false);
+            }
+            _ => {
+                if let (Slot::Winner(gf), _) = self.pair(round + 1, champion, survivors[0], false) {
+                    self.pair(round + 2, Slot::Winner(gf), Slot::Loser(gf), true);
+                }
+            }
+        }
+    }
+
+    /// Losers bracket rounds which have only byes (because of the byes in the first round)
+    /// are dropped from numbering.
+    fn compress_losers_rounds(&mut self, first: usize) {
+        let mut rounds: Vec<i64> = self.matches[first..].iter().map(|m| m.round).collect();
+        rounds.sort_unstable_by(|a, b| b.cmp(a));
+        rounds.dedup();
+        for m in &mut self.matches[first..] {
+            m.round = -(rounds.iter().position(|&r| r == m.round).unwrap() as i64 + 1);
+        }
+    }
+
+    /// Round robin rounds by the circle method: the first participant stays in place
+    /// while the others rotate.
+    fn round_robin(&mut self, ids: &[ParticipantId]) {
+        let mut circle: Vec<Slot> = ids.iter().map(|&id| Slot::Player(id)).collect();
+        if circle.len() % 2 == 1 {
+            circle.push(Slot::Empty);
+        }
+        let n = circle.len();
+        for round in 1..n {
+            for i in 0..n / 2 {
+                self.pair(round as i64, circle[i], circle[n - 1 - i], false);
+            }
+            let last = circle.pop().unwrap();
+            circle.insert(1, last);
+        }
+    }
+
+    /// Suggested play order goes round by round through the bracket: a match is suggested
+    /// right after the matches it depends on.
+    fn finish(mut self) -> MatchIndex {
+        let mut order: Vec<usize> = (0..self.matches.len()).collect();
+        let matches = &self.matches;
+        let depths = &self.depths;
+        order.sort_by_key(|&i| (depths[i], matches[i].round < 0, i));
+        for (position, i) in order.into_iter().enumerate() {
+            self.matches[i].suggested_play_order = Some(position as u64 + 1);
+        }
+        MatchIndex(self.matches)
+    }
+}
+
+fn player(slot: Slot) -> Player {
+    let (id, prereq_match_id, is_prereq_match_loser) = match slot {
+        Slot::Player(id) => (Some(id), None, false),
+        Slot::Winner(m) => (None, Some(m), false),
+        Slot::Loser(m) => (None, Some(m), true),
+        Slot::Empty => (None, None, false),
+    };
+    Player {
+        id,
+        is_prereq_match_loser,
+        prereq_match_id,
+        votes: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::{Bracket, Side};
+    use crate::generator::identifier;
+    use crate::matches::{Index as MatchIndex, Match, MatchId, MatchState};
+    use crate::participants::tests::make_participant;
+    use crate::participants::{Index as ParticipantIndex, Participant, ParticipantId};
+    use crate::tournament::{TournamentCreate, TournamentType};
+    use chrono::{DateTime, FixedOffset};
+    use std::collections::{HashMap, HashSet};
+
+    fn start_time() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2015-01-21T18:00:00-05:00").unwrap()
+    }
+
+    fn generate(participants: &[Participant], tournament: &TournamentCreate) -> MatchIndex {
+        crate::generator::generate(participants, tournament, start_time())
+    }
+
+    fn participants(count: u64) -> Vec<Participant> {
+        (1..=count)
+            .map(|i| make_participant(i, &format!("Participant #{}", i), i))
+            .collect()
+    }
+
+    fn tournament(tournament_type: TournamentType) -> TournamentCreate {
+        let mut tc = TournamentCreate::new();
+        tc.tournament_type(tournament_type).url("preview");
+        tc
+    }
+
+    /// (identifier, round, player 1, player 2) with players as participant ids
+    /// or prerequisite matches ("W3" for the winner of match 3, "L3" for the loser).
+    fn summary(index: &MatchIndex) -> Vec<(String, i64, String, String)> {
+        let slot = |p: &crate::matches::Player| match p.prereq_match_id {
+            Some(m) if p.is_prereq_match_loser => format!("L{}", m),
+            Some(m) => format!("W{}", m),
+            None => p.id.map(|id| id.to_string()).unwrap_or_default(),
+        };
+        index
+            .0
+            .iter()
+            .map(|m| {
+                (
+                    m.identifier.clone(),
+                    m.round,
+                    slot(&m.player1),
+                    slot(&m.player2),
+                )
+            })
+            .collect()
+    }
+
+    fn s(rows: &[(&str, i64, &str, &str)]) -> Vec<(String, i64, String, String)> {
+        rows.iter()
+            .map(|r| (r.0.to_owned(), r.1, r.2.to_owned(), r.3.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let ids: Vec<String> = [0, 1, 25, 26, 27, 51, 52, 701, 702]
+            .iter()
+            .map(|&i| identifier(i))
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["A", "B", "Z", "AA", "AB", "AZ", "BA", "ZZ", "AAA"]
+        );
+    }
+
+    #[test]
+    fn test_single_elimination() {
+        let index = generate(
+            &participants(8),
+            &tournament(TournamentType::SingleElimination),
+        );
+        assert_eq!(
+            summary(&index),
+            s(&[
+                ("A", 1, "1", "8"),
+                ("B", 1, "4", "5"),
+                ("C", 1, "2", "7"),
+                ("D", 1, "3", "6"),
+                ("E", 2, "W1", "W2"),
+                ("F", 2, "W3", "W4"),
+                ("G", 3, "W5", "W6"),
+            ])
+        );
+        assert!(index.0[..4].iter().all(|m| m.state == MatchState::Open));
+        assert!(index.0[4..].iter().all(|m| m.state == MatchState::Pending));
+        assert_eq!(
+            index.0[6].prerequisite_match_ids,
+            vec![MatchId(5), MatchId(6)]
+        );
+    }
+
+    #[test]
+    fn test_single_elimination_byes_and_third_place() {
+        let mut tc = tournament(TournamentType::SingleElimination);
+        tc.hold_third_place_match(true);
+        let index = generate(&participants(6), &tc);
+        assert_eq!(
+            summary(&index),
+            s(&[
+                ("A", 1, "4", "5"),
+                ("B", 1, "3", "6"),
+                ("C", 2, "1", "W1"),
+                ("D", 2, "2", "W2"),
+                ("E", 3, "W3", "W4"),
+                ("F", 3, "L3", "L4"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_sequential_pairings() {
+        let mut tc = tournament(TournamentType::SingleElimination);
+        tc.sequential_pairings(true);
+        let index = generate(&participants(6), &tc);
+        assert_eq!(
+            summary(&index),
+            s(&[
+                ("A", 1, "3", "4"),
+                ("B", 1, "5", "6"),
+                ("C", 2, "1", "2"),
+                ("D", 2, "W1", "W2"),
+                ("E", 3, "W3", "W4"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_double_elimination() {
+        let index = generate(
+            &participants(4),
+            &tournament(TournamentType::DoubleElimination),
+        );
+        assert_eq!(
+            summary(&index),
+            s(&[
+                ("A", 1, "1", "4"),
+                ("B", 1, "2", "3"),
+                ("C", 2, "W1", "W2"),
+                ("D", -1, "L1", "L2"),
+                ("E", -2, "W4", "L3"),
+                ("F", 3, "W3", "W5"),
+                ("G", 4, "W6", "L6"),
+            ])
+        );
+        assert!(index.0[6].optional);
+
+        let b = Bracket::new(&index);
+        assert_eq!(b.side_matches(Side::Winners).len(), 3);
+        assert_eq!(b.side_matches(Side::Losers).len(), 2);
+        assert_eq!(b.side_matches(Side::GrandFinals).len(), 2);
+        let order: Vec<u64> = index
+            .0
+            .iter()
+            .map(|m| m.suggested_play_order.unwrap())
+            .collect();
+        assert_eq!(order, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_double_elimination_eight() {
+        let mut tc = tournament(TournamentType::DoubleElimination);
+        tc.grand_finals_modifier(Some("single match".to_owned()));
+        let index = generate(&participants(8), &tc);
+        let losers: Vec<_> = summary(&index).into_iter().filter(|m| m.1 < 0).collect();
+        assert_eq!(
+            losers,
+            s(&[
+                ("H", -1, "L1", "L2"),
+                ("I", -1, "L3", "L4"),
+                ("J", -2, "W8", "L6"),
+                ("K", -2, "W9", "L5"),
+                ("L", -3, "W10", "W11"),
+                ("M", -4, "W12", "L7"),
+            ])
+        );
+        assert_eq!(index.0.len(), 14);
+        assert_eq!(summary(&index)[13], s(&[("N", 4, "W7", "W13")])[0]);
+
+        tc.grand_finals_modifier(Some("skip".to_owned()));
+        assert_eq!(generate(&participants(8), &tc).0.len(), 13);
+    }
+
+    #[test]
+    fn test_double_elimination_byes() {
+        let index = generate(
+            &participants(5),
+            &tournament(TournamentType::DoubleElimination),
+        );
+        let b = Bracket::new(&index);
+        // 5 participants play 2 * 5 - 2 matches and the optional reset.
+        assert_eq!(index.0.len(), 9);
+        let rounds: HashSet<i64> = b
+            .side_matches(Side::Losers)
+            .iter()
+            .map(|m| m.round)
+            .collect();
+        assert_eq!(rounds, (-3..=-1).collect());
+        // 4 plays 5 in the first round, while 2 and 3 have byes into the second one.
+        assert_eq!(b.playable().len(), 2);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let index = generate(&participants(5), &tournament(TournamentType::RoundRobin));
+        assert_eq!(index.0.len(), 10);
+        let mut pairs = HashSet::new();
+        for m in &index.0 {
+            assert_eq!(m.state, MatchState::Open);
+            let (a, b) = (m.player1.id.unwrap().0, m.player2.id.unwrap().0);
+            assert!(pairs.insert((a.min(b), a.max(b))));
+        }
+        for round in 1..=5 {
+            let players: Vec<ParticipantId> = index
+                .0
+                .iter()
+                .filter(|m| m.round == round)
+                .flat_map(|m| vec![m.player1.id.unwrap(), m.player2.id.unwrap()])
+                .collect();
+            assert_eq!(players.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_created_at() {
+        let index = generate(
+            &participants(8),
+            &tournament(TournamentType::DoubleElimination),
+        );
+        assert!(index
+            .0
+            .iter()
+            .all(|m| m.created_at == start_time() && m.updated_at == start_time()));
+    }
+
+    /// (identifier, round, state, optional, play order, player 1, player 2)
+    type Structure = (String, i64, MatchState, bool, Option<u64>, String, String);
+
+    /// Describes the matches by their identifiers, so brackets with different match ids
+    /// can be compared.
+    fn structure(index: &MatchIndex) -> Vec<Structure> {
+        let identifiers: HashMap<MatchId, &str> = index
+            .0
+            .iter()
+            .map(|m| (m.id, m.identifier.as_str()))
+            .collect();
+        let slot = |p: &crate::matches::Player| match (p.id, p.prereq_match_id) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(m)) if p.is_prereq_match_loser => format!("L{}", identifiers[&m]),
+            (None, Some(m)) => format!("W{}", identifiers[&m]),
+            (None, None) => String::new(),
+        };
+        index
+            .0
+            .iter()
+            .map(|m: &Match| {
+                (
+                    m.identifier.clone(),
+                    m.round,
+                    m.state.clone(),
+                    m.optional,
+                    m.suggested_play_order,
+                    slot(&m.player1),
+                    slot(&m.player2),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_double_elimination_fixture() {
+        // A freshly started double elimination bracket in the shape of the API's
+        // participant and match indexes. The fixture is synthetic, written by hand rather
+        // than exported from Challonge.
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/double_elimination.json")).unwrap();
+        let participants = ParticipantIndex::decode(fixture["participants"].clone()).unwrap();
+        let expected = MatchIndex::decode(fixture["matches"].clone()).unwrap();
+
+        let index = generate(
+            &participants.0,
+            &tournament(TournamentType::DoubleElimination),
+        );
+        assert_eq!(structure(&index), structure(&expected));
+        assert!(index
+            .0
+            .iter()
+            .zip(expected.0.iter())
+            .all(|(m, e)| m.created_at == e.created_at));
+    }
+}
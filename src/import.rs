@@ -0,0 +1,507 @@
+//! Import of participants from CSV and TSV spreadsheets.
+//!
+//! The first row names the columns. Common headers ("Name", "E-mail", "Challonge username",
+//! "Seed", "Misc", ...) are recognized, others can be mapped with `Importer::map` and the
+//! rest are ignored. Every row is validated and checked for duplicates, so the result can be
+//! reviewed (see `Import::summary`) before passing `Import::participants` to
+//! `create_participant_bulk`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::participants::ParticipantCreate;
+
+/// Longest `misc` value Challonge accepts.
+const MAX_MISC: usize = 255;
+
+/// A participant field a spreadsheet column is imported into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// `ParticipantCreate::name`
+    Name,
+
+    /// `ParticipantCreate::email`
+    Email,
+
+    /// `ParticipantCreate::challonge_username`
+    ChallongeUsername,
+
+    /// `ParticipantCreate::seed`
+    Seed,
+
+    /// `ParticipantCreate::misc`
+    Misc,
+
+    /// The column is not imported.
+    Ignore,
+}
+impl Column {
+    /// Recognizes common header names, ignoring case, spaces and punctuation.
+    fn from_header(header: &str) -> Column {
+        let normalized: String = header
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "name" | "displayname" | "participant" | "player" | "playername" => Column::Name,
+            "email" | "emailaddress" | "mail" => Column::Email,
+            "challongeusername" | "challonge" | "username" => Column::ChallongeUsername,
+            "seed" | "seeding" => Column::Seed,
+            "misc" | "notes" | "note" => Column::Misc,
+            _ => Column::Ignore,
+        }
+    }
+}
+
+/// A problem with a row of the spreadsheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    /// Line number (starting from 1) where the row starts.
+    pub line: usize,
+
+    /// Description of the problem.
+    pub message: String,
+}
+impl fmt::Display for ImportError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Result of an import: the participants of the valid rows and the problems found.
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// Participants to create, in the order of the rows.
+    pub participants: Vec<ParticipantCreate>,
+
+    /// Line numbers of the rows the participants come from.
+    pub lines: Vec<usize>,
+
+    /// Invalid and duplicate rows, which are not imported.
+    pub errors: Vec<ImportError>,
+
+    /// Headers of the columns which are not imported.
+    pub ignored_columns: Vec<String>,
+}
+impl Import {
+    /// Returns true if all the rows are imported.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Describes what would be imported, for a dry run.
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} participant(s) to import, {} error(s)\n",
+            self.participants.len(),
+            self.errors.len()
+        );
+        if !self.ignored_columns.is_empty() {
+            out.push_str(&format!(
+                "ignored columns: {}\n",
+                self.ignored_columns.join(", ")
+            ));
+        }
+        for (p, line) in self.participants.iter().zip(&self.lines) {
+            let mut details = Vec::new();
+            if let Some(ref email) = p.email {
+                details.push(format!("email {}", email));
+            }
+            if let Some(ref username) = p.challonge_username {
+                details.push(format!("challonge {}", username));
+            }
+            if let Some(seed) = p.seed {
+                details.push(format!("seed {}", seed));
+            }
+            let name = p
+                .name
+                .as_ref()
+                .or(p.challonge_username.as_ref())
+                .or(p.email.as_ref())
+                .map_or("", |n| n.as_str());
+            out.push_str(&format!("  line {}: {}", line, name));
+            if !details.is_empty() {
+                out.push_str(&format!(" ({})", details.join(", ")));
+            }
+            out.push('\n');
+        }
+        for e in &self.errors {
+            out.push_str(&format!("  error: {}\n", e));
+        }
+        out
+    }
+}
+
+/// Participants importer.
+#[derive(Debug, Clone)]
+pub struct Importer {
+    delimiter: Option<char>,
+    mapping: HashMap<String, Column>,
+}
+impl Importer {
+    /// Creates an importer which detects the delimiter and recognizes common headers.
+    pub fn new() -> Importer {
+        Importer {
+            delimiter: None,
+            mapping: HashMap::new(),
+        }
+    }
+
+    /// Sets the delimiter. By default a tab is used if the header contains one,
+    /// a comma otherwise.
+    pub fn delimiter(&mut self, delimiter: char) -> &mut Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Imports the column with the given header (matched ignoring case) into the field.
+    pub fn map<S: Into<String>>(&mut self, header: S, column: Column) -> &mut Self {
+        self.mapping
+            .insert(header.into().trim().to_lowercase(), column);
+        self
+    }
+
+    /// Imports the participants from the spreadsheet text.
+    pub fn parse(&self, input: &str) -> Import {
+        let input = input.trim_start_matches('\u{feff}');
+        let delimiter = self.delimiter.unwrap_or_else(|| {
+            let header = input.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+            if header.contains('\t') {
+                '\t'
+            } else {
+                ','
+            }
+        });
+
+        let mut import = Import {
+            participants: Vec::new(),
+            lines: Vec::new(),
+            errors: Vec::new(),
+            ignored_columns: Vec::new(),
+        };
+        let records = match parse_records(input, delimiter) {
+            Ok(records) => records,
+            Err(e) => {
+                import.errors.push(e);
+                return import;
+            }
+        };
+        let mut records = records.into_iter();
+        let (header_line, header) = match records.next() {
+            Some(header) => header,
+            None => return import,
+        };
+
+        let columns: Vec<Column> = header
+            .iter()
+            .map(|h| {
+                let column = self
+                    .mapping
+                    .get(&h.trim().to_lowercase())
+                    .cloned()
+                    .unwrap_or_else(|| Column::from_header(h));
+                if column == Column::Ignore {
+                    import.ignored_columns.push(h.trim().to_owned());
+                }
+                column
+            })
+            .collect();
+        for column in &[
+            Column::Name,
+            Column::Email,
+            Column::ChallongeUsername,
+            Column::Seed,
+            Column::Misc,
+        ] {
+            if columns.iter().filter(|c| *c == column).count() > 1 {
+                import.errors.push(ImportError {
+                    line: header_line,
+                    message: format!("more than one column is imported as {:?}", column),
+                });
+            }
+        }
+        if !columns
+            .iter()
+            .any(|c| [Column::Name, Column::Email, Column::ChallongeUsername].contains(c))
+        {
+            import.errors.push(ImportError {
+                line: header_line,
+                message: "no name, email or challonge username column".to_owned(),
+            });
+        }
+        if !import.errors.is_empty() {
+            return import;
+        }
+
+        // Values already taken, with the lines they were first seen on.
+        let mut seen: HashMap<(Column, String), usize> = HashMap::new();
+        for (line, record) in records {
+            match self.participant(&columns, &record) {
+                Ok(p) => {
+                    let keys = [
+                        (Column::Name, p.name.as_ref().map(|s| s.to_lowercase())),
+                        (Column::Email, p.email.as_ref().map(|s| s.to_lowercase())),
+                        (
+                            Column::ChallongeUsername,
+                            p.challonge_username.as_ref().map(|s| s.to_lowercase()),
+                        ),
+                        (Column::Seed, p.seed.map(|s| s.to_string())),
+                    ];
+                    let duplicate = keys.iter().find_map(|(column, value)| {
+                        let value = value.as_ref()?;
+                        let first = seen.get(&(*column, value.clone()))?;
+                        Some(format!(
+                            "duplicate {} {:?} (first on line {})",
+                            column_name(*column),
+                            value,
+                            first
+                        ))
+                    });
+                    if let Some(message) = duplicate {
+                        import.errors.push(ImportError { line, message });
+                        continue;
+                    }
+                    for (column, value) in keys.iter() {
+                        if let Some(value) = value {
+                            seen.insert((*column, value.clone()), line);
+                        }
+                    }
+                    import.participants.push(p);
+                    import.lines.push(line);
+                }
+                Err(message) => import.errors.push(ImportError { line, message }),
+            }
+        }
+        import
+    }
+
+    fn participant(
+        &self,
+        columns: &[Column],
+        record: &[String],
+    ) -> Result<ParticipantCreate, String> {
+        if record.len() > columns.len() {
+            return Err(format!(
+                "{} values for {} columns",
+                record.len(),
+                columns.len()
+            ));
+        }
+        let mut p = ParticipantCreate::new();
+        for (column, value) in columns.iter().zip(record) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match *column {
+                Column::Name => {
+                    p.name(value);
+                }
+                Column::Email => {
+                    if !is_valid_email(value) {
+                        return Err(format!("invalid email {:?}", value));
+                    }
+                    p.email(value);
+                }
+                Column::ChallongeUsername => {
+                    p.challonge_username(value);
+                }
+                Column::Seed => match value.parse::<u64>() {
+                    Ok(seed) if seed > 0 => {
+                        p.seed(seed);
+                    }
+                    _ => return Err(format!("invalid seed {:?}", value)),
+                },
+                Column::Misc => {
+                    if value.chars().count() > MAX_MISC {
+                        return Err(format!("misc is longer than {} characters", MAX_MISC));
+                    }
+                    p.misc(value);
+                }
+                Column::Ignore => {}
+            }
+        }
+        if p.name.is_none() && p.email.is_none() && p.challonge_username.is_none() {
+            return Err("no name, email or challonge username".to_owned());
+        }
+        Ok(p)
+    }
+}
+
+impl Default for Importer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn column_name(column: Column) -> &'static str {
+    match column {
+        Column::Name => "name",
+        Column::Email => "email",
+        Column::ChallongeUsername => "challonge username",
+        Column::Seed => "seed",
+        Column::Misc => "misc",
+        Column::Ignore => "ignored column",
+    }
+}
+
+fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && domain.contains('.')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Splits the text into records of fields, with the line numbers the records start on.
+/// Fields may be quoted with `"` (doubled inside quotes), quoted fields may span lines.
+/// Blank lines are skipped.
+fn parse_records(input: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = input.chars().peekable();
+
+    let mut end_record = |record: &mut Vec<String>, field: &mut String, start: usize| {
+        record.push(std::mem::take(field));
+        let record = std::mem::take(record);
+        if !(record.len() == 1 && record[0].trim().is_empty()) {
+            records.push((start, record));
+        }
+    };
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_record(&mut record, &mut field, start);
+                line += 1;
+                start = line;
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(ImportError {
+            line: start,
+            message: "unterminated quoted value".to_owned(),
+        });
+    }
+    end_record(&mut record, &mut field, start);
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::{Column, ImportError, Importer};
+
+    #[test]
+    fn test_import_csv() {
+        let csv = "\
+Name,E-mail,Challonge Username,Seed,Notes,Team
+Alice,alice@example.com,,1,\"likes \"\"quotes\"\", commas\",Red
+
+Bob,,bob42,2,,Blue
+";
+        let import = Importer::new().parse(csv);
+        assert!(import.is_ok(), "{:?}", import.errors);
+        assert_eq!(import.ignored_columns, vec!["Team"]);
+        assert_eq!(import.lines, vec![2, 4]);
+        let alice = &import.participants[0];
+        assert_eq!(alice.name.as_deref(), Some("Alice"));
+        assert_eq!(alice.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(alice.challonge_username, None);
+        assert_eq!(alice.seed, Some(1));
+        assert_eq!(alice.misc.as_deref(), Some("likes \"quotes\", commas"));
+        let bob = &import.participants[1];
+        assert_eq!(bob.challonge_username.as_deref(), Some("bob42"));
+        assert_eq!(bob.misc, None);
+    }
+
+    #[test]
+    fn test_import_tsv_with_mapping() {
+        let tsv = "Gamer Tag\tSeed\nAlice\t2\nBob\t1\n";
+        let import = Importer::new().map("gamer tag", Column::Name).parse(tsv);
+        assert!(import.is_ok());
+        let names: Vec<_> = import
+            .participants
+            .iter()
+            .map(|p| (p.name.clone().unwrap(), p.seed.unwrap()))
+            .collect();
+        assert_eq!(names, vec![("Alice".to_owned(), 2), ("Bob".to_owned(), 1)]);
+
+        let import = Importer::new().parse("Gamer Tag\tSeed\nAlice\t2\n");
+        assert_eq!(import.errors[0].line, 1);
+        assert!(import.participants.is_empty());
+    }
+
+    #[test]
+    fn test_import_errors_and_duplicates() {
+        let csv = "\
+name,email,seed
+Alice,alice@example.com,1
+Bob,not-an-email,2
+Carol,,zero
+ALICE,,3
+Dave,ALICE@example.com,4
+,,5
+Erin,erin@example.com,1,extra
+\"Frank
+";
+        let import = Importer::new().parse(csv);
+        assert!(import.participants.is_empty());
+        assert_eq!(
+            import.errors,
+            vec![ImportError {
+                line: 9,
+                message: "unterminated quoted value".to_owned(),
+            }]
+        );
+
+        let import = Importer::new().parse(csv.trim_end_matches("\"Frank\n"));
+        let errors: Vec<String> = import.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 3: invalid email \"not-an-email\"",
+                "line 4: invalid seed \"zero\"",
+                "line 5: duplicate name \"alice\" (first on line 2)",
+                "line 6: duplicate email \"alice@example.com\" (first on line 2)",
+                "line 7: no name, email or challonge username",
+                "line 8: 4 values for 3 columns",
+            ]
+        );
+        assert_eq!(import.participants.len(), 1);
+        assert!(import
+            .summary()
+            .starts_with("1 participant(s) to import, 6 error(s)\n  line 2: Alice (email alice@example.com, seed 1)\n"));
+    }
+}
@@ -10,24 +10,47 @@
 #![deny(warnings)]
 
 use chrono::offset::Local;
+#[allow(deprecated)]
 use chrono::Date;
 #[macro_use]
 mod macroses;
 pub mod attachments;
+pub mod bracket;
+pub mod checkin;
 pub mod error;
+pub mod export;
+pub mod generator;
+pub mod import;
+pub mod lifecycle;
 pub mod matches;
 pub mod participants;
+pub mod ratings;
+pub mod render;
+pub mod scheduler;
+pub mod seeding;
+pub mod standings;
+pub mod swiss;
+#[cfg(feature = "sqlite")]
+pub mod sync;
+pub mod templates;
 pub mod tournament;
 mod util;
+pub mod watch;
+pub mod webhooks;
 pub use attachments::{Attachment, AttachmentCreate, AttachmentId, Index as AttachmentIndex};
+pub use bracket::Bracket;
 use error::Error;
 pub use matches::{
     Index as MatchIndex, Match, MatchId, MatchScore, MatchScores, MatchState, MatchUpdate,
+    MatchWinner,
 };
-pub use participants::{Index as ParticipantIndex, Participant, ParticipantCreate, ParticipantId};
+pub use participants::{
+    GroupId, Index as ParticipantIndex, Participant, ParticipantCreate, ParticipantId, TeamCreate,
+};
+pub use standings::{Standing, Standings};
 pub use tournament::{
     Index as TournamentIndex, Tournament, TournamentCreate, TournamentId, TournamentIncludes,
-    TournamentState, TournamentType,
+    TournamentState, TournamentStatus, TournamentType, TournamentUpdate,
 };
 
 const API_BASE: &str = "https://api.challonge.com/v1";
@@ -66,29 +89,37 @@ fn pairs_to_string(params: FieldPairs) -> String {
     body
 }
 
-fn pcs_to_pairs(participants: Vec<ParticipantCreate>) -> FieldPairs {
+/// `existing` is the number of participants already in the tournament.
+fn pcs_to_pairs(participants: Vec<ParticipantCreate>, existing: usize) -> FieldPairs {
     let mut params = Vec::new();
-    for p in participants {
-        params.push((ps!("email"), p.email.clone()));
-        params.push((ps!("seed"), p.seed.to_string()));
-        params.push((ps!("misc"), p.misc.clone()));
+    for (i, p) in participants.into_iter().enumerate() {
+        // Every entry of the array starts with the seed so the entries can't be merged.
+        // The participants are added one after another, so the seed after the ones added
+        // before appends a participant without an explicit seed.
+        let seed = p.seed.unwrap_or((existing + i + 1) as u64);
+        params.push((ps!("seed"), seed.to_string()));
 
-        if let Some(n) = p.name.as_ref() {
-            params.push((ps!("name"), n.clone()));
+        if let Some(n) = p.name {
+            params.push((ps!("name"), n));
+        }
+        if let Some(un) = p.challonge_username {
+            params.push((ps!("challonge_username"), un));
+        }
+        if let Some(e) = p.email {
+            params.push((ps!("email"), e));
         }
-        if let Some(un) = p.challonge_username.as_ref() {
-            params.push((ps!("challonge_username"), un.clone()));
+        if let Some(ie) = p.invite_name_or_email {
+            params.push((ps!("invite_name_or_email"), ie));
+        }
+        if let Some(m) = p.misc {
+            params.push((ps!("misc"), m));
         }
     }
     params
 }
 
 fn pc_to_pairs(participant: &ParticipantCreate) -> FieldPairs {
-    let mut params = vec![
-        (p!("email"), participant.email.clone()),
-        (p!("seed"), participant.seed.to_string()),
-        (p!("misc"), participant.misc.clone()),
-    ];
+    let mut params = FieldPairs::new();
 
     if let Some(n) = participant.name.as_ref() {
         params.push((p!("name"), n.clone()));
@@ -96,6 +127,18 @@ fn pc_to_pairs(participant: &ParticipantCreate) -> FieldPairs {
     if let Some(un) = participant.challonge_username.as_ref() {
         params.push((p!("challonge_username"), un.clone()));
     }
+    if let Some(e) = participant.email.as_ref() {
+        params.push((p!("email"), e.clone()));
+    }
+    if let Some(ie) = participant.invite_name_or_email.as_ref() {
+        params.push((p!("invite_name_or_email"), ie.clone()));
+    }
+    if let Some(s) = participant.seed {
+        params.push((p!("seed"), s.to_string()));
+    }
+    if let Some(m) = participant.misc.as_ref() {
+        params.push((p!("misc"), m.clone()));
+    }
     params
 }
 
@@ -115,85 +158,109 @@ fn at_to_pairs(attachment: &AttachmentCreate) -> FieldPairs {
 }
 
 fn tc_to_pairs(tournament: &TournamentCreate) -> FieldPairs {
-    let mut params = vec![
-        (t!("name"), tournament.name.clone()),
-        (
-            t!("tournament_type"),
-            tournament.tournament_type.to_string(),
-        ),
-        (t!("url"), tournament.url.clone()),
-        (t!("subdomain"), tournament.subdomain.clone()),
-        (t!("description"), tournament.description.clone()),
-        (t!("open_signup"), tournament.open_signup.to_string()),
-        (
-            t!("hold_third_place_match"),
-            tournament.hold_third_place_match.to_string(),
-        ),
-        (
-            t!("pts_for_match_win"),
-            tournament.swiss_points.match_win.to_string(),
-        ),
-        (
-            t!("pts_for_match_tie"),
-            tournament.swiss_points.match_tie.to_string(),
-        ),
-        (
-            t!("pts_for_game_win"),
-            tournament.swiss_points.game_win.to_string(),
-        ),
-        (
-            t!("pts_for_game_tie"),
-            tournament.swiss_points.game_tie.to_string(),
-        ),
-        (t!("swiss_rounds"), tournament.swiss_rounds.to_string()),
-        (t!("ranked_by"), tournament.ranked_by.to_string()),
-        (
-            t!("rr_pts_for_match_win"),
-            tournament.round_robin_points.match_win.to_string(),
-        ),
-        (
-            t!("rr_pts_for_match_tie"),
-            tournament.round_robin_points.match_tie.to_string(),
-        ),
-        (
-            t!("rr_pts_for_game_win"),
-            tournament.round_robin_points.game_win.to_string(),
-        ),
-        (
-            t!("rr_pts_for_game_tie"),
-            tournament.round_robin_points.game_tie.to_string(),
-        ),
-        (t!("show_rounds"), tournament.show_rounds.to_string()),
-        (t!("private"), tournament.private.to_string()),
-        (
-            t!("notify_users_when_matches_open"),
-            tournament.notify_users_when_matches_open.to_string(),
-        ),
-        (
-            t!("notify_users_when_the_tournament_ends"),
-            tournament.notify_users_when_the_tournament_ends.to_string(),
-        ),
-        (
-            t!("sequential_pairings"),
-            tournament.sequential_pairings.to_string(),
-        ),
-        (t!("signup_cap"), tournament.signup_cap.to_string()),
-        (
-            t!("check_in_duration"),
-            tournament.check_in_duration.to_string(),
-        ),
-    ];
-    if let Some(gfm) = tournament.grand_finals_modifier.as_ref() {
-        params.push((t!("grand_finals_modifier"), gfm.clone()));
-    }
-    if let Some(start_at) = tournament.start_at.as_ref() {
-        params.push((t!("start_at"), start_at.to_rfc3339()));
-    }
-    if let Some(s_bye_pts) = tournament.swiss_points.bye.as_ref() {
-        params.push((t!("pts_for_bye"), s_bye_pts.to_string()));
-    }
-    if let Some(game) = tournament.game_name.as_ref() {
-        params.push((t!("game_name"), game.clone()));
+    // A new tournament has nothing to clear, the unset attributes are left out.
+    tu_to_pairs(&TournamentUpdate::from(tournament))
+        .into_iter()
+        .filter(|(_, v)| !v.is_empty())
+        .collect()
+}
+
+fn tu_to_pairs(update: &TournamentUpdate) -> FieldPairs {
+    let mut params = FieldPairs::new();
+    // An empty value clears an attribute.
+    fn or_empty<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map(T::to_string).unwrap_or_default()
+    }
+
+    if let Some(v) = update.name.as_ref() {
+        params.push((t!("name"), v.clone()));
+    }
+    if let Some(v) = update.tournament_type.as_ref() {
+        params.push((t!("tournament_type"), v.to_string()));
+    }
+    if let Some(v) = update.url.as_ref() {
+        params.push((t!("url"), v.clone()));
+    }
+    if let Some(v) = update.subdomain.as_ref() {
+        params.push((t!("subdomain"), v.clone()));
+    }
+    if let Some(v) = update.description.as_ref() {
+        params.push((t!("description"), v.clone()));
+    }
+    if let Some(v) = update.open_signup {
+        params.push((t!("open_signup"), v.to_string()));
+    }
+    if let Some(v) = update.hold_third_place_match {
+        params.push((t!("hold_third_place_match"), v.to_string()));
+    }
+    if let Some(v) = update.swiss_points.as_ref() {
+        params.push((t!("pts_for_match_win"), v.match_win.to_string()));
+        params.push((t!("pts_for_match_tie"), v.match_tie.to_string()));
+        params.push((t!("pts_for_game_win"), v.game_win.to_string()));
+        params.push((t!("pts_for_game_tie"), v.game_tie.to_string()));
+        params.push((t!("pts_for_bye"), or_empty(&v.bye)));
+    }
+    if let Some(v) = update.swiss_rounds {
+        params.push((t!("swiss_rounds"), v.to_string()));
+    }
+    if let Some(v) = update.ranked_by {
+        params.push((t!("ranked_by"), v.to_string()));
+    }
+    if let Some(v) = update.round_robin_points.as_ref() {
+        params.push((t!("rr_pts_for_match_win"), v.match_win.to_string()));
+        params.push((t!("rr_pts_for_match_tie"), v.match_tie.to_string()));
+        params.push((t!("rr_pts_for_game_win"), v.game_win.to_string()));
+        params.push((t!("rr_pts_for_game_tie"), v.game_tie.to_string()));
+    }
+    if let Some(v) = update.tie_breaks.as_ref() {
+        for tb in v {
+            params.push((t!("tie_breaks]["), tb.to_string()));
+        }
+        if v.is_empty() {
+            params.push((t!("tie_breaks]["), String::new()));
+        }
+    }
+    if let Some(v) = update.accept_attachments {
+        params.push((t!("accept_attachments"), v.to_string()));
+    }
+    if let Some(v) = update.hide_forum {
+        params.push((t!("hide_forum"), v.to_string()));
+    }
+    if let Some(v) = update.teams {
+        params.push((t!("teams"), v.to_string()));
+    }
+    if let Some(v) = update.team_convertable {
+        params.push((t!("team_convertable"), v.to_string()));
+    }
+    if let Some(v) = update.show_rounds {
+        params.push((t!("show_rounds"), v.to_string()));
+    }
+    if let Some(v) = update.private {
+        params.push((t!("private"), v.to_string()));
+    }
+    if let Some(v) = update.game_name.as_ref() {
+        params.push((t!("game_name"), or_empty(v)));
+    }
+    if let Some(v) = update.notify_users_when_matches_open {
+        params.push((t!("notify_users_when_matches_open"), v.to_string()));
+    }
+    if let Some(v) = update.notify_users_when_the_tournament_ends {
+        params.push((t!("notify_users_when_the_tournament_ends"), v.to_string()));
+    }
+    if let Some(v) = update.sequential_pairings {
+        params.push((t!("sequential_pairings"), v.to_string()));
+    }
+    if let Some(v) = update.signup_cap.as_ref() {
+        params.push((t!("signup_cap"), or_empty(v)));
+    }
+    if let Some(v) = update.start_at.as_ref() {
+        params.push((t!("start_at"), or_empty(&v.map(|s| s.to_rfc3339()))));
+    }
+    if let Some(v) = update.check_in_duration.as_ref() {
+        params.push((t!("check_in_duration"), or_empty(v)));
+    }
+    if let Some(v) = update.grand_finals_modifier.as_ref() {
+        params.push((t!("grand_finals_modifier"), or_empty(v)));
     }
     params
 }
@@ -209,7 +276,10 @@ fn mu_to_pairs(mu: &MatchUpdate) -> FieldPairs {
     }
     params.push((m!("scores_csv"), mu.scores_csv.to_string()));
     if let Some(w) = mu.winner_id.as_ref() {
-        params.push((m!("winner_id"), w.0.to_string()));
+        params.push((m!("winner_id"), w.to_string()));
+    }
+    if let Some(l) = mu.location.as_ref() {
+        params.push((m!("location"), l.clone()));
     }
     params
 }
@@ -251,6 +321,7 @@ impl Challonge {
     ///        "subdomain"
     /// );
     /// ```
+    #[allow(deprecated)]
     pub fn tournament_index(
         &self,
         state: &TournamentState,
@@ -259,11 +330,45 @@ impl Challonge {
         created_before: &Date<Local>,
         subdomain: &str,
     ) -> Result<TournamentIndex, Error> {
+        self.tournament_index_of(
+            state,
+            Some(tournament_type),
+            created_after,
+            created_before,
+            subdomain,
+        )
+    }
+
+    /// Retrieve a set of tournaments created with your account, of every tournament type.
+    #[allow(deprecated)]
+    pub fn tournament_index_any_type(
+        &self,
+        state: &TournamentState,
+        created_after: &Date<Local>,
+        created_before: &Date<Local>,
+        subdomain: &str,
+    ) -> Result<TournamentIndex, Error> {
+        self.tournament_index_of(state, None, created_after, created_before, subdomain)
+    }
+
+    #[allow(deprecated)]
+    fn tournament_index_of(
+        &self,
+        state: &TournamentState,
+        tournament_type: Option<&TournamentType>,
+        created_after: &Date<Local>,
+        created_before: &Date<Local>,
+        subdomain: &str,
+    ) -> Result<TournamentIndex, Error> {
+        let type_param = match tournament_type {
+            Some(t) => format!("&type={}", t.to_get_param()),
+            None => String::new(),
+        };
         let url = format!(
-            "{}/tournaments.json?state={}&type={}&created_after={}&created_before={}&subdomain={}",
+            "{}/tournaments.json?state={}{}&created_after={}&created_before={}&subdomain={}",
             API_BASE,
             state,
-            tournament_type.to_get_param(),
+            type_param,
             format_date!(created_after),
             format_date!(created_before),
             subdomain
@@ -288,8 +393,7 @@ impl Challonge {
         includes: &TournamentIncludes,
     ) -> Result<Tournament, Error> {
         let mut url =
-            reqwest::Url::parse(&format!("{}/tournaments/{}.json", API_BASE, id.to_string()))
-                .unwrap();
+            reqwest::Url::parse(&format!("{}/tournaments/{}.json", API_BASE, id)).unwrap();
 
         Challonge::add_tournament_includes(&mut url, includes);
         let response = self.client.get(url).send()?;
@@ -327,9 +431,14 @@ impl Challonge {
     ///            notify_users_when_matches_open: true,
     ///            notify_users_when_the_tournament_ends: true,
     ///            sequential_pairings: false,
-    ///            signup_cap: 4,
+    ///            tie_breaks: Vec::new(),
+    ///            accept_attachments: false,
+    ///            hide_forum: false,
+    ///            teams: false,
+    ///            team_convertable: false,
+    ///            signup_cap: Some(4),
     ///            start_at: UTC::now().add(Duration::weeks(2)),
-    ///            check_in_duration: 60,
+    ///            check_in_duration: Some(60),
     ///            grand_finals_modifier: None,
     /// };
     /// let t = c.create_tournament(&tc);
@@ -355,19 +464,85 @@ impl Challonge {
         id: &TournamentId,
         tournament: &TournamentCreate,
     ) -> Result<Tournament, Error> {
-        let url = &format!("{}/tournaments/{}.json", API_BASE, id.to_string());
+        let url = &format!("{}/tournaments/{}.json", API_BASE, id);
         let body = pairs_to_string(tc_to_pairs(tournament));
         let response = self.client.put(url).body(body).send()?;
         Tournament::decode(serde_json::from_reader(response)?)
     }
 
+    /// Update only the attributes set in the `TournamentUpdate`.
+    /// # Example
+    /// ```ignore
+    /// use challonge::{Challonge, TournamentCreate, TournamentIncludes, TournamentUpdate};
+    ///
+    /// let c = Challonge::new("myusername", "myapikey");
+    /// let t = c.get_tournament(&id, &TournamentIncludes::Matches)?;
+    /// // read, modify...
+    /// let before = TournamentCreate::from(&t);
+    /// let mut after = before.clone();
+    /// after.swiss_rounds(5).signup_cap(32);
+    /// // ...and write back only what changed.
+    /// c.update_tournament_fields(&t.id, &TournamentUpdate::changes(&before, &after))?;
+    /// ```
+    pub fn update_tournament_fields(
+        &self,
+        id: &TournamentId,
+        update: &TournamentUpdate,
+    ) -> Result<Tournament, Error> {
+        let url = &format!("{}/tournaments/{}.json", API_BASE, id);
+        let body = pairs_to_string(tu_to_pairs(update));
+        let response = self.client.put(url).body(body).send()?;
+        Tournament::decode(serde_json::from_reader(response)?)
+    }
+
     /// Deletes a tournament along with all its associated records. There is no undo, so use with care!
     pub fn delete_tournament(&self, id: &TournamentId) -> Result<(), Error> {
-        let url = &format!("{}/tournaments/{}.json", API_BASE, id.to_string());
+        let url = &format!("{}/tournaments/{}.json", API_BASE, id);
         let _ = self.client.delete(url).send()?;
         Ok(())
     }
 
+    /// Creates a new tournament with the settings of an existing one, under a new url and name.
+    /// The start time isn't copied (see `TournamentCreate::copy_of`). With `participants`, the participants are copied too, keeping their seeds. If they can't
+    /// be added, the new tournament is deleted again.
+    pub fn clone_tournament<U: Into<String>, N: Into<String>>(
+        &self,
+        id: &TournamentId,
+        new_url: U,
+        new_name: N,
+        participants: bool,
+    ) -> Result<Tournament, Error> {
+        let tournament = self.get_tournament(id, &TournamentIncludes::Participants)?;
+        let mut tc = TournamentCreate::copy_of(&tournament);
+        tc.url(new_url).name(new_name);
+        let mut ps: Vec<_> = match tournament.participants {
+            Some(ref index) if participants => index.0.iter().collect(),
+            _ => Vec::new(),
+        };
+        ps.sort_by_key(|p| p.seed);
+        self.create_tournament_with(&tc, ps.into_iter().map(ParticipantCreate::from).collect())
+    }
+
+    /// Creates a tournament and adds the participants to it, deleting the tournament again
+    /// when they can't be added.
+    pub(crate) fn create_tournament_with(
+        &self,
+        tournament: &TournamentCreate,
+        participants: Vec<ParticipantCreate>,
+    ) -> Result<Tournament, Error> {
+        let created = self.create_tournament(tournament)?;
+        if !participants.is_empty() {
+            // The tournament is new, so it has no participants yet.
+            if let Err(e) = self.create_participant_bulk_after(&created.id, participants, 0) {
+                if let Err(d) = self.delete_tournament(&created.id) {
+                    log::warn!("Couldn't delete the tournament {}: {:?}", created.id, d);
+                }
+                return Err(e);
+            }
+        }
+        Ok(created)
+    }
+
     /// This should be invoked after a tournament's check-in window closes before the tournament is started.
     ///
     /// 1. Marks participants who have not checked in as inactive.
@@ -424,11 +599,7 @@ impl Challonge {
 
     /// Retrieve a tournament's participant list.
     pub fn participant_index(&self, id: &TournamentId) -> Result<ParticipantIndex, Error> {
-        let url = &format!(
-            "{}/tournaments/{}/participants.json",
-            API_BASE,
-            id.to_string()
-        );
+        let url = &format!("{}/tournaments/{}/participants.json", API_BASE, id);
         let response = self.client.get(url).send()?;
         ParticipantIndex::decode(serde_json::from_reader(response)?)
     }
@@ -439,29 +610,43 @@ impl Challonge {
         id: &TournamentId,
         participant: &ParticipantCreate,
     ) -> Result<Participant, Error> {
-        let url = &format!(
-            "{}/tournaments/{}/participants.json",
-            API_BASE,
-            id.to_string()
-        );
+        let url = &format!("{}/tournaments/{}/participants.json", API_BASE, id);
         let body = pairs_to_string(pc_to_pairs(participant));
         let response = self.client.post(url).body(body).send()?;
         Participant::decode(serde_json::from_reader(response)?)
     }
 
+    /// Add a team with its members to a team tournament (up until it is started).
+    pub fn create_team(&self, id: &TournamentId, team: &TeamCreate) -> Result<Participant, Error> {
+        self.create_participant(id, &team.to_participant()?)
+    }
+
     /// Bulk add participants to a tournament (up until it is started).
+    /// Participants without an explicit seed are appended after the participants already in
+    /// the tournament, in the order of the list; finding how many there are takes one more
+    /// request. An explicit seed moves the participants at and below it one seed down.
     /// If an invalid participant is detected, bulk participant creation will halt and any previously added participants (from this API request) will be rolled back.
     pub fn create_participant_bulk(
         &self,
         id: &TournamentId,
         participants: Vec<ParticipantCreate>,
     ) -> Result<(), Error> {
-        let url = &format!(
-            "{}/tournaments/{}/participants/bulk_add.json",
-            API_BASE,
-            id.to_string()
-        );
-        let body = pairs_to_string(pcs_to_pairs(participants));
+        let existing = if participants.iter().any(|p| p.seed.is_none()) {
+            self.participant_index(id)?.0.len()
+        } else {
+            0
+        };
+        self.create_participant_bulk_after(id, participants, existing)
+    }
+
+    fn create_participant_bulk_after(
+        &self,
+        id: &TournamentId,
+        participants: Vec<ParticipantCreate>,
+        existing: usize,
+    ) -> Result<(), Error> {
+        let url = &format!("{}/tournaments/{}/participants/bulk_add.json", API_BASE, id);
+        let body = pairs_to_string(pcs_to_pairs(participants, existing));
         let response = self.client.post(url).body(body).send()?;
         let _: () = serde_json::from_reader(response)?;
         Ok(())
@@ -476,9 +661,7 @@ impl Challonge {
     ) -> Result<Participant, Error> {
         let mut url = reqwest::Url::parse(&format!(
             "{}/tournaments/{}/participants/{}.json",
-            API_BASE,
-            id.to_string(),
-            participant_id.0
+            API_BASE, id, participant_id.0
         ))
         .unwrap();
 
@@ -498,9 +681,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/participants/{}.json",
-            API_BASE,
-            id.to_string(),
-            participant_id.0
+            API_BASE, id, participant_id.0
         );
         let body = pairs_to_string(pc_to_pairs(participant));
         let _ = self.client.put(url).body(body).send()?;
@@ -515,9 +696,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/participants/{}/check_in.json",
-            API_BASE,
-            id.to_string(),
-            participant_id.0
+            API_BASE, id, participant_id.0
         );
         let _ = self.client.post(url).send()?;
         Ok(())
@@ -531,9 +710,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/participants/{}/undo_check_in.json",
-            API_BASE,
-            id.to_string(),
-            participant_id.0
+            API_BASE, id, participant_id.0
         );
         let _ = self.client.post(url).send()?;
         Ok(())
@@ -548,9 +725,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/participants/{}.json",
-            API_BASE,
-            id.to_string(),
-            participant_id.0
+            API_BASE, id, participant_id.0
         );
         let _ = self.client.delete(url).send()?;
         Ok(())
@@ -560,8 +735,7 @@ impl Challonge {
     pub fn randomize_participants(&self, id: &TournamentId) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/participants/randomize.json",
-            API_BASE,
-            id.to_string()
+            API_BASE, id
         );
         let _ = self.client.post(url).send()?;
         Ok(())
@@ -574,12 +748,8 @@ impl Challonge {
         state: Option<MatchState>,
         participant_id: Option<ParticipantId>,
     ) -> Result<MatchIndex, Error> {
-        let mut url = reqwest::Url::parse(&format!(
-            "{}/tournaments/{}/matches.json",
-            API_BASE,
-            id.to_string()
-        ))
-        .unwrap();
+        let mut url =
+            reqwest::Url::parse(&format!("{}/tournaments/{}/matches.json", API_BASE, id)).unwrap();
         {
             let mut pairs = url.query_pairs_mut();
             if let Some(s) = state {
@@ -602,9 +772,7 @@ impl Challonge {
     ) -> Result<Match, Error> {
         let mut url = reqwest::Url::parse(&format!(
             "{}/tournaments/{}/matches/{}.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0
+            API_BASE, id, match_id.0
         ))
         .unwrap();
 
@@ -619,6 +787,7 @@ impl Challonge {
     }
 
     /// Update/submit the score(s) for a match.
+    /// See `MatchUpdate::from_scores` and `MatchUpdate::validate` to keep the winner consistent with the scores.
     pub fn update_match(
         &self,
         id: &TournamentId,
@@ -627,9 +796,7 @@ impl Challonge {
     ) -> Result<Match, Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0
+            API_BASE, id, match_id.0
         );
         let body = pairs_to_string(mu_to_pairs(match_update));
         let response = self.client.put(url).body(body).send()?;
@@ -644,9 +811,7 @@ impl Challonge {
     ) -> Result<AttachmentIndex, Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}/attachments.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0
+            API_BASE, id, match_id.0
         );
         let response = self.client.get(url).send()?;
         AttachmentIndex::decode(serde_json::from_reader(response)?)
@@ -661,10 +826,7 @@ impl Challonge {
     ) -> Result<Attachment, Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}/attachments/{}.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0,
-            attachment_id.0
+            API_BASE, id, match_id.0, attachment_id.0
         );
         let response = self.client.get(url).send()?;
         Attachment::decode(serde_json::from_reader(response)?)
@@ -679,9 +841,7 @@ impl Challonge {
     ) -> Result<Attachment, Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}/attachments.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0
+            API_BASE, id, match_id.0
         );
         let body = pairs_to_string(at_to_pairs(attachment));
         let response = self.client.post(url).body(body).send()?;
@@ -698,10 +858,7 @@ impl Challonge {
     ) -> Result<Attachment, Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}/attachments/{}.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0,
-            attachment_id.0
+            API_BASE, id, match_id.0, attachment_id.0
         );
         let body = pairs_to_string(at_to_pairs(attachment));
         let response = self.client.put(url).body(body).send()?;
@@ -717,10 +874,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let url = &format!(
             "{}/tournaments/{}/matches/{}/attachments/{}.json",
-            API_BASE,
-            id.to_string(),
-            match_id.0,
-            attachment_id.0
+            API_BASE, id, match_id.0, attachment_id.0
         );
         let _ = self.client.delete(url).send()?;
         Ok(())
@@ -734,9 +888,7 @@ impl Challonge {
     ) -> Result<(), Error> {
         let mut url = reqwest::Url::parse(&format!(
             "{}/tournaments/{}/{}.json",
-            API_BASE,
-            id.to_string(),
-            endpoint
+            API_BASE, id, endpoint
         ))
         .unwrap();
         Challonge::add_tournament_includes(&mut url, includes);
@@ -766,3 +918,166 @@ impl Challonge {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::tournament::tests::{full_tournament_json, SAMPLE_TOURNAMENT};
+    use crate::{
+        mu_to_pairs, pc_to_pairs, pcs_to_pairs, tc_to_pairs, tu_to_pairs, FieldPairs, MatchUpdate,
+        ParticipantCreate, Tournament, TournamentCreate, TournamentUpdate,
+    };
+    use serde_json::Value;
+
+    /// Stores the tournament attributes of a request into a tournament JSON, like the API would.
+    fn store(mut value: Value, pairs: FieldPairs) -> Tournament {
+        let t = &mut value["tournament"];
+        let mut tie_breaks: Option<Vec<Value>> = None;
+        for (key, v) in pairs {
+            let key = &key["tournament[".len()..key.len() - 1];
+            if key == "tie_breaks][" {
+                // A single empty value clears the list.
+                let list = tie_breaks.get_or_insert_with(Vec::new);
+                if !v.is_empty() {
+                    list.push(Value::from(v));
+                }
+                continue;
+            }
+            t[key] = if v.is_empty() {
+                Value::Null
+            } else if key.contains("pts_for") {
+                Value::from(v)
+            } else if let Ok(b) = v.parse::<bool>() {
+                Value::from(b)
+            } else if let Ok(n) = v.parse::<u64>() {
+                Value::from(n)
+            } else {
+                Value::from(v)
+            };
+        }
+        if let Some(tie_breaks) = tie_breaks {
+            t["tie_breaks"] = Value::from(tie_breaks);
+        }
+        Tournament::decode(value).unwrap()
+    }
+
+    #[test]
+    fn test_participant_create_pairs() {
+        let mut pc = ParticipantCreate::new();
+        pc.name("Participant #1");
+        assert_eq!(
+            pc_to_pairs(&pc),
+            vec![("participant[name]", "Participant #1".to_owned())]
+        );
+
+        pc.invite_name_or_email("user@example.com").seed(3);
+        assert_eq!(
+            pc_to_pairs(&pc),
+            vec![
+                ("participant[name]", "Participant #1".to_owned()),
+                (
+                    "participant[invite_name_or_email]",
+                    "user@example.com".to_owned()
+                ),
+                ("participant[seed]", "3".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_participant_bulk_pairs_keep_order() {
+        let mut first = ParticipantCreate::new();
+        first.name("A");
+        let mut second = ParticipantCreate::new();
+        second.name("B").seed(1);
+        let mut third = ParticipantCreate::new();
+        third.email("c@example.com");
+
+        // A is added first, then B takes seed 1 and moves A down.
+        assert_eq!(
+            pcs_to_pairs(vec![first.clone(), second.clone(), third.clone()], 0),
+            vec![
+                ("participant[][seed]", "1".to_owned()),
+                ("participant[][name]", "A".to_owned()),
+                ("participant[][seed]", "1".to_owned()),
+                ("participant[][name]", "B".to_owned()),
+                ("participant[][seed]", "3".to_owned()),
+                ("participant[][email]", "c@example.com".to_owned()),
+            ]
+        );
+
+        // With four participants in the tournament, A and C are appended after them.
+        let seeds: Vec<String> = pcs_to_pairs(vec![first, second, third], 4)
+            .into_iter()
+            .filter(|(key, _)| *key == "participant[][seed]")
+            .map(|(_, seed)| seed)
+            .collect();
+        assert_eq!(seeds, vec!["5", "1", "7"]);
+    }
+
+    #[test]
+    fn test_match_update_location_pairs() {
+        let mut mu = MatchUpdate::new();
+        mu.location("Stream");
+        assert_eq!(
+            mu_to_pairs(&mu),
+            vec![
+                ("match[scores_csv]", String::new()),
+                ("match[location]", "Stream".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tournament_create_round_trip() {
+        let samples = [
+            serde_json::from_str(SAMPLE_TOURNAMENT).unwrap(),
+            full_tournament_json(),
+        ];
+        for value in samples.iter() {
+            let tc = TournamentCreate::from(&Tournament::decode(value.clone()).unwrap());
+            let stored = store(value.clone(), tc_to_pairs(&tc));
+            assert_eq!(TournamentCreate::from(&stored), tc);
+        }
+    }
+
+    #[test]
+    fn test_tournament_update_round_trip() {
+        let value = full_tournament_json();
+        let before = TournamentCreate::from(&Tournament::decode(value.clone()).unwrap());
+        let mut after = before.clone();
+        after.swiss_rounds(3).private(true);
+        after.signup_cap = None;
+        after.grand_finals_modifier = None;
+
+        let update = TournamentUpdate::changes(&before, &after);
+        let pairs = tu_to_pairs(&update);
+        assert_eq!(
+            pairs,
+            vec![
+                ("tournament[swiss_rounds]", "3".to_owned()),
+                ("tournament[private]", "true".to_owned()),
+                ("tournament[signup_cap]", String::new()),
+                ("tournament[grand_finals_modifier]", String::new()),
+            ]
+        );
+        assert_eq!(TournamentCreate::from(&store(value, pairs)), after);
+    }
+
+    #[test]
+    fn test_tournament_update_clears_tie_breaks() {
+        let value = full_tournament_json();
+        let before = TournamentCreate::from(&Tournament::decode(value.clone()).unwrap());
+        assert!(!before.tie_breaks.is_empty());
+        let mut after = before.clone();
+        after.tie_breaks = Vec::new();
+
+        let pairs = tu_to_pairs(&TournamentUpdate::changes(&before, &after));
+        assert_eq!(pairs, vec![("tournament[tie_breaks][]", String::new())]);
+        assert_eq!(TournamentCreate::from(&store(value, pairs)), after);
+
+        // A new tournament without tie breaks gets Challonge's defaults.
+        assert!(tc_to_pairs(&after)
+            .iter()
+            .all(|(key, _)| *key != "tournament[tie_breaks][]"));
+    }
+}
@@ -0,0 +1,421 @@
+//! Tournament lifecycle.
+//!
+//! The tournament actions of the API are only valid in some states, and some of them have
+//! further requirements. `Lifecycle` tells, for a tournament with its participants and
+//! matches, which actions are legal and why the others are not. `Checked` performs the
+//! actions only after checking them against the current state of the tournament.
+//!
+//! | Action            | Leads to   | Valid in |
+//! |-------------------|------------|----------|
+//! | process check-ins | checked_in | checking_in |
+//! | abort check-ins   | pending    | checking_in, checked_in |
+//! | start             | underway   | pending, checking_in, checked_in |
+//! | finalize          | complete   | underway, awaiting_review |
+//! | reset             | pending    | group_stages_underway, group_stages_finalized, underway, awaiting_review, complete |
+
+use std::fmt;
+
+use crate::error::Error;
+use crate::matches::{Index as MatchIndex, MatchId, MatchState};
+use crate::participants::Index as ParticipantIndex;
+use crate::tournament::{Tournament, TournamentId, TournamentIncludes, TournamentStatus};
+use crate::Challonge;
+
+/// A tournament action.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// `Challonge::tournament_process_checkins`
+    ProcessCheckins,
+
+    /// `Challonge::tournament_abort_checkins`
+    AbortCheckins,
+
+    /// `Challonge::tournament_start`
+    Start,
+
+    /// `Challonge::tournament_finalize`
+    Finalize,
+
+    /// `Challonge::tournament_reset`
+    Reset,
+}
+impl Action {
+    /// All the actions.
+    pub const ALL: [Action; 5] = [
+        Action::ProcessCheckins,
+        Action::AbortCheckins,
+        Action::Start,
+        Action::Finalize,
+        Action::Reset,
+    ];
+
+    /// The states the action is valid in.
+    pub fn valid_in(self) -> &'static [TournamentStatus] {
+        use TournamentStatus::*;
+        match self {
+            Action::ProcessCheckins => &[CheckingIn],
+            Action::AbortCheckins => &[CheckingIn, CheckedIn],
+            Action::Start => &[Pending, CheckingIn, CheckedIn],
+            Action::Finalize => &[Underway, AwaitingReview],
+            Action::Reset => &[
+                GroupStagesUnderway,
+                GroupStagesFinalized,
+                Underway,
+                AwaitingReview,
+                Complete,
+            ],
+        }
+    }
+
+    /// The state of the tournament after the action.
+    pub fn target(self) -> TournamentStatus {
+        match self {
+            Action::ProcessCheckins => TournamentStatus::CheckedIn,
+            Action::AbortCheckins | Action::Reset => TournamentStatus::Pending,
+            Action::Start => TournamentStatus::Underway,
+            Action::Finalize => TournamentStatus::Complete,
+        }
+    }
+}
+impl fmt::Display for Action {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Action::ProcessCheckins => fmt.write_str("process check-ins"),
+            Action::AbortCheckins => fmt.write_str("abort check-ins"),
+            Action::Start => fmt.write_str("start"),
+            Action::Finalize => fmt.write_str("finalize"),
+            Action::Reset => fmt.write_str("reset"),
+        }
+    }
+}
+
+/// Why an action is not legal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    /// The action is not valid in the tournament's state
+    WrongState(TournamentStatus),
+
+    /// A tournament needs at least 2 active participants to start
+    NotEnoughParticipants(usize),
+
+    /// All matches must be complete to finalize the tournament
+    IncompleteMatches(Vec<MatchId>),
+}
+impl fmt::Display for Reason {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Reason::WrongState(ref state) => write!(fmt, "the tournament is {}", state),
+            Reason::NotEnoughParticipants(n) => write!(
+                fmt,
+                "the tournament has {} active participant(s), at least 2 are needed",
+                n
+            ),
+            Reason::IncompleteMatches(ref ids) => {
+                write!(fmt, "{} match(es) are not complete", ids.len())
+            }
+        }
+    }
+}
+
+/// An illegal action with the reasons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refusal {
+    /// The action
+    pub action: Action,
+    /// Why it is not legal
+    pub reasons: Vec<Reason>,
+}
+impl fmt::Display for Refusal {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "can't {} the tournament: ", self.action)?;
+        let mut sep = "";
+        for r in &self.reasons {
+            write!(fmt, "{}{}", sep, r)?;
+            sep = ", ";
+        }
+        Ok(())
+    }
+}
+
+/// The lifecycle of a tournament.
+#[derive(Debug, Copy, Clone)]
+pub struct Lifecycle<'a> {
+    tournament: &'a Tournament,
+    participants: &'a ParticipantIndex,
+    matches: &'a MatchIndex,
+}
+impl<'a> Lifecycle<'a> {
+    /// Creates the lifecycle of the tournament with its participants and matches.
+    pub fn new(
+        tournament: &'a Tournament,
+        participants: &'a ParticipantIndex,
+        matches: &'a MatchIndex,
+    ) -> Lifecycle<'a> {
+        Lifecycle {
+            tournament,
+            participants,
+            matches,
+        }
+    }
+
+    /// Checks the action, returns the state of the tournament after it.
+    pub fn check(&self, action: Action) -> Result<TournamentStatus, Refusal> {
+        let mut reasons = Vec::new();
+        let state = &self.tournament.state;
+        if !action.valid_in().contains(state) {
+            reasons.push(Reason::WrongState(state.clone()));
+        }
+        match action {
+            Action::Start => {
+                let active = self
+                    .participants
+                    .0
+                    .iter()
+                    .filter(|p| p.active && !p.on_waiting_list)
+                    .filter(|p| *state != TournamentStatus::CheckedIn || p.checked_in)
+                    .count();
+                if active < 2 {
+                    reasons.push(Reason::NotEnoughParticipants(active));
+                }
+            }
+            Action::Finalize => {
+                let mut incomplete: Vec<MatchId> = self
+                    .matches
+                    .0
+                    .iter()
+                    .filter(|m| m.state != MatchState::Complete && !m.optional)
+                    .map(|m| m.id)
+                    .collect();
+                incomplete.sort();
+                if !incomplete.is_empty() {
+                    reasons.push(Reason::IncompleteMatches(incomplete));
+                }
+            }
+            _ => {}
+        }
+        if reasons.is_empty() {
+            Ok(action.target())
+        } else {
+            Err(Refusal { action, reasons })
+        }
+    }
+
+    /// The legal actions.
+    pub fn allowed(&self) -> Vec<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .filter(|&a| self.check(a).is_ok())
+            .collect()
+    }
+}
+
+/// Performs tournament actions after checking them.
+///
+/// The tournament is fetched with its participants or matches before every action, and the
+/// action fails with `Error::Refused` without calling the API if it's not legal.
+#[derive(Copy, Clone)]
+pub struct Checked<'a>(&'a Challonge);
+impl<'a> Checked<'a> {
+    /// Creates checked actions through the client.
+    pub fn new(c: &'a Challonge) -> Checked<'a> {
+        Checked(c)
+    }
+
+    /// Checks the action against the current state of the tournament.
+    pub fn check(&self, id: &TournamentId, action: Action) -> Result<TournamentStatus, Error> {
+        let includes = if action == Action::Finalize {
+            TournamentIncludes::Matches
+        } else {
+            TournamentIncludes::Participants
+        };
+        let mut tournament = self.0.get_tournament(id, &includes)?;
+        let participants = tournament
+            .participants
+            .take()
+            .unwrap_or_else(|| ParticipantIndex(Vec::new()));
+        let matches = tournament
+            .matches
+            .take()
+            .unwrap_or_else(|| MatchIndex(Vec::new()));
+        Lifecycle::new(&tournament, &participants, &matches)
+            .check(action)
+            .map_err(Error::Refused)
+    }
+
+    /// Checks and performs the action.
+    pub fn perform(
+        &self,
+        id: &TournamentId,
+        action: Action,
+        includes: &TournamentIncludes,
+    ) -> Result<(), Error> {
+        self.check(id, action)?;
+        match action {
+            Action::ProcessCheckins => self.0.tournament_process_checkins(id, includes),
+            Action::AbortCheckins => self.0.tournament_abort_checkins(id, includes),
+            Action::Start => self.0.tournament_start(id, includes),
+            Action::Finalize => self.0.tournament_finalize(id, includes),
+            Action::Reset => self.0.tournament_reset(id, includes),
+        }
+    }
+
+    /// Checked `Challonge::tournament_process_checkins`.
+    pub fn process_checkins(
+        &self,
+        id: &TournamentId,
+        includes: &TournamentIncludes,
+    ) -> Result<(), Error> {
+        self.perform(id, Action::ProcessCheckins, includes)
+    }
+
+    /// Checked `Challonge::tournament_abort_checkins`.
+    pub fn abort_checkins(
+        &self,
+        id: &TournamentId,
+        includes: &TournamentIncludes,
+    ) -> Result<(), Error> {
+        self.perform(id, Action::AbortCheckins, includes)
+    }
+
+    /// Checked `Challonge::tournament_start`.
+    pub fn start(&self, id: &TournamentId, includes: &TournamentIncludes) -> Result<(), Error> {
+        self.perform(id, Action::Start, includes)
+    }
+
+    /// Checked `Challonge::tournament_finalize`.
+    pub fn finalize(&self, id: &TournamentId, includes: &TournamentIncludes) -> Result<(), Error> {
+        self.perform(id, Action::Finalize, includes)
+    }
+
+    /// Checked `Challonge::tournament_reset`.
+    pub fn reset(&self, id: &TournamentId, includes: &TournamentIncludes) -> Result<(), Error> {
+        self.perform(id, Action::Reset, includes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::lifecycle::{Action, Lifecycle, Reason, Refusal};
+    use crate::matches::{Index as MatchIndex, MatchId};
+    use crate::participants::tests::make_participant;
+    use crate::participants::Index as ParticipantIndex;
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::TournamentStatus;
+
+    #[test]
+    fn test_start() {
+        let mut t = sample_tournament();
+        t.state = TournamentStatus::Pending;
+        let mut waiting = make_participant(2, "Bob", 2);
+        waiting.on_waiting_list = true;
+        let mut participants = ParticipantIndex(vec![make_participant(1, "Alice", 1), waiting]);
+        let matches = MatchIndex(Vec::new());
+
+        let refusal = Lifecycle::new(&t, &participants, &matches)
+            .check(Action::Start)
+            .unwrap_err();
+        assert_eq!(refusal.reasons, vec![Reason::NotEnoughParticipants(1)]);
+        assert_eq!(
+            refusal.to_string(),
+            "can't start the tournament: the tournament has 1 active participant(s), at least 2 are needed"
+        );
+
+        participants.0.push(make_participant(3, "Carol", 3));
+        let lifecycle = Lifecycle::new(&t, &participants, &matches);
+        assert_eq!(
+            lifecycle.check(Action::Start),
+            Ok(TournamentStatus::Underway)
+        );
+        assert_eq!(lifecycle.allowed(), vec![Action::Start]);
+
+        // After processing the check-ins only the checked in participants play.
+        t.state = TournamentStatus::CheckedIn;
+        participants.0[0].checked_in = true;
+        let lifecycle = Lifecycle::new(&t, &participants, &matches);
+        assert_eq!(lifecycle.allowed(), vec![Action::AbortCheckins]);
+    }
+
+    #[test]
+    fn test_finalize() {
+        let t = sample_tournament();
+        let participants = ParticipantIndex(Vec::new());
+        let done = make_match(
+            1,
+            "A",
+            1,
+            (Some(1), None),
+            (Some(2), None),
+            "complete",
+            Some(1),
+            "1-0",
+        );
+        let open = make_match(
+            2,
+            "B",
+            2,
+            (Some(1), None),
+            (Some(3), None),
+            "open",
+            None,
+            "",
+        );
+        let matches = MatchIndex(vec![open, done.clone()]);
+        let lifecycle = Lifecycle::new(&t, &participants, &matches);
+        assert_eq!(
+            lifecycle.check(Action::Finalize),
+            Err(Refusal {
+                action: Action::Finalize,
+                reasons: vec![Reason::IncompleteMatches(vec![MatchId(2)])],
+            })
+        );
+        assert_eq!(lifecycle.allowed(), vec![Action::Reset]);
+        let refusal = lifecycle.check(Action::Start).unwrap_err();
+        assert_eq!(
+            refusal.reasons,
+            vec![
+                Reason::WrongState(TournamentStatus::Underway),
+                Reason::NotEnoughParticipants(0)
+            ]
+        );
+
+        // The unplayed grand finals reset doesn't block finalizing.
+        let mut reset = make_match(
+            3,
+            "C",
+            3,
+            (Some(1), None),
+            (Some(2), None),
+            "pending",
+            None,
+            "",
+        );
+        reset.optional = true;
+        let matches = MatchIndex(vec![done, reset]);
+        let lifecycle = Lifecycle::new(&t, &participants, &matches);
+        assert_eq!(
+            lifecycle.check(Action::Finalize),
+            Ok(TournamentStatus::Complete)
+        );
+    }
+
+    #[test]
+    fn test_reset_group_stages() {
+        let mut t = sample_tournament();
+        let participants = ParticipantIndex(Vec::new());
+        let matches = MatchIndex(Vec::new());
+        for state in &[
+            TournamentStatus::GroupStagesUnderway,
+            TournamentStatus::GroupStagesFinalized,
+        ] {
+            t.state = state.clone();
+            let lifecycle = Lifecycle::new(&t, &participants, &matches);
+            assert_eq!(
+                lifecycle.check(Action::Reset),
+                Ok(TournamentStatus::Pending)
+            );
+            assert_eq!(lifecycle.allowed(), vec![Action::Reset]);
+        }
+    }
+}
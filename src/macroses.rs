@@ -37,7 +37,7 @@ macro_rules! m {
 macro_rules! builder {
     ($field:ident, $field_type:ty) => {
         /// A builder method for $field with `$field_type` type.
-        pub fn $field<'a>(&'a mut self, $field: $field_type) -> &'a mut Self {
+        pub fn $field(&mut self, $field: $field_type) -> &mut Self {
             self.$field = $field;
             self
         }
@@ -47,7 +47,7 @@ macro_rules! builder {
 macro_rules! builder_s {
     ($field:ident) => {
         /// A builder method for $field with `String` type.
-        pub fn $field<'a, S: Into<String>>(&'a mut self, $field: S) -> &'a mut Self {
+        pub fn $field<S: Into<String>>(&mut self, $field: S) -> &mut Self {
             self.$field = $field.into();
             self
         }
@@ -57,7 +57,7 @@ macro_rules! builder_s {
 macro_rules! builder_o {
     ($field:ident, $field_type:ty) => {
         /// A builder method for $field with `Option` type.
-        pub fn $field<'a>(&'a mut self, $field: $field_type) -> &'a mut Self {
+        pub fn $field(&mut self, $field: $field_type) -> &mut Self {
             self.$field = Some($field.into());
             self
         }
@@ -67,9 +67,25 @@ macro_rules! builder_o {
 macro_rules! builder_so {
     ($field:ident) => {
         /// A builder method for $field with `Option<String>` type.
-        pub fn $field<'a, S: Into<String>>(&'a mut self, $field: S) -> &'a mut Self {
+        pub fn $field<S: Into<String>>(&mut self, $field: S) -> &mut Self {
             self.$field = Some($field.into());
             self
         }
     };
 }
+
+macro_rules! numeric_id {
+    ($id:ident) => {
+        impl std::fmt::Display for $id {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.fmt(fmt)
+            }
+        }
+        impl std::str::FromStr for $id {
+            type Err = std::num::ParseIntError;
+            fn from_str(s: &str) -> Result<$id, Self::Err> {
+                Ok($id(s.trim().parse()?))
+            }
+        }
+    };
+}
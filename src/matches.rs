@@ -6,31 +6,42 @@ use std::fmt;
 use std::str::FromStr;
 
 use crate::error::Error;
-use crate::participants::ParticipantId;
-use crate::tournament::TournamentId;
-use crate::util::{decode_array, into_map, remove};
+use crate::participants::{GroupId, ParticipantId};
+use crate::tournament::{TournamentId, TournamentType};
+use crate::util::{decode_array, decode_time, into_map, remove, remove_or_null};
 
 /// Represents a pair of scores - for player 1 and player 2 respectively.
+/// Challonge allows negative scores, so both of them are signed.
 #[derive(Debug, Clone, PartialEq)]
-pub struct MatchScore(pub u64, pub u64);
+pub struct MatchScore(pub i64, pub i64);
 impl MatchScore {
-    /// Decodes `MatchScore` from JSON.
+    /// Decodes `MatchScore` from a string like `"3-1"` or `"-1--3"`.
+    /// Fails if any of the scores is absent or is not a number.
     pub fn decode(string: &str) -> Result<MatchScore, Error> {
-        let mut parts = string.trim().split('-');
-        Ok(MatchScore(
-            parts
-                .next()
-                .unwrap_or("")
-                .trim()
-                .parse::<u64>()
-                .unwrap_or(0),
-            parts
-                .next()
-                .unwrap_or("")
-                .trim()
-                .parse::<u64>()
-                .unwrap_or(0),
-        ))
+        let (first, second) = split_score(string);
+        match (first.parse::<i64>(), second.map(str::parse::<i64>)) {
+            (Ok(first), Some(Ok(second))) => Ok(MatchScore(first, second)),
+            _ => Err(Error::Decode(
+                "Invalid match score",
+                Value::String(string.to_owned()),
+            )),
+        }
+    }
+
+    /// Decodes `MatchScore` from possibly malformed legacy data.
+    /// Scores which can't be parsed are treated as zero.
+    pub fn decode_lenient(string: &str) -> MatchScore {
+        let (first, second) = split_score(string);
+        MatchScore(
+            first.parse::<i64>().unwrap_or(0),
+            second.and_then(|s| s.parse::<i64>().ok()).unwrap_or(0),
+        )
+    }
+}
+impl FromStr for MatchScore {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<MatchScore, Error> {
+        MatchScore::decode(s)
     }
 }
 impl fmt::Display for MatchScore {
@@ -39,20 +50,64 @@ impl fmt::Display for MatchScore {
     }
 }
 
+/// Splits a score string into the scores of player 1 and player 2.
+/// A minus directly followed by a digit at the beginning of a score is its sign.
+fn split_score(string: &str) -> (&str, Option<&str>) {
+    let string = string.trim();
+    let is_sign = |s: &str| s.starts_with('-') && s[1..].starts_with(|c: char| c.is_ascii_digit());
+    let start = if is_sign(string) { 1 } else { 0 };
+    match string[start..].find('-') {
+        Some(pos) => (
+            string[..start + pos].trim(),
+            Some(string[start + pos + 1..].trim()),
+        ),
+        None => (string, None),
+    }
+}
+
 /// A list of scores.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MatchScores(pub Vec<MatchScore>);
 impl MatchScores {
-    /// Decodes `MatchScores` from JSON.
+    /// Decodes `MatchScores` from possibly malformed legacy data.
+    /// Empty entries are skipped and scores which can't be parsed are treated as zero.
+    /// Use `str::parse` to get an error for malformed scores instead.
     pub fn decode(string: String) -> MatchScores {
-        let mut scores = Vec::new();
-        let iter = string.split(',');
-        for s in iter {
-            if let Ok(ms) = MatchScore::decode(s.trim()) {
-                scores.push(ms);
+        MatchScores(
+            string
+                .split(',')
+                .filter(|s| !s.trim().is_empty())
+                .map(MatchScore::decode_lenient)
+                .collect(),
+        )
+    }
+
+    /// Returns the number of games won by player 1 and player 2 respectively.
+    pub fn games_won(&self) -> (u64, u64) {
+        self.0.iter().fold((0, 0), |(p1, p2), s| {
+            if s.0 > s.1 {
+                (p1 + 1, p2)
+            } else if s.1 > s.0 {
+                (p1, p2 + 1)
+            } else {
+                (p1, p2)
             }
+        })
+    }
+}
+impl FromStr for MatchScores {
+    type Err = Error;
+
+    /// Strictly parses comma separated scores like `"3-1,2-3"`. An empty string means no scores.
+    fn from_str(s: &str) -> Result<MatchScores, Error> {
+        if s.trim().is_empty() {
+            return Ok(MatchScores(Vec::new()));
         }
-        MatchScores(scores)
+        Ok(MatchScores(
+            s.split(',')
+                .map(MatchScore::decode)
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
     }
 }
 impl fmt::Display for MatchScores {
@@ -60,7 +115,7 @@ impl fmt::Display for MatchScores {
         let mut scores = String::new();
         let mut sep = "";
         for s in &self.0 {
-            scores.push_str(&format!("{}{}", sep, s.to_string()));
+            scores.push_str(&format!("{}{}", sep, s));
             sep = ",";
         }
         fmt.write_str(&scores)
@@ -68,8 +123,9 @@ impl fmt::Display for MatchScores {
 }
 
 /// Represents an ID of a match
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MatchId(pub u64);
+numeric_id!(MatchId);
 
 /// Current match state.
 #[derive(Debug, Clone, PartialEq)]
@@ -120,6 +176,29 @@ impl Index {
     }
 }
 
+/// A winner of a match.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MatchWinner {
+    /// The participant won the match.
+    Participant(ParticipantId),
+
+    /// The match is a tie (Round Robin and Swiss only).
+    Tie,
+}
+impl From<ParticipantId> for MatchWinner {
+    fn from(id: ParticipantId) -> MatchWinner {
+        MatchWinner::Participant(id)
+    }
+}
+impl fmt::Display for MatchWinner {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MatchWinner::Participant(id) => id.fmt(fmt),
+            MatchWinner::Tie => fmt.write_str("tie"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 /// NOTE: If you're updating winner_id, scores_csv must also be provided. You may, however, update score_csv without providing winner_id for live score updates.
 pub struct MatchUpdate {
@@ -128,13 +207,16 @@ pub struct MatchUpdate {
 
     /// The participant ID of the winner or "tie" if applicable (Round Robin and Swiss).
     /// NOTE: If you change the outcome of a completed match, all matches in the bracket that branch from the updated match will be reset.
-    pub winner_id: Option<ParticipantId>,
+    pub winner_id: Option<MatchWinner>,
 
     /// Overwrites the number of votes for player 1
     pub player1_votes: Option<u64>,
 
     /// Overwrites the number of votes for player 2
     pub player2_votes: Option<u64>,
+
+    /// Where the match is played, for example a station or a table
+    pub location: Option<String>,
 }
 impl MatchUpdate {
     /// Creates new `MatchUpdate` structure with default values.
@@ -144,13 +226,93 @@ impl MatchUpdate {
             winner_id: None,
             player1_votes: None,
             player2_votes: None,
+            location: None,
         }
     }
 
+    /// Creates a `MatchUpdate` for the match with the winner inferred from the scores:
+    /// the player who won more games wins the match, equal number of games won is a tie.
+    /// No winner is set if there are no scores.
+    /// Ties are only allowed in Round Robin and Swiss, so tied scores are an error for
+    /// the matches of other tournament types.
+    pub fn from_scores(
+        m: &Match,
+        scores: MatchScores,
+        tournament_type: &TournamentType,
+    ) -> Result<MatchUpdate, Error> {
+        let winner_id = scores_winner(m, &scores);
+        if winner_id == Some(MatchWinner::Tie) {
+            check_tie(tournament_type)?;
+        }
+        Ok(MatchUpdate {
+            scores_csv: scores,
+            winner_id,
+            player1_votes: None,
+            player2_votes: None,
+            location: None,
+        })
+    }
+
+    /// Checks that the update is consistent with the match of a tournament of the type: the
+    /// winner must be one of the match's players, or a tie in Round Robin and Swiss, the
+    /// scores must be provided along with the winner and must designate the same winner.
+    pub fn validate(&self, m: &Match, tournament_type: &TournamentType) -> Result<(), Error> {
+        let winner = match self.winner_id {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+        match winner {
+            MatchWinner::Participant(id) => {
+                if Some(id) != m.player1.id && Some(id) != m.player2.id {
+                    return Err(Error::Api("The winner is not a player of the match"));
+                }
+            }
+            MatchWinner::Tie => check_tie(tournament_type)?,
+        }
+        if self.scores_csv.0.is_empty() {
+            return Err(Error::Api("Scores must be provided along with the winner"));
+        }
+        if scores_winner(m, &self.scores_csv) != Some(winner) {
+            return Err(Error::Api("The winner contradicts the scores"));
+        }
+        Ok(())
+    }
+
     builder!(scores_csv, MatchScores);
     builder_o!(winner_id, ParticipantId);
     builder_o!(player1_votes, u64);
     builder_o!(player2_votes, u64);
+    builder_so!(location);
+
+    /// Marks the match as a tie (Round Robin and Swiss only).
+    pub fn tie(&mut self) -> &mut Self {
+        self.winner_id = Some(MatchWinner::Tie);
+        self
+    }
+}
+
+/// Fails unless matches of the tournament type can end in a tie.
+fn check_tie(tournament_type: &TournamentType) -> Result<(), Error> {
+    match *tournament_type {
+        TournamentType::RoundRobin | TournamentType::Swiss => Ok(()),
+        TournamentType::SingleElimination | TournamentType::DoubleElimination => {
+            Err(Error::Api("Elimination matches can't end in a tie"))
+        }
+    }
+}
+
+/// Returns the winner designated by the scores, `None` if there are no scores or the
+/// winning player isn't known yet.
+fn scores_winner(m: &Match, scores: &MatchScores) -> Option<MatchWinner> {
+    if scores.0.is_empty() {
+        return None;
+    }
+    let (player1, player2) = scores.games_won();
+    match player1.cmp(&player2) {
+        std::cmp::Ordering::Greater => m.player1.id.map(MatchWinner::Participant),
+        std::cmp::Ordering::Less => m.player2.id.map(MatchWinner::Participant),
+        std::cmp::Ordering::Equal => Some(MatchWinner::Tie),
+    }
 }
 
 impl Default for MatchUpdate {
@@ -162,8 +324,9 @@ impl Default for MatchUpdate {
 /// Player data in match.
 #[derive(Debug, Clone)]
 pub struct Player {
-    /// Unique participant identifier
-    pub id: ParticipantId,
+    /// Unique participant identifier, `None` until the player is known
+    /// (e.g. while the prerequisite match isn't completed).
+    pub id: Option<ParticipantId>,
     /// ???
     pub is_prereq_match_loser: bool,
     /// ???
@@ -173,23 +336,18 @@ pub struct Player {
 }
 impl Player {
     /// Decodes `Player` from JSON
-    pub fn decode(
-        mut map: &mut serde_json::Map<String, Value>,
-        prefix: &str,
-    ) -> Result<Player, Error> {
+    pub fn decode(map: &mut serde_json::Map<String, Value>, prefix: &str) -> Result<Player, Error> {
         Ok(Player {
-            id: ParticipantId(
-                remove(&mut map, &format!("{}id", prefix))?
-                    .as_u64()
-                    .unwrap_or(0),
-            ),
-            is_prereq_match_loser: remove(&mut map, &format!("{}is_prereq_match_loser", prefix))?
+            id: remove(map, &format!("{}id", prefix))?
+                .as_u64()
+                .map(ParticipantId),
+            is_prereq_match_loser: remove(map, &format!("{}is_prereq_match_loser", prefix))?
                 .as_bool()
                 .unwrap_or(false),
-            prereq_match_id: remove(&mut map, &format!("{}prereq_match_id", prefix))?
+            prereq_match_id: remove(map, &format!("{}prereq_match_id", prefix))?
                 .as_u64()
                 .map(MatchId),
-            votes: remove(&mut map, &format!("{}votes", prefix))?
+            votes: remove(map, &format!("{}votes", prefix))?
                 .as_u64()
                 .unwrap_or(0),
         })
@@ -199,10 +357,21 @@ impl Player {
 /// Challonge `Match` definition.
 #[derive(Debug, Clone)]
 pub struct Match {
-    // attachment_count: ,
+    /// Number of attachments of the match.
+    pub attachment_count: u64,
+
     /// Holds a time when match was created.
     pub created_at: DateTime<FixedOffset>,
-    // group_id: ,
+
+    /// Holds a time when match was completed.
+    pub completed_at: Option<DateTime<FixedOffset>>,
+
+    /// Match was won by forfeit.
+    pub forfeited: bool,
+
+    /// Id of the group the match belongs to (for tournaments with group stages).
+    pub group_id: Option<GroupId>,
+
     /// Does the match has an attachment?
     pub has_attachment: bool,
 
@@ -211,10 +380,16 @@ pub struct Match {
 
     /// ???
     pub identifier: String,
-    // location:
+
+    /// Location (station, table, court) where the match is played.
+    pub location: Option<String>,
+
     /// An id of user which lost the match
     pub loser_id: Option<ParticipantId>,
 
+    /// The match is optional (e.g. the grand finals reset match).
+    pub optional: bool,
+
     /// Information about first player
     pub player1: Player,
 
@@ -222,25 +397,35 @@ pub struct Match {
     pub player2: Player,
 
     /// Number of current round of the match.
-    pub round: u64,
-    // // // scheduled_time:
+    /// Losers bracket rounds of double elimination tournaments are negative.
+    pub round: i64,
+
+    /// Time for which the match is scheduled.
+    pub scheduled_time: Option<DateTime<FixedOffset>>,
+
     /// Holds a time when match was started.
     pub started_at: Option<DateTime<FixedOffset>>,
 
     /// State of the match.
     pub state: MatchState,
 
+    /// Order in which Challonge suggests to play the matches.
+    pub suggested_play_order: Option<u64>,
+
     /// Id of a tournament to which this match belongs.
     pub tournament_id: TournamentId,
-    // // underway_at:
+
+    /// Holds a time when match was marked as underway.
+    pub underway_at: Option<DateTime<FixedOffset>>,
+
     /// A time when match was updated last time.
     pub updated_at: DateTime<FixedOffset>,
 
     /// An id of user which won the match
     pub winner_id: Option<ParticipantId>,
 
-    /// ???
-    pub prerequisite_match_ids_csv: String,
+    /// Ids of the matches which must be completed before this one.
+    pub prerequisite_match_ids: Vec<MatchId>,
 
     /// Match scores (pairs of score for first and second player)
     pub scores_csv: MatchScores,
@@ -260,10 +445,18 @@ impl Match {
         }
 
         Ok(Match {
+            attachment_count: remove_or_null(&mut tv, "attachment_count")
+                .as_u64()
+                .unwrap_or(0),
             created_at: DateTime::parse_from_rfc3339(
                 remove(&mut tv, "created_at")?.as_str().unwrap_or(""),
             )
             .unwrap(),
+            completed_at: decode_time(remove_or_null(&mut tv, "completed_at")),
+            forfeited: remove_or_null(&mut tv, "forfeited")
+                .as_bool()
+                .unwrap_or(false),
+            group_id: remove_or_null(&mut tv, "group_id").as_u64().map(GroupId),
             has_attachment: remove(&mut tv, "has_attachment")?
                 .as_bool()
                 .unwrap_or(false),
@@ -272,23 +465,32 @@ impl Match {
                 .as_str()
                 .unwrap_or("")
                 .to_owned(),
+            location: remove_or_null(&mut tv, "location")
+                .as_str()
+                .map(|l| l.to_owned()),
             loser_id: remove(&mut tv, "loser_id")?.as_u64().map(ParticipantId),
+            optional: remove_or_null(&mut tv, "optional")
+                .as_bool()
+                .unwrap_or(false),
             player1: Player::decode(&mut tv, "player1_").unwrap(),
             player2: Player::decode(&mut tv, "player2_").unwrap(),
-            round: remove(&mut tv, "round")?.as_u64().unwrap(),
+            round: remove(&mut tv, "round")?.as_i64().unwrap(),
+            scheduled_time: decode_time(remove_or_null(&mut tv, "scheduled_time")),
             started_at,
             state: MatchState::from_str(remove(&mut tv, "state")?.as_str().unwrap_or(""))
                 .unwrap_or(MatchState::All),
+            suggested_play_order: remove_or_null(&mut tv, "suggested_play_order").as_u64(),
             tournament_id: TournamentId::Id(remove(&mut tv, "tournament_id")?.as_u64().unwrap()),
+            underway_at: decode_time(remove_or_null(&mut tv, "underway_at")),
             updated_at: DateTime::parse_from_rfc3339(
                 remove(&mut tv, "updated_at")?.as_str().unwrap_or(""),
             )
             .unwrap(),
             winner_id: remove(&mut tv, "winner_id")?.as_u64().map(ParticipantId),
-            prerequisite_match_ids_csv: remove(&mut tv, "prerequisite_match_ids_csv")?
-                .as_str()
-                .unwrap_or("")
-                .to_owned(),
+            prerequisite_match_ids: decode_match_ids(remove(
+                &mut tv,
+                "prerequisite_match_ids_csv",
+            )?)?,
             scores_csv: MatchScores::decode(
                 remove(&mut tv, "scores_csv")?
                     .as_str()
@@ -299,33 +501,92 @@ impl Match {
     }
 }
 
+fn decode_match_ids(value: Value) -> Result<Vec<MatchId>, Error> {
+    let csv = match value.as_str() {
+        Some(csv) => csv,
+        None => return Ok(Vec::new()),
+    };
+    csv.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse()
+                .map_err(|_| Error::Decode("Invalid prerequisite match id", value.clone()))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::matches::{Match, MatchScore, MatchState};
-    use crate::tournament::TournamentId;
+    use crate::matches::{
+        Match, MatchId, MatchScore, MatchScores, MatchState, MatchUpdate, MatchWinner,
+    };
+    use crate::participants::{GroupId, ParticipantId};
+    use crate::tournament::{TournamentId, TournamentType};
+    use chrono::DateTime;
 
     #[test]
     fn test_score_parse() {
-        let strings = vec!["3-1", "", "3-0", "3--5", "0-0", "  9-", "    -    118  "];
-        let correct_scores = vec![
+        let strings = ["3-1", "3-0", "3--5", "-1-3", " -1 - -3 ", "0-0", "  9 - 2"];
+        let correct_scores = [
             MatchScore(3, 1),
-            MatchScore(0, 0),
-            MatchScore(3, 0),
             MatchScore(3, 0),
+            MatchScore(3, -5),
+            MatchScore(-1, 3),
+            MatchScore(-1, -3),
             MatchScore(0, 0),
-            MatchScore(9, 0),
-            MatchScore(0, 118),
+            MatchScore(9, 2),
         ];
         let iter = strings.iter().zip(correct_scores.iter());
         for pair in iter {
             if let Ok(ms) = MatchScore::decode(pair.0) {
-                assert_eq!(ms.0, (pair.1).0);
-                assert_eq!(ms.1, (pair.1).1);
-                assert_eq!(ms.to_string(), (pair.1).to_string());
+                assert_eq!(&ms, pair.1);
+                assert_eq!(ms.to_string().parse::<MatchScore>().unwrap(), ms);
             } else {
                 unreachable!();
             }
         }
+
+        for s in &["", "3", "3-", "-3", "a-1", "1-b", "1-2-3", "    -    118  "] {
+            assert!(MatchScore::decode(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_score_parse_lenient() {
+        let strings = ["3-1", "", "3-0", "3--5", "0-0", "  9-", "    -    118  "];
+        let correct_scores = [
+            MatchScore(3, 1),
+            MatchScore(0, 0),
+            MatchScore(3, 0),
+            MatchScore(3, -5),
+            MatchScore(0, 0),
+            MatchScore(9, 0),
+            MatchScore(0, 118),
+        ];
+        let iter = strings.iter().zip(correct_scores.iter());
+        for pair in iter {
+            let ms = MatchScore::decode_lenient(pair.0);
+            assert_eq!(ms.0, (pair.1).0);
+            assert_eq!(ms.1, (pair.1).1);
+            assert_eq!(ms.to_string(), (pair.1).to_string());
+        }
+    }
+
+    #[test]
+    fn test_scores_parse() {
+        let scores = "3-1, -1-2,2--3".parse::<MatchScores>().unwrap();
+        assert_eq!(
+            scores.0,
+            vec![MatchScore(3, 1), MatchScore(-1, 2), MatchScore(2, -3)]
+        );
+        assert_eq!(scores.to_string(), "3-1,-1-2,2--3");
+        assert!("".parse::<MatchScores>().unwrap().0.is_empty());
+        assert!("3-1,,2-0".parse::<MatchScores>().is_err());
+        assert!("3-1,x".parse::<MatchScores>().is_err());
+
+        let legacy = MatchScores::decode("3-1,,x".to_owned());
+        assert_eq!(legacy.0, vec![MatchScore(3, 1), MatchScore(0, 0)]);
     }
 
     #[test]
@@ -364,20 +625,20 @@ mod tests {
         assert!(json_r.is_ok());
         let json = json_r.unwrap();
         if let Ok(m) = Match::decode(json) {
-            // assert_eq!(m.attachment_count, );
+            assert_eq!(m.attachment_count, 0);
             // assert_eq!(m.created_at, );
-            // assert_eq!(m.group_id, );
+            assert_eq!(m.group_id, None);
             assert!(!m.has_attachment);
             assert_eq!(m.id.0, 23575258);
             assert_eq!(m.identifier, "A");
             assert_eq!(m.loser_id, None);
-            assert_eq!(m.player1.id.0, 16543993);
+            assert_eq!(m.player1.id, Some(ParticipantId(16543993)));
             assert!(!m.player1.is_prereq_match_loser);
             assert_eq!(m.player1.prereq_match_id, None);
             assert_eq!(m.player1.votes, 0);
             assert!(!m.player2.is_prereq_match_loser);
             assert_eq!(m.player2.prereq_match_id, None);
-            assert_eq!(m.player2.id.0, 16543997);
+            assert_eq!(m.player2.id, Some(ParticipantId(16543997)));
             assert_eq!(m.player2.votes, 3);
             assert_eq!(m.round, 1);
             // assert_eq!(m.started_at, );
@@ -385,9 +646,9 @@ mod tests {
             assert_eq!(m.tournament_id, TournamentId::Id(1086875));
             // assert_eq!(m.updated_at, );
             assert_eq!(m.winner_id, None);
-            assert!(m.prerequisite_match_ids_csv.is_empty());
+            assert!(m.prerequisite_match_ids.is_empty());
             {
-                let correct_scores = vec![MatchScore(3, 1), MatchScore(3, 2)];
+                let correct_scores = [MatchScore(3, 1), MatchScore(3, 2)];
                 assert_eq!(m.scores_csv.0.len(), 2);
                 let iter = m.scores_csv.0.iter().zip(correct_scores.iter());
                 for pair in iter {
@@ -399,4 +660,230 @@ mod tests {
             unreachable!();
         }
     }
+
+    #[test]
+    fn test_match_schedule_parse() {
+        let string = r#"{
+          "match": {
+            "attachment_count": 2,
+            "completed_at": "2015-01-19T17:20:00-05:00",
+            "created_at": "2015-01-19T16:57:17-05:00",
+            "forfeited": false,
+            "group_id": 5125,
+            "has_attachment": true,
+            "id": 23575260,
+            "identifier": "C",
+            "location": "Stream setup",
+            "loser_id": 16543997,
+            "optional": false,
+            "player1_id": 16543993,
+            "player1_is_prereq_match_loser": false,
+            "player1_prereq_match_id": 23575258,
+            "player1_votes": null,
+            "player2_id": 16543997,
+            "player2_is_prereq_match_loser": false,
+            "player2_prereq_match_id": 23575259,
+            "player2_votes": null,
+            "round": 2,
+            "scheduled_time": "2015-01-19T17:00:00-05:00",
+            "started_at": "2015-01-19T16:57:17-05:00",
+            "state": "complete",
+            "suggested_play_order": 3,
+            "tournament_id": 1086875,
+            "underway_at": "2015-01-19T17:01:00-05:00",
+            "updated_at": "2015-01-19T17:20:00-05:00",
+            "winner_id": 16543993,
+            "prerequisite_match_ids_csv": "23575258,23575259",
+            "scores_csv": "2-0"
+          }
+        }"#;
+        let m = Match::decode(serde_json::from_str(string).unwrap()).unwrap();
+        assert_eq!(m.attachment_count, 2);
+        assert_eq!(
+            m.completed_at,
+            DateTime::parse_from_rfc3339("2015-01-19T17:20:00-05:00").ok()
+        );
+        assert!(!m.forfeited);
+        assert_eq!(m.group_id, Some(GroupId(5125)));
+        assert_eq!(m.location, Some("Stream setup".to_owned()));
+        assert!(!m.optional);
+        assert_eq!(
+            m.scheduled_time,
+            DateTime::parse_from_rfc3339("2015-01-19T17:00:00-05:00").ok()
+        );
+        assert_eq!(m.suggested_play_order, Some(3));
+        assert_eq!(
+            m.underway_at,
+            DateTime::parse_from_rfc3339("2015-01-19T17:01:00-05:00").ok()
+        );
+        assert_eq!(m.winner_id, Some(ParticipantId(16543993)));
+        assert_eq!(
+            m.prerequisite_match_ids,
+            vec![MatchId(23575258), MatchId(23575259)]
+        );
+    }
+
+    #[test]
+    fn test_match_parse_without_optional_keys() {
+        let mut json: serde_json::Value = serde_json::from_str(
+            r#"{
+          "match": {
+            "created_at": "2015-01-19T16:57:17-05:00",
+            "has_attachment": false,
+            "id": 23575258,
+            "identifier": "A",
+            "loser_id": null,
+            "player1_id": 16543993,
+            "player1_is_prereq_match_loser": false,
+            "player1_prereq_match_id": null,
+            "player1_votes": null,
+            "player2_id": 16543997,
+            "player2_is_prereq_match_loser": false,
+            "player2_prereq_match_id": null,
+            "player2_votes": null,
+            "round": 1,
+            "started_at": null,
+            "state": "pending",
+            "tournament_id": 1086875,
+            "updated_at": "2015-01-19T16:57:17-05:00",
+            "winner_id": null,
+            "prerequisite_match_ids_csv": "23575258, 23575259",
+            "scores_csv": ""
+          }
+        }"#,
+        )
+        .unwrap();
+        let m = Match::decode(json.clone()).unwrap();
+        assert_eq!(m.attachment_count, 0);
+        assert_eq!(m.group_id, None);
+        assert_eq!(m.location, None);
+        assert_eq!(m.scheduled_time, None);
+        assert_eq!(m.underway_at, None);
+        assert_eq!(
+            m.prerequisite_match_ids,
+            vec![MatchId(23575258), MatchId(23575259)]
+        );
+
+        json["match"]["prerequisite_match_ids_csv"] = "23575258,B".into();
+        assert!(Match::decode(json).is_err());
+    }
+
+    fn open_match() -> Match {
+        let string = r#"{
+          "match": {
+            "attachment_count": null,
+            "created_at": "2015-01-19T16:57:17-05:00",
+            "group_id": null,
+            "has_attachment": false,
+            "id": 23575258,
+            "identifier": "A",
+            "location": null,
+            "loser_id": null,
+            "player1_id": 16543993,
+            "player1_is_prereq_match_loser": false,
+            "player1_prereq_match_id": null,
+            "player1_votes": null,
+            "player2_id": 16543997,
+            "player2_is_prereq_match_loser": false,
+            "player2_prereq_match_id": null,
+            "player2_votes": null,
+            "round": 1,
+            "scheduled_time": null,
+            "started_at": "2015-01-19T16:57:17-05:00",
+            "state": "open",
+            "tournament_id": 1086875,
+            "underway_at": null,
+            "updated_at": "2015-01-19T16:57:17-05:00",
+            "winner_id": null,
+            "prerequisite_match_ids_csv": "",
+            "scores_csv": ""
+          }
+        }"#;
+        Match::decode(serde_json::from_str(string).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_match_update_from_scores() {
+        let m = open_match();
+        let cases = [
+            (
+                "2-0",
+                Some(MatchWinner::Participant(ParticipantId(16543993))),
+            ),
+            (
+                "1-3,3-0,2-3",
+                Some(MatchWinner::Participant(ParticipantId(16543997))),
+            ),
+            (
+                "-1-3",
+                Some(MatchWinner::Participant(ParticipantId(16543997))),
+            ),
+            ("1-1", Some(MatchWinner::Tie)),
+            ("3-1,1-3", Some(MatchWinner::Tie)),
+            ("", None),
+        ];
+        for (scores, winner) in cases.iter() {
+            let mu = MatchUpdate::from_scores(&m, scores.parse().unwrap(), &TournamentType::Swiss)
+                .unwrap();
+            assert_eq!(&mu.winner_id, winner);
+            assert!(mu.validate(&m, &TournamentType::Swiss).is_ok());
+
+            let elimination = MatchUpdate::from_scores(
+                &m,
+                scores.parse().unwrap(),
+                &TournamentType::SingleElimination,
+            );
+            if winner == &Some(MatchWinner::Tie) {
+                assert!(elimination.is_err());
+            } else {
+                assert_eq!(&elimination.unwrap().winner_id, winner);
+            }
+        }
+
+        let mut pending = open_match();
+        pending.player2.id = None;
+        let mu = MatchUpdate::from_scores(&pending, "0-2".parse().unwrap(), &TournamentType::Swiss)
+            .unwrap();
+        assert_eq!(mu.winner_id, None);
+    }
+
+    #[test]
+    fn test_match_update_validate() {
+        let m = open_match();
+        let mut mu = MatchUpdate::new();
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_ok());
+
+        mu.winner_id(ParticipantId(16543993));
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_err());
+
+        mu.scores_csv("2-0".parse().unwrap());
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_ok());
+
+        mu.winner_id(ParticipantId(16543997));
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_err());
+
+        mu.winner_id(ParticipantId(1));
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_err());
+
+        mu.tie();
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_err());
+
+        mu.scores_csv("1-1".parse().unwrap());
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_ok());
+        assert_eq!(mu.winner_id.unwrap().to_string(), "tie");
+
+        mu.winner_id(ParticipantId(16543997));
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_err());
+
+        // The same tie in an elimination match.
+        mu.tie();
+        assert!(mu.validate(&m, &TournamentType::Swiss).is_ok());
+        for t in &[
+            TournamentType::SingleElimination,
+            TournamentType::DoubleElimination,
+        ] {
+            assert!(mu.validate(&m, t).is_err());
+        }
+        assert!(mu.validate(&m, &TournamentType::RoundRobin).is_ok());
+    }
 }
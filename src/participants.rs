@@ -4,13 +4,23 @@ use chrono::*;
 use serde_json::Value;
 
 use crate::error::Error;
-use crate::util::{decode_array, into_map, remove};
+use crate::tournament::{Tournament, TournamentId};
+use crate::util::{decode_array, into_map, remove, remove_or_null};
 
 /// Represents an ID of a participant
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ParticipantId(pub u64);
+numeric_id!(ParticipantId);
+
+/// Represents an ID of a group (of a group stage) the participant belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(pub u64);
+numeric_id!(GroupId);
 
 /// A structure for creating a participant (adding the participant to the tournament).
+///
+/// Only the fields which are set are sent to Challonge, so unset fields keep their
+/// current (or default) values on the server side.
 #[derive(Debug, Clone)]
 pub struct ParticipantCreate {
     /// The name displayed in the bracket/schedule - not required if email or challonge_username is provided. Must be unique per tournament.
@@ -22,15 +32,20 @@ pub struct ParticipantCreate {
     /// Providing this will first search for a matching Challonge account.
     /// If one is found, this will have the same effect as the "challonge_username" attribute.
     /// If one is not found, the "new-user-email" attribute will be set, and the user will be invited via email to create an account.
-    pub email: String,
+    pub email: Option<String>,
+
+    /// Challonge username or email of the participant.
+    /// If a Challonge account is found, the user will be invited to the tournament, otherwise an email invitation is sent.
+    pub invite_name_or_email: Option<String>,
 
     /// The participant's new seed.
     /// Must be between 1 and the current number of participants (including the new record).
     /// Overwriting an existing seed will automatically bump other participants as you would expect.
-    pub seed: u64,
+    /// When omitted, the participant is added to the bottom of the list.
+    pub seed: Option<u64>,
 
     /// Max: 255 characters. Multi-purpose field that is only visible via the API and handy for site integration (e.g. key to your users table).
-    pub misc: String,
+    pub misc: Option<String>,
 }
 impl ParticipantCreate {
     /// Creates a structure to create participant with default values.
@@ -38,17 +53,19 @@ impl ParticipantCreate {
         ParticipantCreate {
             name: None,
             challonge_username: None,
-            email: String::default(),
-            seed: 1,
-            misc: String::default(),
+            email: None,
+            invite_name_or_email: None,
+            seed: None,
+            misc: None,
         }
     }
 
     builder_so!(name);
     builder_so!(challonge_username);
-    builder_s!(email);
-    builder!(seed, u64);
-    builder_s!(misc);
+    builder_so!(email);
+    builder_so!(invite_name_or_email);
+    builder_o!(seed, u64);
+    builder_so!(misc);
 }
 
 impl Default for ParticipantCreate {
@@ -57,6 +74,127 @@ impl Default for ParticipantCreate {
     }
 }
 
+/// Takes the name, Challonge account, seed and misc of an existing participant,
+/// to add them to another tournament.
+impl<'a> From<&'a Participant> for ParticipantCreate {
+    fn from(p: &'a Participant) -> ParticipantCreate {
+        let non_empty = |s: &String| Some(s.clone()).filter(|s| !s.is_empty());
+        ParticipantCreate {
+            name: non_empty(&p.name),
+            challonge_username: non_empty(&p.challonge_username),
+            email: None,
+            invite_name_or_email: None,
+            seed: Some(p.seed),
+            misc: non_empty(&p.misc),
+        }
+    }
+}
+
+/// Prefix of a team roster kept in the `misc` field of a participant.
+///
+/// Challonge's API has no attribute for the members of a team, so a team tournament's
+/// participants are the teams, and their members are listed on the first line of `misc`
+/// after this prefix, separated by commas. Other text follows on the next lines:
+/// `members: Alice, Bob, Carol\nSponsored by ACME`. This format is a convention of this
+/// crate, not of Challonge, which treats the line as plain text.
+pub const MEMBERS_PREFIX: &str = "members:";
+
+/// Maximum length of the `misc` field ("Max: 255 characters" in the API documentation of
+/// `participant[misc]`).
+const MISC_MAX_LEN: usize = 255;
+
+/// Splits the `misc` field of a team into its roster and the rest of the text. Without a
+/// roster, there are no members and the whole field is the text.
+pub fn decode_roster(misc: &str) -> (Vec<String>, &str) {
+    let (first, rest) = match misc.find('\n') {
+        Some(i) => (&misc[..i], &misc[i + 1..]),
+        None => (misc, ""),
+    };
+    match first.trim_start().strip_prefix(MEMBERS_PREFIX) {
+        Some(members) => (
+            members
+                .split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(String::from)
+                .collect(),
+            rest,
+        ),
+        None => (Vec::new(), misc),
+    }
+}
+
+/// A team to add to a team tournament (see `TournamentCreate::teams`).
+///
+/// The members are stored in the team's `misc` field in this crate's own roster format (see
+/// `MEMBERS_PREFIX`), which Challonge doesn't know about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamCreate {
+    /// Name of the team, displayed in the bracket
+    pub name: String,
+
+    /// Names (or Challonge usernames) of the members
+    pub members: Vec<String>,
+
+    /// Seed of the team, at the bottom of the list if omitted
+    pub seed: Option<u64>,
+
+    /// Text kept in `misc` after the roster
+    pub misc: Option<String>,
+}
+impl TeamCreate {
+    /// Creates a team without members.
+    pub fn new<S: Into<String>>(name: S) -> TeamCreate {
+        TeamCreate {
+            name: name.into(),
+            members: Vec::new(),
+            seed: None,
+            misc: None,
+        }
+    }
+
+    /// Adds a member to the team.
+    pub fn member<S: Into<String>>(&mut self, member: S) -> &mut Self {
+        self.members.push(member.into());
+        self
+    }
+
+    builder_o!(seed, u64);
+    builder_so!(misc);
+
+    /// The participant representing the team, with the roster and the text in `misc`.
+    ///
+    /// Fails if a member's name contains a comma or a line break, or the roster and the
+    /// text don't fit in `misc` (255 characters).
+    pub fn to_participant(&self) -> Result<ParticipantCreate, Error> {
+        if self
+            .members
+            .iter()
+            .any(|m| m.contains(',') || m.contains('\n'))
+        {
+            return Err(Error::Api(
+                "Team member names can't contain commas or line breaks",
+            ));
+        }
+        let mut misc = format!("{} {}", MEMBERS_PREFIX, self.members.join(", "));
+        if let Some(ref text) = self.misc {
+            misc.push('\n');
+            misc.push_str(text);
+        }
+        if misc.chars().count() > MISC_MAX_LEN {
+            return Err(Error::Api(
+                "Team roster and misc are longer than 255 characters",
+            ));
+        }
+        Ok(ParticipantCreate {
+            name: Some(self.name.clone()),
+            seed: self.seed,
+            misc: Some(misc),
+            ..ParticipantCreate::new()
+        })
+    }
+}
+
 /// A list of participants for the tournament.
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -83,8 +221,12 @@ pub struct Participant {
     /// ???
     pub final_rank: Option<u64>,
 
-    /// ???
-    pub group_id: Option<u64>,
+    /// Id of the group the participant belongs to (for tournaments with group stages).
+    pub group_id: Option<GroupId>,
+
+    /// Ids standing for the participant in the group stage matches,
+    /// which refer to the players by these ids instead of the participant id.
+    pub group_player_ids: Vec<ParticipantId>,
 
     /// ???
     pub icon: String,
@@ -111,7 +253,7 @@ pub struct Participant {
     pub seed: u64,
 
     /// Id of the tournament the participant belongs to.
-    pub tournament_id: u64,
+    pub tournament_id: TournamentId,
 
     /// Time when the participant was updated last time
     pub updated_at: DateTime<FixedOffset>,
@@ -177,7 +319,8 @@ impl Participant {
             )
             .unwrap(),
             final_rank: remove(&mut tv, "final_rank")?.as_u64(),
-            group_id: remove(&mut tv, "group_id")?.as_u64(),
+            group_id: remove(&mut tv, "group_id")?.as_u64().map(GroupId),
+            group_player_ids: decode_group_player_ids(remove_or_null(&mut tv, "group_player_ids"))?,
             icon: remove(&mut tv, "icon")?.as_str().unwrap_or("").to_owned(),
             id: ParticipantId(remove(&mut tv, "id")?.as_u64().unwrap()),
             invitation_id: remove(&mut tv, "invitation_id")?.as_u64(),
@@ -191,7 +334,7 @@ impl Participant {
                 .as_bool()
                 .unwrap_or(false),
             seed: remove(&mut tv, "seed")?.as_u64().unwrap(),
-            tournament_id: remove(&mut tv, "tournament_id")?.as_u64().unwrap(),
+            tournament_id: TournamentId::Id(remove(&mut tv, "tournament_id")?.as_u64().unwrap()),
             updated_at: DateTime::parse_from_rfc3339(
                 remove(&mut tv, "updated_at")?.as_str().unwrap_or(""),
             )
@@ -244,11 +387,78 @@ impl Participant {
             reactivatable: remove(&mut tv, "reactivatable")?.as_bool().unwrap_or(false),
         })
     }
+
+    /// Members of the team (see `MEMBERS_PREFIX`), empty unless the participant's tournament
+    /// is a team tournament.
+    pub fn members(&self, tournament: &Tournament) -> Vec<String> {
+        if tournament.teams {
+            decode_roster(&self.misc).0
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn decode_group_player_ids(value: Value) -> Result<Vec<ParticipantId>, Error> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(ids) => ids
+            .into_iter()
+            .map(|id| match id.as_u64() {
+                Some(id) => Ok(ParticipantId(id)),
+                None => Err(Error::Decode("Invalid group player id", id)),
+            })
+            .collect(),
+        v => Err(Error::Decode("Expected an array of group player ids", v)),
+    }
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::participants::Participant;
+pub(crate) mod tests {
+    use crate::participants::{decode_roster, GroupId, Participant, ParticipantId, TeamCreate};
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::TournamentId;
+    use std::collections::HashMap;
+
+    pub fn make_participant(id: u64, name: &str, seed: u64) -> Participant {
+        Participant::decode(participant_json(id, name, seed)).unwrap()
+    }
+
+    /// The API representation of a participant.
+    pub fn participant_json(id: u64, name: &str, seed: u64) -> serde_json::Value {
+        serde_json::json!({
+            "participant": {
+                "active": true,
+                "checked_in_at": null,
+                "created_at": "2015-01-19T16:54:40-05:00",
+                "final_rank": null,
+                "group_id": null,
+                "icon": null,
+                "id": id,
+                "invitation_id": null,
+                "invite_email": null,
+                "misc": null,
+                "name": name,
+                "on_waiting_list": false,
+                "seed": seed,
+                "tournament_id": 1086875,
+                "updated_at": "2015-01-19T16:54:40-05:00",
+                "challonge_username": null,
+                "challonge_email_address_verified": null,
+                "removable": true,
+                "participatable_or_invitation_attached": false,
+                "confirm_remove": true,
+                "invitation_pending": false,
+                "display_name_with_invitation_email_address": name,
+                "email_hash": null,
+                "username": null,
+                "attached_participatable_portrait_url": null,
+                "can_check_in": false,
+                "checked_in": false,
+                "reactivatable": false
+            }
+        })
+    }
 
     #[test]
     fn test_participant_parse() {
@@ -301,7 +511,7 @@ mod tests {
             assert_eq!(p.name, "Participant #1");
             assert!(!p.on_waiting_list);
             assert_eq!(p.seed, 1);
-            assert_eq!(p.tournament_id, 1086875);
+            assert_eq!(p.tournament_id, TournamentId::Id(1086875));
             // assert_eq!(p.updated_at, );
             assert!(p.challonge_username.is_empty());
             assert!(p.challonge_email_address_verified.is_empty());
@@ -320,7 +530,63 @@ mod tests {
             assert!(!p.checked_in);
             assert!(!p.reactivatable);
         } else {
-            unreachable!(false);
+            unreachable!();
+        }
+    }
+
+    #[test]
+    fn test_id_parse_and_display() {
+        let id: ParticipantId = " 16543993".parse().unwrap();
+        assert_eq!(id, ParticipantId(16543993));
+        assert_eq!(id.to_string(), "16543993");
+        assert!("abc".parse::<ParticipantId>().is_err());
+        assert_eq!("42".parse::<GroupId>().unwrap(), GroupId(42));
+
+        let mut seeds = HashMap::new();
+        seeds.insert(id, 1);
+        assert_eq!(seeds.get(&ParticipantId(16543993)), Some(&1));
+    }
+
+    #[test]
+    fn test_team_roster() {
+        let mut team = TeamCreate::new("Blue");
+        team.member("Alice").member("Bob").seed(2);
+        let pc = team.to_participant().unwrap();
+        assert_eq!(pc.name.as_deref(), Some("Blue"));
+        assert_eq!(pc.seed, Some(2));
+        assert_eq!(pc.misc.as_deref(), Some("members: Alice, Bob"));
+
+        team.misc("Sponsored by ACME\nPaid");
+        let pc = team.to_participant().unwrap();
+        assert_eq!(
+            pc.misc.as_deref(),
+            Some("members: Alice, Bob\nSponsored by ACME\nPaid")
+        );
+        let (members, text) = decode_roster(pc.misc.as_deref().unwrap());
+        assert_eq!(members, team.members);
+        assert_eq!(text, "Sponsored by ACME\nPaid");
+        assert_eq!(decode_roster("key 42"), (Vec::new(), "key 42"));
+
+        // Only a team tournament's participants have members.
+        let mut t = sample_tournament();
+        let mut json = participant_json(1, "Blue", 2);
+        json["participant"]["misc"] = pc.misc.into();
+        let p = Participant::decode(json).unwrap();
+        assert!(p.members(&t).is_empty());
+        t.teams = true;
+        assert_eq!(p.members(&t), team.members);
+        assert!(make_participant(2, "Red", 1).members(&t).is_empty());
+
+        assert!(TeamCreate::new("Green")
+            .member("Doe, John")
+            .to_participant()
+            .is_err());
+        let mut big = TeamCreate::new("Big");
+        for i in 0..20 {
+            big.member(format!("Player {}", i));
         }
+        assert!(big.to_participant().is_ok());
+        big.misc("x".repeat(100));
+        assert!(big.to_participant().is_err());
     }
 }
@@ -0,0 +1,492 @@
+//! Player ratings from match results.
+//!
+//! `Ratings` collects the completed matches of one or more tournaments and rates the
+//! players with Elo (updated after every match) or Glicko-2 (every tournament is a rating
+//! period). Participants are different records in every tournament, `Identities` maps them
+//! to stable player names.
+//!
+//! Forfeited matches and matches with an unknown player are not rated.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::matches::{Index as MatchIndex, MatchId, MatchState};
+use crate::participants::{Index as ParticipantIndex, Participant, ParticipantId};
+use crate::tournament::TournamentId;
+
+/// Glicko-2 ratings are scaled by this factor.
+const GLICKO2_SCALE: f64 = 173.7178;
+
+/// Maps participants to players.
+///
+/// A participant is identified by the Challonge username, the email hash or the name,
+/// the first of them that isn't empty (case-insensitively). Aliases map any of these
+/// to another player, for example a player's old name to their username.
+#[derive(Debug, Clone, Default)]
+pub struct Identities {
+    aliases: HashMap<String, String>,
+}
+impl Identities {
+    /// Creates identities without aliases.
+    pub fn new() -> Identities {
+        Identities::default()
+    }
+
+    /// Makes participants with the username, email hash or name `alias` the `player`.
+    pub fn alias<S1: AsRef<str>, S2: Into<String>>(&mut self, alias: S1, player: S2) -> &mut Self {
+        self.aliases
+            .insert(alias.as_ref().to_lowercase(), player.into());
+        self
+    }
+
+    /// Returns the player of the participant.
+    pub fn player(&self, p: &Participant) -> String {
+        let keys = [&p.challonge_username, &p.email_hash, &p.name];
+        for key in &keys {
+            if let Some(player) = self.aliases.get(&key.to_lowercase()) {
+                return player.clone();
+            }
+        }
+        keys.iter()
+            .find(|k| !k.is_empty())
+            .map_or_else(String::new, |k| k.to_lowercase())
+    }
+}
+
+/// A rated match between two players.
+#[derive(Debug, Clone)]
+pub struct Game {
+    /// The tournament of the match
+    pub tournament_id: TournamentId,
+    /// The match
+    pub match_id: MatchId,
+    /// When the match was completed
+    pub completed_at: Option<DateTime<FixedOffset>>,
+    /// The first player
+    pub player1: String,
+    /// The second player
+    pub player2: String,
+    /// Score of the first player: 1 for a win, 0.5 for a tie and 0 for a loss
+    pub score: f64,
+}
+
+/// A player's rating.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rating {
+    /// The rating
+    pub rating: f64,
+    /// Rating deviation, 0 for Elo
+    pub deviation: f64,
+    /// Rating volatility, 0 for Elo
+    pub volatility: f64,
+}
+
+/// A player's rating after a match (Elo) or a tournament (Glicko-2).
+#[derive(Debug, Clone)]
+pub struct RatingPoint {
+    /// The tournament
+    pub tournament_id: TournamentId,
+    /// The match, none for Glicko-2
+    pub match_id: Option<MatchId>,
+    /// The rating after it
+    pub rating: Rating,
+}
+
+/// A rated player.
+#[derive(Debug, Clone)]
+pub struct PlayerRating {
+    /// The player
+    pub player: String,
+    /// The current rating
+    pub rating: Rating,
+    /// Number of rated matches
+    pub games: u64,
+    /// The rating after each of the player's matches or tournaments
+    pub history: Vec<RatingPoint>,
+}
+
+/// Elo rating system parameters.
+#[derive(Debug, Copy, Clone)]
+pub struct Elo {
+    /// Rating of new players
+    pub initial: f64,
+    /// Maximal rating change after a match
+    pub k: f64,
+}
+impl Default for Elo {
+    fn default() -> Elo {
+        Elo {
+            initial: 1500.0,
+            k: 32.0,
+        }
+    }
+}
+impl Elo {
+    /// Returns the expected score of a player with the rating against the opponent.
+    pub fn expected(rating: f64, opponent: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+    }
+}
+
+/// Glicko-2 rating system parameters.
+#[derive(Debug, Copy, Clone)]
+pub struct Glicko2 {
+    /// Rating of new players
+    pub initial: Rating,
+    /// System constant constraining the volatility change
+    pub tau: f64,
+}
+impl Default for Glicko2 {
+    fn default() -> Glicko2 {
+        Glicko2 {
+            initial: Rating {
+                rating: 1500.0,
+                deviation: 350.0,
+                volatility: 0.06,
+            },
+            tau: 0.5,
+        }
+    }
+}
+impl Glicko2 {
+    /// Rates a player after a rating period with the opponents' ratings
+    /// and the player's scores against them.
+    pub fn rate(&self, player: Rating, results: &[(Rating, f64)]) -> Rating {
+        let mu = (player.rating - 1500.0) / GLICKO2_SCALE;
+        let phi = player.deviation / GLICKO2_SCALE;
+        let sigma = player.volatility;
+        if results.is_empty() {
+            return Rating {
+                deviation: (phi * phi + sigma * sigma).sqrt() * GLICKO2_SCALE,
+                ..player
+            };
+        }
+
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (std::f64::consts::PI.powi(2))).sqrt();
+        let mut v_inv = 0.0;
+        let mut delta_sum = 0.0;
+        for (opponent, score) in results {
+            let mu_j = (opponent.rating - 1500.0) / GLICKO2_SCALE;
+            let g_j = g(opponent.deviation / GLICKO2_SCALE);
+            let e = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+            v_inv += g_j * g_j * e * (1.0 - e);
+            delta_sum += g_j * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * delta_sum;
+
+        // The new volatility, by the Illinois algorithm.
+        let a = (sigma * sigma).ln();
+        let tau2 = self.tau * self.tau;
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + v + ex;
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d) - (x - a) / tau2
+        };
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * self.tau) < 0.0 {
+                k += 1.0;
+            }
+            a - k * self.tau
+        };
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > 1e-6 {
+            let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(big_c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = big_c;
+            f_b = f_c;
+        }
+        let sigma = (big_a / 2.0).exp();
+
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + v_inv).sqrt();
+        let mu = mu + phi * phi * delta_sum;
+        Rating {
+            rating: mu * GLICKO2_SCALE + 1500.0,
+            deviation: phi * GLICKO2_SCALE,
+            volatility: sigma,
+        }
+    }
+}
+
+/// Matches of one or more tournaments to rate.
+#[derive(Debug, Clone, Default)]
+pub struct Ratings {
+    identities: Identities,
+    tournaments: Vec<Vec<Game>>,
+}
+impl Ratings {
+    /// Creates ratings with the player identities.
+    pub fn new(identities: Identities) -> Ratings {
+        Ratings {
+            identities,
+            tournaments: Vec::new(),
+        }
+    }
+
+    /// Adds the completed matches of a tournament. Tournaments are rated in the order
+    /// they are added, their matches by completion time.
+    pub fn add(&mut self, participants: &ParticipantIndex, matches: &MatchIndex) -> &mut Self {
+        let players: HashMap<ParticipantId, String> = participants
+            .0
+            .iter()
+            .map(|p| (p.id, self.identities.player(p)))
+            .collect();
+        let mut completed: Vec<_> = matches
+            .0
+            .iter()
+            .filter(|m| m.state == MatchState::Complete && !m.forfeited)
+            .collect();
+        completed.sort_by_key(|m| (m.completed_at, m.suggested_play_order, m.id));
+        let games: Vec<Game> = completed
+            .into_iter()
+            .filter_map(|m| {
+                let player1 = players.get(&m.player1.id?)?;
+                let player2 = players.get(&m.player2.id?)?;
+                let score = match m.winner_id {
+                    Some(w) if Some(w) == m.player1.id => 1.0,
+                    Some(w) if Some(w) == m.player2.id => 0.0,
+                    Some(_) => return None,
+                    None => 0.5,
+                };
+                Some(Game {
+                    tournament_id: m.tournament_id.clone(),
+                    match_id: m.id,
+                    completed_at: m.completed_at,
+                    player1: player1.clone(),
+                    player2: player2.clone(),
+                    score,
+                })
+            })
+            .collect();
+        if !games.is_empty() {
+            self.tournaments.push(games);
+        }
+        self
+    }
+
+    /// The rated games, in rating order.
+    pub fn games(&self) -> Vec<&Game> {
+        self.tournaments.iter().flatten().collect()
+    }
+
+    /// Rates the players with Elo, updating the ratings after every match.
+    /// Players are returned by rating, the best first.
+    pub fn elo(&self, elo: &Elo) -> Vec<PlayerRating> {
+        let initial = Rating {
+            rating: elo.initial,
+            deviation: 0.0,
+            volatility: 0.0,
+        };
+        let mut players = HashMap::new();
+        for game in self.games() {
+            let r1 = players
+                .get(&game.player1)
+                .map_or(elo.initial, |p: &PlayerRating| p.rating.rating);
+            let r2 = players
+                .get(&game.player2)
+                .map_or(elo.initial, |p: &PlayerRating| p.rating.rating);
+            let change = elo.k * (game.score - Elo::expected(r1, r2));
+            for (player, rating) in &[(&game.player1, r1 + change), (&game.player2, r2 - change)] {
+                let p = players
+                    .entry((*player).clone())
+                    .or_insert_with(|| new_player(player, initial));
+                p.rating.rating = *rating;
+                p.games += 1;
+                p.history.push(RatingPoint {
+                    tournament_id: game.tournament_id.clone(),
+                    match_id: Some(game.match_id),
+                    rating: p.rating,
+                });
+            }
+        }
+        sorted(players)
+    }
+
+    /// Rates the players with Glicko-2, every tournament being a rating period.
+    /// Players are returned by rating, the best first.
+    pub fn glicko2(&self, glicko2: &Glicko2) -> Vec<PlayerRating> {
+        let mut players: HashMap<String, PlayerRating> = HashMap::new();
+        for games in &self.tournaments {
+            let mut results: HashMap<&String, Vec<(Rating, f64)>> = HashMap::new();
+            let rating =
+                |player: &String| players.get(player).map_or(glicko2.initial, |p| p.rating);
+            for game in games {
+                results
+                    .entry(&game.player1)
+                    .or_default()
+                    .push((rating(&game.player2), game.score));
+                results
+                    .entry(&game.player2)
+                    .or_default()
+                    .push((rating(&game.player1), 1.0 - game.score));
+            }
+
+            // Players who didn't play become less certain.
+            for p in players.values_mut() {
+                if !results.contains_key(&p.player) {
+                    p.rating = glicko2.rate(p.rating, &[]);
+                }
+            }
+            let tournament_id = &games[0].tournament_id;
+            for (player, results) in results {
+                let p = players
+                    .entry(player.clone())
+                    .or_insert_with(|| new_player(player, glicko2.initial));
+                p.rating = glicko2.rate(p.rating, &results);
+                p.games += results.len() as u64;
+                p.history.push(RatingPoint {
+                    tournament_id: tournament_id.clone(),
+                    match_id: None,
+                    rating: p.rating,
+                });
+            }
+        }
+        sorted(players)
+    }
+}
+
+fn new_player(player: &str, rating: Rating) -> PlayerRating {
+    PlayerRating {
+        player: player.to_owned(),
+        rating,
+        games: 0,
+        history: Vec::new(),
+    }
+}
+
+fn sorted(players: HashMap<String, PlayerRating>) -> Vec<PlayerRating> {
+    let mut players: Vec<PlayerRating> = players.into_values().collect();
+    players.sort_by(|a, b| {
+        b.rating
+            .rating
+            .partial_cmp(&a.rating.rating)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.player.cmp(&b.player))
+    });
+    players
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::Index as MatchIndex;
+    use crate::participants::tests::make_participant;
+    use crate::participants::Index as ParticipantIndex;
+    use crate::ratings::{Elo, Glicko2, Identities, Rating, Ratings};
+
+    fn close(a: f64, b: f64, precision: f64) -> bool {
+        (a - b).abs() < precision
+    }
+
+    #[test]
+    fn test_identities() {
+        let mut alice = make_participant(1, "Alice", 1);
+        alice.challonge_username = "AliceC".to_owned();
+        let mut hashed = make_participant(2, "A. Smith", 1);
+        hashed.email_hash = "e3b0c442".to_owned();
+        let bob = make_participant(3, "Bob", 2);
+        let mut ids = Identities::new();
+        assert_eq!(ids.player(&alice), "alicec");
+        assert_eq!(ids.player(&hashed), "e3b0c442");
+        assert_eq!(ids.player(&bob), "bob");
+
+        ids.alias("E3B0C442", "alicec").alias("bob", "robert");
+        assert_eq!(ids.player(&hashed), "alicec");
+        assert_eq!(ids.player(&bob), "robert");
+    }
+
+    #[test]
+    fn test_glicko2_example() {
+        // The example from Glickman's description of Glicko-2.
+        let rating = |rating, deviation| Rating {
+            rating,
+            deviation,
+            volatility: 0.06,
+        };
+        let r = Glicko2::default().rate(
+            rating(1500.0, 200.0),
+            &[
+                (rating(1400.0, 30.0), 1.0),
+                (rating(1550.0, 100.0), 0.0),
+                (rating(1700.0, 300.0), 0.0),
+            ],
+        );
+        assert!(close(r.rating, 1464.06, 0.01), "{:?}", r);
+        assert!(close(r.deviation, 151.52, 0.01), "{:?}", r);
+        assert!(close(r.volatility, 0.05999, 0.00001), "{:?}", r);
+
+        let idle = Glicko2::default().rate(rating(1500.0, 200.0), &[]);
+        assert_eq!(idle.rating, 1500.0);
+        assert!(idle.deviation > 200.0);
+    }
+
+    #[test]
+    fn test_ratings() {
+        let participants = |offset: u64| {
+            ParticipantIndex(vec![
+                make_participant(offset + 1, "Alice", 1),
+                make_participant(offset + 2, "Bob", 2),
+                make_participant(offset + 3, "Carol", 3),
+            ])
+        };
+        let game = |id, p1, p2, winner| {
+            make_match(
+                id,
+                "A",
+                1,
+                (Some(p1), None),
+                (Some(p2), None),
+                "complete",
+                winner,
+                "1-0",
+            )
+        };
+        let mut forfeited = game(4, 1, 2, Some(2));
+        forfeited.forfeited = true;
+        let first = MatchIndex(vec![
+            game(1, 1, 2, Some(1)),
+            game(2, 1, 3, Some(1)),
+            game(3, 2, 3, None),
+            forfeited,
+        ]);
+        let second = MatchIndex(vec![game(11, 13, 11, Some(11))]);
+
+        let mut ratings = Ratings::new(Identities::new());
+        ratings
+            .add(&participants(0), &first)
+            .add(&participants(10), &second);
+        assert_eq!(ratings.games().len(), 4);
+
+        let elo = ratings.elo(&Elo::default());
+        let order: Vec<&str> = elo.iter().map(|p| p.player.as_str()).collect();
+        assert_eq!(order, vec!["alice", "bob", "carol"]);
+        // Alice beats Bob, both at 1500.
+        assert_eq!(elo[0].history[0].rating.rating, 1516.0);
+        assert_eq!(elo[0].games, 3);
+        assert_eq!(elo[0].history.len(), 3);
+        let total: f64 = elo.iter().map(|p| p.rating.rating).sum();
+        assert!(close(total, 4500.0, 1e-9));
+
+        let glicko = ratings.glicko2(&Glicko2::default());
+        assert_eq!(glicko[0].player, "alice");
+        assert_eq!(glicko[0].history.len(), 2);
+        assert!(glicko[0].history[1].match_id.is_none());
+        // Bob didn't play in the second tournament.
+        let bob = glicko.iter().find(|p| p.player == "bob").unwrap();
+        assert_eq!(bob.history.len(), 1);
+        assert!(bob.rating.deviation > bob.history[0].rating.deviation);
+    }
+}
@@ -0,0 +1,1019 @@
+//! Bracket rendering as monospace text and as SVG.
+//!
+//! Elimination brackets are drawn as columns of rounds with lines leading from each match
+//! to the match its winner goes to, the losers bracket of double elimination below the
+//! winners one. Round robin and Swiss tournaments are drawn as a grid of results between
+//! every pair of participants. Round labels are shown when the tournament's `show_rounds` is set,
+//! seeds unless its `hide_seeds` is.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::bracket::{Bracket, Side};
+use crate::matches::{Index as MatchIndex, Match, MatchId};
+use crate::participants::{Index as ParticipantIndex, ParticipantId};
+use crate::tournament::{Tournament, TournamentType};
+
+/// Longest name shown, longer ones are truncated.
+const MAX_NAME: usize = 24;
+/// Rows between the tops of adjacent first round matches.
+const SLOT_ROWS: usize = 3;
+/// Columns between the rounds.
+const GAP: usize = 3;
+
+const CHAR_WIDTH: usize = 8;
+const ROW_HEIGHT: usize = 20;
+const PADDING: usize = 10;
+
+/// Draws the tournament as monospace text.
+pub fn ascii(
+    tournament: &Tournament,
+    participants: &ParticipantIndex,
+    matches: &MatchIndex,
+) -> String {
+    match layout(tournament, participants, matches) {
+        Layout::Bracket(sections, widths) => {
+            let parts: Vec<String> = sections.iter().map(|s| ascii_section(s, &widths)).collect();
+            parts.join("\n")
+        }
+        Layout::Grid(grid) => ascii_grid(&grid),
+    }
+}
+
+/// Draws the tournament as a standalone SVG document.
+pub fn svg(
+    tournament: &Tournament,
+    participants: &ParticipantIndex,
+    matches: &MatchIndex,
+) -> String {
+    let (body, width, height) = match layout(tournament, participants, matches) {
+        Layout::Bracket(sections, widths) => svg_sections(&sections, &widths),
+        Layout::Grid(grid) => svg_grid(&grid),
+    };
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="13">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    out.push_str(&body);
+    out.push_str("</svg>\n");
+    out
+}
+
+/// A participant's line in a match box.
+#[derive(Debug, Clone)]
+struct Line {
+    seed: Option<u64>,
+    name: String,
+    score: String,
+    winner: bool,
+}
+
+/// A match placed in a section of the bracket.
+#[derive(Debug, Clone)]
+struct MatchBox {
+    identifier: String,
+    caption: Option<String>,
+    column: usize,
+    top: usize,
+    lines: [Line; 2],
+}
+
+/// A line leading from the row of one match to the row of another one.
+#[derive(Debug, Clone)]
+struct Link {
+    from_column: usize,
+    from_row: usize,
+    to_column: usize,
+    to_row: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    title: Option<String>,
+    labels: Vec<String>,
+    boxes: Vec<MatchBox>,
+    links: Vec<Link>,
+    rows: usize,
+}
+
+/// Widths (in characters) of the parts of a match box, shared by all sections.
+#[derive(Debug, Clone, Default)]
+struct Widths {
+    identifier: usize,
+    seed: usize,
+    name: usize,
+    score: usize,
+}
+impl Widths {
+    fn new(sections: &[Section]) -> Widths {
+        let mut w = Widths::default();
+        for b in sections.iter().flat_map(|s| &s.boxes) {
+            w.identifier = w.identifier.max(b.identifier.chars().count());
+            for line in &b.lines {
+                if let Some(seed) = line.seed {
+                    w.seed = w.seed.max(seed.to_string().len());
+                }
+                w.name = w.name.max(line.name.chars().count());
+                w.score = w.score.max(line.score.chars().count());
+            }
+        }
+        w.name = w.name.min(MAX_NAME);
+        w
+    }
+
+    /// Offset of the seed, the name and the score, and the width of the whole box.
+    fn offsets(&self) -> (usize, usize, usize, usize) {
+        let seed = self.identifier + 1;
+        let name = if self.seed > 0 {
+            seed + self.seed + 1
+        } else {
+            seed
+        };
+        let score = name + self.name + 1;
+        (seed, name, score, score + self.score + 1)
+    }
+
+    fn total(&self) -> usize {
+        self.offsets().3
+    }
+
+    fn column_x(&self, column: usize) -> usize {
+        column * (self.total() + GAP)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Grid {
+    names: Vec<(Option<u64>, String)>,
+    cells: Vec<Vec<String>>,
+}
+
+enum Layout {
+    Bracket(Vec<Section>, Widths),
+    Grid(Grid),
+}
+
+fn layout(
+    tournament: &Tournament,
+    participants: &ParticipantIndex,
+    matches: &MatchIndex,
+) -> Layout {
+    let names: HashMap<ParticipantId, (u64, String)> = participants
+        .0
+        .iter()
+        .map(|p| (p.id, (p.seed, truncate(&p.name))))
+        .collect();
+    match tournament.tournament_type {
+        TournamentType::RoundRobin | TournamentType::Swiss => {
+            Layout::Grid(grid(tournament, participants, matches))
+        }
+        TournamentType::SingleElimination | TournamentType::DoubleElimination => {
+            let double = tournament.tournament_type == TournamentType::DoubleElimination;
+            let bracket = Bracket::new(matches);
+            let winners: Vec<&Match> = bracket
+                .matches()
+                .filter(|m| bracket.side(m.id) != Some(Side::Losers))
+                .collect();
+            let losers = bracket.side_matches(Side::Losers);
+            let context = Context {
+                bracket: &bracket,
+                names: &names,
+                hide_seeds: tournament.hide_seeds,
+                show_rounds: tournament.show_rounds,
+                double,
+            };
+            let mut sections = vec![context.section(
+                if double {
+                    Some("Winners Bracket")
+                } else {
+                    None
+                },
+                &winners,
+            )];
+            if !losers.is_empty() {
+                sections.push(context.section(Some("Losers Bracket"), &losers));
+            }
+            let widths = Widths::new(&sections);
+            Layout::Bracket(sections, widths)
+        }
+    }
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() > MAX_NAME {
+        let mut s: String = name.chars().take(MAX_NAME - 1).collect();
+        s.push('~');
+        s
+    } else {
+        name.to_owned()
+    }
+}
+
+struct Context<'a> {
+    bracket: &'a Bracket,
+    names: &'a HashMap<ParticipantId, (u64, String)>,
+    hide_seeds: bool,
+    show_rounds: bool,
+    double: bool,
+}
+impl<'a> Context<'a> {
+    /// Places the matches into columns by rounds. A match is placed in the middle of the
+    /// matches whose winners it takes, the others take the next free slot.
+    fn section(&self, title: Option<&str>, matches: &[&Match]) -> Section {
+        let mut rounds: Vec<i64> = matches.iter().map(|m| m.round).collect();
+        rounds.sort_by_key(|r| r.abs());
+        rounds.dedup();
+        let column = |m: &Match| rounds.iter().position(|&r| r == m.round).unwrap();
+        let ids: HashSet<MatchId> = matches.iter().map(|m| m.id).collect();
+
+        let children = |m: &Match| -> Vec<MatchId> {
+            let mut c: Vec<MatchId> = [&m.player1, &m.player2]
+                .iter()
+                .filter(|p| !p.is_prereq_match_loser)
+                .filter_map(|p| p.prereq_match_id)
+                .filter(|id| ids.contains(id))
+                .collect();
+            c.dedup();
+            c
+        };
+        let mut roots: Vec<&Match> = matches
+            .iter()
+            .filter(
+                |m| !matches!(self.bracket.winner_goes_to(m.id), Some(to) if ids.contains(&to.id)),
+            )
+            .cloned()
+            .collect();
+        // Matches left out of the tree (there shouldn't be any) are placed after it.
+        roots.extend(matches.iter().cloned());
+
+        let mut tops: HashMap<MatchId, usize> = HashMap::new();
+        let mut next_slot = 0;
+        for root in roots {
+            self.place(root.id, &children, &mut tops, &mut next_slot);
+        }
+
+        let mut links = Vec::new();
+        for m in matches {
+            if let Some(to) = self.bracket.winner_goes_to(m.id) {
+                if !ids.contains(&to.id) {
+                    continue;
+                }
+                let slot = if to.player1.prereq_match_id == Some(m.id)
+                    && !to.player1.is_prereq_match_loser
+                {
+                    0
+                } else {
+                    1
+                };
+                let to_row = tops[&to.id] + slot;
+                let from_row = if to_row > tops[&m.id] {
+                    tops[&m.id] + 1
+                } else {
+                    tops[&m.id]
+                };
+                links.push(Link {
+                    from_column: column(m),
+                    from_row,
+                    to_column: column(to),
+                    to_row,
+                });
+            }
+        }
+
+        let labels = if self.show_rounds {
+            rounds
+                .iter()
+                .map(|&r| self.label(r, matches, &rounds))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let boxes = matches
+            .iter()
+            .map(|m| MatchBox {
+                identifier: m.identifier.clone(),
+                caption: self.caption(m),
+                column: column(m),
+                top: tops[&m.id],
+                lines: [self.line(m, 0), self.line(m, 1)],
+            })
+            .collect();
+        Section {
+            title: title.map(|t| t.to_owned()),
+            labels,
+            boxes,
+            links,
+            rows: tops.values().max().map_or(0, |t| t + 2),
+        }
+    }
+
+    fn place(
+        &self,
+        id: MatchId,
+        children: &dyn Fn(&Match) -> Vec<MatchId>,
+        tops: &mut HashMap<MatchId, usize>,
+        next_slot: &mut usize,
+    ) -> usize {
+        if let Some(&top) = tops.get(&id) {
+            return top;
+        }
+        // Marks the match as visited, the children overwrite nothing else.
+        tops.insert(id, 0);
+        let m = self.bracket.get(id).unwrap();
+        let child_tops: Vec<usize> = children(m)
+            .into_iter()
+            .map(|c| self.place(c, children, tops, next_slot))
+            .collect();
+        let top = if child_tops.is_empty() {
+            *next_slot += 1;
+            (*next_slot - 1) * SLOT_ROWS
+        } else {
+            child_tops.iter().sum::<usize>() / child_tops.len()
+        };
+        tops.insert(id, top);
+        top
+    }
+
+    fn label(&self, round: i64, matches: &[&Match], rounds: &[i64]) -> String {
+        let sides: Vec<Option<Side>> = matches
+            .iter()
+            .filter(|m| m.round == round)
+            .map(|m| self.bracket.side(m.id))
+            .collect();
+        if sides.iter().all(|s| *s == Some(Side::GrandFinals)) {
+            let reset = matches
+                .iter()
+                .filter(|m| m.round == round)
+                .all(|m| m.optional);
+            return if reset {
+                "Grand Final Reset"
+            } else {
+                "Grand Final"
+            }
+            .to_owned();
+        }
+        let last = rounds
+            .iter()
+            .filter(|&&r| {
+                matches
+                    .iter()
+                    .any(|m| m.round == r && self.bracket.side(m.id) != Some(Side::GrandFinals))
+            })
+            .map(|r| r.abs())
+            .max()
+            .unwrap_or(0);
+        match (round < 0, self.double, last - round.abs()) {
+            (true, _, 0) => "Losers Final".to_owned(),
+            (true, _, _) => format!("Losers Round {}", -round),
+            (false, true, 0) => "Winners Final".to_owned(),
+            (false, false, 0) => "Final".to_owned(),
+            (false, false, 1) => "Semifinals".to_owned(),
+            (false, _, _) => format!("Round {}", round),
+        }
+    }
+
+    /// The third place match shares the column of the final, so it is captioned.
+    fn caption(&self, m: &Match) -> Option<String> {
+        if self.show_rounds
+            && !self.double
+            && m.player1.is_prereq_match_loser
+            && m.player2.is_prereq_match_loser
+        {
+            Some("3rd place".to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn line(&self, m: &Match, slot: usize) -> Line {
+        let player = if slot == 0 { &m.player1 } else { &m.player2 };
+        match self.bracket.player(player) {
+            Some(id) => {
+                let (seed, name) = self.names.get(&id).cloned().unwrap_or((0, id.to_string()));
+                let score: Vec<String> = m
+                    .scores_csv
+                    .0
+                    .iter()
+                    .map(|s| if slot == 0 { s.0 } else { s.1 }.to_string())
+                    .collect();
+                Line {
+                    seed: if self.hide_seeds || seed == 0 {
+                        None
+                    } else {
+                        Some(seed)
+                    },
+                    name,
+                    score: score.join(","),
+                    winner: m.winner_id == Some(id),
+                }
+            }
+            None => Line {
+                seed: None,
+                name: player
+                    .prereq_match_id
+                    .and_then(|pre| self.bracket.get(pre))
+                    .map_or_else(
+                        || "TBD".to_owned(),
+                        |pre| {
+                            let who = if player.is_prereq_match_loser {
+                                "Loser"
+                            } else {
+                                "Winner"
+                            };
+                            format!("{} of {}", who, pre.identifier)
+                        },
+                    ),
+                score: String::new(),
+                winner: false,
+            },
+        }
+    }
+}
+
+/// Results between every pair of participants, ordered by seed: each cell holds
+/// the scores of the row's participant first.
+fn grid(tournament: &Tournament, participants: &ParticipantIndex, matches: &MatchIndex) -> Grid {
+    let mut active: Vec<_> = participants
+        .0
+        .iter()
+        .filter(|p| p.active && !p.on_waiting_list)
+        .collect();
+    active.sort_by_key(|p| p.seed);
+    let position: HashMap<ParticipantId, usize> =
+        active.iter().enumerate().map(|(i, p)| (p.id, i)).collect();
+
+    let n = active.len();
+    let mut cells: Vec<Vec<Vec<String>>> = vec![vec![Vec::new(); n]; n];
+    for m in &matches.0 {
+        if m.scores_csv.0.is_empty() {
+            continue;
+        }
+        let index = |id: Option<ParticipantId>| id.and_then(|id| position.get(&id));
+        let (a, b) = match (index(m.player1.id), index(m.player2.id)) {
+            (Some(&a), Some(&b)) => (a, b),
+            _ => continue,
+        };
+        let scores = |reverse: bool| -> String {
+            let s: Vec<String> = m
+                .scores_csv
+                .0
+                .iter()
+                .map(|s| {
+                    if reverse {
+                        format!("{}-{}", s.1, s.0)
+                    } else {
+                        format!("{}-{}", s.0, s.1)
+                    }
+                })
+                .collect();
+            s.join(",")
+        };
+        cells[a][b].push(scores(false));
+        cells[b][a].push(scores(true));
+    }
+    Grid {
+        names: active
+            .iter()
+            .map(|p| {
+                let seed = if tournament.hide_seeds {
+                    None
+                } else {
+                    Some(p.seed)
+                };
+                (seed, truncate(&p.name))
+            })
+            .collect(),
+        cells: cells
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.into_iter()
+                    .enumerate()
+                    .map(|(j, c)| if i == j { "-".to_owned() } else { c.join(" ") })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+impl Grid {
+    /// Widths of the row number, the name and the cells.
+    fn widths(&self) -> (usize, usize, usize) {
+        let number = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, (seed, _))| seed.unwrap_or(i as u64 + 1).to_string().len())
+            .max()
+            .unwrap_or(1);
+        let name = self
+            .names
+            .iter()
+            .map(|n| n.1.chars().count())
+            .max()
+            .unwrap_or(0);
+        let cell = self
+            .cells
+            .iter()
+            .flatten()
+            .map(|c| c.chars().count())
+            .chain(std::iter::once(number))
+            .max()
+            .unwrap_or(1);
+        (number, name, cell)
+    }
+
+    fn number(&self, i: usize) -> String {
+        self.names[i].0.unwrap_or(i as u64 + 1).to_string()
+    }
+}
+
+fn ascii_line(line: &Line, identifier: &str, widths: &Widths) -> String {
+    let mut s = format!("{:<w$} ", identifier, w = widths.identifier);
+    if widths.seed > 0 {
+        let seed = line.seed.map(|s| s.to_string()).unwrap_or_default();
+        let _ = write!(s, "{:>w$} ", seed, w = widths.seed);
+    }
+    let _ = write!(
+        s,
+        "{:<nw$} {:>sw$}{}",
+        line.name,
+        line.score,
+        if line.winner { '*' } else { ' ' },
+        nw = widths.name,
+        sw = widths.score
+    );
+    s
+}
+
+fn ascii_section(section: &Section, widths: &Widths) -> String {
+    let columns = section
+        .boxes
+        .iter()
+        .map(|b| b.column + 1)
+        .max()
+        .unwrap_or(0);
+    let width = widths.column_x(columns);
+    let mut canvas = vec![vec![' '; width]; section.rows];
+    let mut put = |row: usize, x: usize, c: char| {
+        if let Some(cell) = canvas.get_mut(row).and_then(|r| r.get_mut(x)) {
+            *cell = c;
+        }
+    };
+
+    for link in &section.links {
+        let start = widths.column_x(link.from_column) + widths.total();
+        let bend = widths.column_x(link.to_column).saturating_sub(2);
+        for x in start..bend {
+            put(link.from_row, x, '-');
+        }
+        let (low, high) = if link.from_row < link.to_row {
+            (link.from_row, link.to_row)
+        } else {
+            (link.to_row, link.from_row)
+        };
+        for row in low..=high {
+            put(row, bend, if row == low || row == high { '+' } else { '|' });
+        }
+        if low == high {
+            put(low, bend, '-');
+        }
+        put(link.to_row, bend + 1, '-');
+    }
+    for b in &section.boxes {
+        let x = widths.column_x(b.column);
+        if let (Some(caption), Some(row)) = (&b.caption, b.top.checked_sub(1)) {
+            for (j, c) in caption.chars().enumerate() {
+                put(row, x + j, c);
+            }
+        }
+        for (i, line) in b.lines.iter().enumerate() {
+            let identifier = if i == 0 { b.identifier.as_str() } else { "" };
+            for (j, c) in ascii_line(line, identifier, widths).chars().enumerate() {
+                put(b.top + i, x + j, c);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    if let Some(ref title) = section.title {
+        let _ = writeln!(out, "{}", title);
+    }
+    if !section.labels.is_empty() {
+        let mut header = String::new();
+        for (i, label) in section.labels.iter().enumerate() {
+            let x = widths.column_x(i);
+            while header.len() < x {
+                header.push(' ');
+            }
+            header.push_str(label);
+            header.push(' ');
+        }
+        let _ = writeln!(out, "{}", header.trim_end());
+    }
+    for row in canvas {
+        let line: String = row.into_iter().collect();
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+fn ascii_grid(grid: &Grid) -> String {
+    let (number, name, cell) = grid.widths();
+    let mut out = String::new();
+    let mut header = format!("{:>nw$} {:<w$} |", "", "", nw = number, w = name);
+    for i in 0..grid.names.len() {
+        let _ = write!(header, " {:>w$}", grid.number(i), w = cell);
+    }
+    let _ = writeln!(out, "{}", header);
+    for (i, row) in grid.cells.iter().enumerate() {
+        let mut line = format!(
+            "{:>nw$} {:<w$} |",
+            grid.number(i),
+            grid.names[i].1,
+            nw = number,
+            w = name
+        );
+        for c in row {
+            let _ = write!(line, " {:>w$}", c, w = cell);
+        }
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn px(chars: usize) -> usize {
+    PADDING + chars * CHAR_WIDTH
+}
+
+/// Returns the SVG elements of the sections, the width and the height of the image.
+fn svg_sections(sections: &[Section], widths: &Widths) -> (String, usize, usize) {
+    let (seed_x, name_x, score_x, total) = widths.offsets();
+    let mut out = String::new();
+    let mut y = PADDING;
+    let mut width = 0;
+    let mid = |top: usize, row: usize| top + row * ROW_HEIGHT + ROW_HEIGHT / 2;
+
+    for section in sections {
+        let columns = section
+            .boxes
+            .iter()
+            .map(|b| b.column + 1)
+            .max()
+            .unwrap_or(0);
+        width = width.max(px(widths.column_x(columns)) + PADDING);
+        if let Some(ref title) = section.title {
+            let _ = writeln!(
+                out,
+                r#"<text x="{}" y="{}" font-weight="bold">{}</text>"#,
+                PADDING,
+                y + 14,
+                escape(title)
+            );
+            y += ROW_HEIGHT;
+        }
+        if !section.labels.is_empty() {
+            for (i, label) in section.labels.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    r##"<text x="{}" y="{}" fill="#555">{}</text>"##,
+                    px(widths.column_x(i)),
+                    y + 14,
+                    escape(label)
+                );
+            }
+            y += ROW_HEIGHT;
+        }
+
+        for link in &section.links {
+            let start = px(widths.column_x(link.from_column) + widths.total());
+            let end = px(widths.column_x(link.to_column));
+            let bend = px(widths.column_x(link.to_column).saturating_sub(2)) + CHAR_WIDTH / 2;
+            let (from, to) = (mid(y, link.from_row), mid(y, link.to_row));
+            let _ = writeln!(
+                out,
+                r##"<polyline points="{},{} {},{} {},{} {},{}" fill="none" stroke="#888"/>"##,
+                start, from, bend, from, bend, to, end, to
+            );
+        }
+        for b in &section.boxes {
+            let x = widths.column_x(b.column);
+            let top = y + b.top * ROW_HEIGHT;
+            let _ = writeln!(
+                out,
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#f4f4f4" stroke="#888"/>"##,
+                px(x),
+                top,
+                total * CHAR_WIDTH,
+                2 * ROW_HEIGHT
+            );
+            let _ = writeln!(
+                out,
+                r##"<text x="{}" y="{}" fill="#888">{}</text>"##,
+                px(x) + 2,
+                top + 14,
+                escape(&b.identifier)
+            );
+            if let Some(ref caption) = b.caption {
+                let _ = writeln!(
+                    out,
+                    r##"<text x="{}" y="{}" fill="#555">{}</text>"##,
+                    px(x),
+                    top - 4,
+                    escape(caption)
+                );
+            }
+            for (i, line) in b.lines.iter().enumerate() {
+                let base = top + i * ROW_HEIGHT + 14;
+                let weight = if line.winner {
+                    r#" font-weight="bold""#
+                } else {
+                    ""
+                };
+                if let Some(seed) = line.seed {
+                    let _ = writeln!(
+                        out,
+                        r##"<text x="{}" y="{}" fill="#888" text-anchor="end">{}</text>"##,
+                        px(x + seed_x + widths.seed),
+                        base,
+                        seed
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{}" y="{}"{}>{}</text>"#,
+                    px(x + name_x),
+                    base,
+                    weight,
+                    escape(&line.name)
+                );
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{}" y="{}" text-anchor="end"{}>{}</text>"#,
+                    px(x + score_x + widths.score),
+                    base,
+                    weight,
+                    escape(&line.score)
+                );
+            }
+        }
+        y += section.rows * ROW_HEIGHT + ROW_HEIGHT;
+    }
+    (out, width, y + PADDING)
+}
+
+/// Returns the SVG elements of the grid, the width and the height of the image.
+fn svg_grid(grid: &Grid) -> (String, usize, usize) {
+    let (number, name, cell) = grid.widths();
+    let first = number + 1 + name + 1;
+    let column_x = |i: usize| first + i * (cell + 1);
+    let mut out = String::new();
+    let row_y = |i: usize| PADDING + (i + 1) * ROW_HEIGHT;
+
+    for i in 0..grid.names.len() {
+        let _ = writeln!(
+            out,
+            r##"<text x="{}" y="{}" fill="#555" text-anchor="end">{}</text>"##,
+            px(column_x(i) + cell),
+            PADDING + 14,
+            grid.number(i)
+        );
+    }
+    for (i, row) in grid.cells.iter().enumerate() {
+        let y = row_y(i);
+        let _ = writeln!(
+            out,
+            r##"<text x="{}" y="{}" fill="#555" text-anchor="end">{}</text>"##,
+            px(number),
+            y + 14,
+            grid.number(i)
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            px(number + 1),
+            y + 14,
+            escape(&grid.names[i].1)
+        );
+        for (j, c) in row.iter().enumerate() {
+            let fill = if i == j { "#ddd" } else { "#f4f4f4" };
+            let _ = writeln!(
+                out,
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="#888"/>"##,
+                px(column_x(j)) - CHAR_WIDTH / 2,
+                y,
+                (cell + 1) * CHAR_WIDTH,
+                ROW_HEIGHT,
+                fill
+            );
+            if i != j {
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+                    px(column_x(j) + cell),
+                    y + 14,
+                    escape(c)
+                );
+            }
+        }
+    }
+    let width = px(column_x(grid.names.len())) + PADDING;
+    let height = row_y(grid.names.len()) + PADDING;
+    (out, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::{double_elimination, make_match};
+    use crate::matches::Index as MatchIndex;
+    use crate::participants::tests::make_participant;
+    use crate::participants::Index as ParticipantIndex;
+    use crate::render::{ascii, svg};
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::{Tournament, TournamentType};
+
+    fn participants() -> ParticipantIndex {
+        ParticipantIndex(vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+            make_participant(4, "Dave & Co", 4),
+        ])
+    }
+
+    fn tournament(tournament_type: TournamentType, show_rounds: bool) -> Tournament {
+        let mut t = sample_tournament();
+        t.tournament_type = tournament_type;
+        t.show_rounds = show_rounds;
+        t.hide_seeds = false;
+        t
+    }
+
+    fn single_elimination() -> MatchIndex {
+        MatchIndex(vec![
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(4), None),
+                "complete",
+                Some(1),
+                "2-0",
+            ),
+            make_match(
+                2,
+                "B",
+                1,
+                (Some(2), None),
+                (Some(3), None),
+                "complete",
+                Some(3),
+                "1-2",
+            ),
+            make_match(
+                3,
+                "C",
+                2,
+                (Some(1), Some((1, false))),
+                (Some(3), Some((2, false))),
+                "open",
+                None,
+                "",
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_ascii_single_elimination() {
+        let t = tournament(TournamentType::SingleElimination, true);
+        let text = ascii(&t, &participants(), &single_elimination());
+        let expected = concat!(
+            "Semifinals         Final\n",
+            "A 1 Alice     2*\n",
+            "  4 Dave & Co 0 ---C 1 Alice\n",
+            "                 +-  3 Carol\n",
+            "B 2 Bob       1 -+\n",
+            "  3 Carol     2*\n",
+        );
+        assert_eq!(text, expected);
+
+        let t = tournament(TournamentType::SingleElimination, false);
+        let text = ascii(&t, &participants(), &single_elimination());
+        assert!(!text.contains("Final"));
+        assert!(text.starts_with("A 1 Alice"));
+    }
+
+    #[test]
+    fn test_ascii_third_place() {
+        let t = tournament(TournamentType::SingleElimination, true);
+        let mut matches = single_elimination();
+        matches.0.push(make_match(
+            4,
+            "D",
+            2,
+            (Some(4), Some((1, true))),
+            (Some(2), Some((2, true))),
+            "open",
+            None,
+            "",
+        ));
+        let text = ascii(&t, &participants(), &matches);
+        let lines: Vec<&str> = text.lines().collect();
+        let d = lines.iter().position(|l| l.contains("D 4 Dave")).unwrap();
+        assert_eq!(lines[d - 1].trim(), "3rd place");
+        assert_eq!(text.matches("Final").count(), 1);
+
+        let svg = svg(&t, &participants(), &matches);
+        assert!(svg.contains(">3rd place</text>"));
+    }
+
+    #[test]
+    fn test_ascii_double_elimination() {
+        let t = tournament(TournamentType::DoubleElimination, true);
+        let text = ascii(&t, &participants(), &double_elimination());
+        assert!(text.starts_with("Winners Bracket\nRound 1"));
+        assert!(text.contains("Winners Final"));
+        assert!(text.contains("Grand Final"));
+        assert!(!text.contains("Grand Final Reset"));
+        assert!(text.contains("\nLosers Bracket\nLosers Round 1"));
+        assert!(text.contains("Losers Final"));
+        // The losers final waits for the loser of the winners final.
+        assert!(text.contains("Loser of C"));
+        assert!(text.contains("Winner of F"));
+    }
+
+    #[test]
+    fn test_ascii_round_robin() {
+        let t = tournament(TournamentType::RoundRobin, false);
+        let matches = MatchIndex(vec![
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(2), None),
+                "complete",
+                Some(1),
+                "3-1",
+            ),
+            make_match(
+                2,
+                "B",
+                1,
+                (Some(3), None),
+                (Some(4), None),
+                "complete",
+                Some(4),
+                "0-2",
+            ),
+            make_match(
+                3,
+                "C",
+                2,
+                (Some(1), None),
+                (Some(3), None),
+                "open",
+                None,
+                "",
+            ),
+        ]);
+        let text = ascii(&t, &participants(), &matches);
+        let expected = concat!(
+            "            |   1   2   3   4\n",
+            "1 Alice     |   - 3-1\n",
+            "2 Bob       | 1-3   -\n",
+            "3 Carol     |           - 0-2\n",
+            "4 Dave & Co |         2-0   -\n",
+        );
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn test_svg() {
+        let t = tournament(TournamentType::SingleElimination, true);
+        let image = svg(&t, &participants(), &single_elimination());
+        assert!(image.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(image.trim_end().ends_with("</svg>"));
+        assert!(image.contains(">Semifinals</text>"));
+        assert!(image.contains(">Dave &amp; Co</text>"));
+        assert!(image.contains(r#"font-weight="bold">Alice</text>"#));
+        assert_eq!(image.matches("<polyline").count(), 2);
+
+        let t = tournament(TournamentType::RoundRobin, false);
+        let image = svg(&t, &participants(), &MatchIndex(Vec::new()));
+        assert_eq!(image.matches("<rect").count(), 17);
+    }
+}
@@ -0,0 +1,314 @@
+//! Assignment of open matches to stations.
+//!
+//! `Scheduler` assigns the open matches of a tournament to free stations (setups, tables,
+//! courts), so that no player plays two matches at once. A match whose `location` is the
+//! name of a station is considered to be played there, so the stations written back with
+//! `Scheduler::push` stay occupied until their matches are completed.
+//!
+//! Matches are taken by their prerequisite depth (earlier bracket matches first), then by
+//! `suggested_play_order` and round. Stream stations take the most advanced playable match
+//! instead, so the stream shows the later stages of the bracket.
+
+use std::collections::HashSet;
+
+use crate::bracket::Bracket;
+use crate::error::Error;
+use crate::matches::{Index as MatchIndex, Match, MatchId, MatchState, MatchUpdate};
+use crate::participants::ParticipantId;
+use crate::tournament::TournamentId;
+use crate::Challonge;
+
+/// A place where matches are played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Station {
+    /// Name of the station, written into the match `location`
+    pub name: String,
+    /// The station is streamed
+    pub stream: bool,
+}
+impl Station {
+    /// Creates a station.
+    pub fn new<S: Into<String>>(name: S) -> Station {
+        Station {
+            name: name.into(),
+            stream: false,
+        }
+    }
+
+    /// Creates a streamed station.
+    pub fn stream<S: Into<String>>(name: S) -> Station {
+        Station {
+            name: name.into(),
+            stream: true,
+        }
+    }
+}
+
+/// A match assigned to a station.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    /// The match
+    pub match_id: MatchId,
+    /// Identifier of the match in the bracket
+    pub identifier: String,
+    /// Name of the station
+    pub station: String,
+    /// The station is streamed
+    pub stream: bool,
+}
+
+/// The result of scheduling.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    /// New assignments, stream stations first
+    pub assignments: Vec<Assignment>,
+    /// Stations still playing a match assigned earlier, with the match
+    pub occupied: Vec<(String, MatchId)>,
+    /// Open matches waiting for a station or a player, the next one first
+    pub queue: Vec<MatchId>,
+}
+
+/// Assigns open matches to stations.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    stations: Vec<Station>,
+    unavailable: HashSet<ParticipantId>,
+}
+impl Scheduler {
+    /// Creates a scheduler for the stations.
+    pub fn new(stations: Vec<Station>) -> Scheduler {
+        Scheduler {
+            stations,
+            unavailable: HashSet::new(),
+        }
+    }
+
+    /// Marks the player as not available (for example on a break):
+    /// their matches wait in the queue.
+    pub fn unavailable(&mut self, player: ParticipantId) -> &mut Self {
+        self.unavailable.insert(player);
+        self
+    }
+
+    /// Marks the player as available again.
+    pub fn available(&mut self, player: ParticipantId) -> &mut Self {
+        self.unavailable.remove(&player);
+        self
+    }
+
+    /// Assigns the open matches to the free stations.
+    pub fn schedule(&self, matches: &MatchIndex) -> Schedule {
+        let bracket = Bracket::new(matches);
+        let mut schedule = Schedule::default();
+        let mut busy: HashSet<ParticipantId> = self.unavailable.clone();
+        let mut free: Vec<&Station> = Vec::new();
+
+        let open: Vec<&Match> = matches
+            .0
+            .iter()
+            .filter(|m| m.state == MatchState::Open)
+            .collect();
+        for station in &self.stations {
+            let playing = open
+                .iter()
+                .find(|m| m.location.as_deref() == Some(station.name.as_str()));
+            match playing {
+                Some(m) => {
+                    busy.extend(m.player1.id);
+                    busy.extend(m.player2.id);
+                    schedule.occupied.push((station.name.clone(), m.id));
+                }
+                None => free.push(station),
+            }
+        }
+
+        let mut waiting: Vec<&Match> = open
+            .into_iter()
+            .filter(|m| !schedule.occupied.iter().any(|(_, id)| *id == m.id))
+            .collect();
+        let depth = |m: &Match| bracket.depth(m.id).unwrap_or(0);
+        waiting.sort_by_key(|m| {
+            (
+                depth(m),
+                m.suggested_play_order.unwrap_or(u64::MAX),
+                m.round.abs(),
+                m.id,
+            )
+        });
+
+        // Stream stations first, so they can pick the most advanced match.
+        free.sort_by_key(|s| !s.stream);
+        for station in free {
+            let playable = |m: &&Match| {
+                [m.player1.id, m.player2.id]
+                    .iter()
+                    .flatten()
+                    .all(|id| !busy.contains(id))
+            };
+            let pick = if station.stream {
+                waiting
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| playable(m))
+                    .max_by_key(|(i, m)| (depth(m), std::cmp::Reverse(*i)))
+                    .map(|(i, _)| i)
+            } else {
+                waiting.iter().position(playable)
+            };
+            if let Some(i) = pick {
+                let m = waiting.remove(i);
+                busy.extend(m.player1.id);
+                busy.extend(m.player2.id);
+                schedule.assignments.push(Assignment {
+                    match_id: m.id,
+                    identifier: m.identifier.clone(),
+                    station: station.name.clone(),
+                    stream: station.stream,
+                });
+            }
+        }
+        schedule.queue = waiting.iter().map(|m| m.id).collect();
+        schedule
+    }
+
+    /// Writes the stations of the new assignments into the matches' `location`.
+    pub fn push(
+        &self,
+        c: &Challonge,
+        id: &TournamentId,
+        schedule: &Schedule,
+    ) -> Result<Vec<Match>, Error> {
+        schedule
+            .assignments
+            .iter()
+            .map(|a| {
+                c.update_match(
+                    id,
+                    &a.match_id,
+                    MatchUpdate::new().location(a.station.as_str()),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::{Index as MatchIndex, MatchId};
+    use crate::participants::ParticipantId;
+    use crate::scheduler::{Scheduler, Station};
+
+    /// Double elimination of 8 players: A and B are completed, C, D, E (A and B winners)
+    /// and G (A and B losers) are open, F waits for C and D.
+    fn matches() -> MatchIndex {
+        let first = |id, identifier, p1, p2, state, winner| {
+            make_match(
+                id,
+                identifier,
+                1,
+                (Some(p1), None),
+                (Some(p2), None),
+                state,
+                winner,
+                if winner.is_some() { "2-0" } else { "" },
+            )
+        };
+        MatchIndex(vec![
+            first(1, "A", 1, 8, "complete", Some(1)),
+            first(2, "B", 4, 5, "complete", Some(4)),
+            first(3, "C", 2, 7, "open", None),
+            first(4, "D", 3, 6, "open", None),
+            make_match(
+                5,
+                "E",
+                2,
+                (Some(1), Some((1, false))),
+                (Some(4), Some((2, false))),
+                "open",
+                None,
+                "",
+            ),
+            make_match(
+                6,
+                "F",
+                2,
+                (None, Some((3, false))),
+                (None, Some((4, false))),
+                "pending",
+                None,
+                "",
+            ),
+            make_match(
+                7,
+                "G",
+                -1,
+                (Some(8), Some((1, true))),
+                (Some(5), Some((2, true))),
+                "open",
+                None,
+                "",
+            ),
+        ])
+    }
+
+    fn ids(ms: &[MatchId]) -> Vec<u64> {
+        ms.iter().map(|m| m.0).collect()
+    }
+
+    #[test]
+    fn test_schedule() {
+        let scheduler = Scheduler::new(vec![
+            Station::new("Setup 1"),
+            Station::new("Setup 2"),
+            Station::stream("Stream"),
+        ]);
+        let s = scheduler.schedule(&matches());
+        let assigned: Vec<(&str, u64)> = s
+            .assignments
+            .iter()
+            .map(|a| (a.station.as_str(), a.match_id.0))
+            .collect();
+        assert_eq!(
+            assigned,
+            vec![("Stream", 5), ("Setup 1", 3), ("Setup 2", 4)]
+        );
+        assert!(s.assignments[0].stream);
+        assert!(s.occupied.is_empty());
+        assert_eq!(ids(&s.queue), vec![7]);
+    }
+
+    #[test]
+    fn test_occupied_and_unavailable() {
+        let mut ms = matches();
+        ms.0[2].location = Some("Setup 1".to_owned());
+        ms.0[4].location = Some("Stream".to_owned());
+        let mut scheduler = Scheduler::new(vec![
+            Station::new("Setup 1"),
+            Station::new("Setup 2"),
+            Station::new("Setup 3"),
+            Station::stream("Stream"),
+        ]);
+        scheduler.unavailable(ParticipantId(5));
+        let s = scheduler.schedule(&ms);
+        assert_eq!(
+            s.occupied,
+            vec![
+                ("Setup 1".to_owned(), MatchId(3)),
+                ("Stream".to_owned(), MatchId(5))
+            ]
+        );
+        let assigned: Vec<(&str, u64)> = s
+            .assignments
+            .iter()
+            .map(|a| (a.station.as_str(), a.match_id.0))
+            .collect();
+        assert_eq!(assigned, vec![("Setup 2", 4)]);
+        assert_eq!(ids(&s.queue), vec![7]);
+
+        scheduler.available(ParticipantId(5));
+        let s = scheduler.schedule(&ms);
+        assert_eq!(s.assignments[1].match_id, MatchId(7));
+        assert_eq!(s.assignments[1].station, "Setup 3");
+    }
+}
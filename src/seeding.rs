@@ -0,0 +1,434 @@
+//! Seeding by rating.
+//!
+//! `Seeder` orders participants (existing `Participant`s or `ParticipantCreate`s about to be
+//! added) by an external rating, optionally moving players so that players of the same
+//! region or team (the participant's `misc` field, after a team's roster) don't meet in the first round of a
+//! standard bracket. The resulting `Plan` can be inspected as a diff of seed changes before
+//! it is pushed to Challonge.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::Error;
+use crate::generator::seed_order;
+use crate::participants::{decode_roster, Participant, ParticipantCreate};
+use crate::ratings::PlayerRating;
+use crate::tournament::TournamentId;
+use crate::Challonge;
+
+/// A participant which can be seeded.
+pub trait Seedable {
+    /// The names the participant may be rated under.
+    fn keys(&self) -> Vec<&str>;
+
+    /// The region or team of the participant, empty if none. The roster of a team isn't part
+    /// of it (see `participants::decode_roster`).
+    fn region(&self) -> &str;
+
+    /// The current seed, if any.
+    fn current_seed(&self) -> Option<u64>;
+
+    /// The name to show in the diff.
+    fn label(&self) -> &str;
+}
+
+impl Seedable for Participant {
+    fn keys(&self) -> Vec<&str> {
+        vec![&self.challonge_username, &self.email_hash, &self.name]
+    }
+
+    fn region(&self) -> &str {
+        decode_roster(&self.misc).1
+    }
+
+    fn current_seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn label(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Seedable for ParticipantCreate {
+    fn keys(&self) -> Vec<&str> {
+        [
+            &self.challonge_username,
+            &self.email,
+            &self.invite_name_or_email,
+            &self.name,
+        ]
+        .iter()
+        .filter_map(|k| k.as_deref())
+        .collect()
+    }
+
+    fn region(&self) -> &str {
+        decode_roster(self.misc.as_deref().unwrap_or("")).1
+    }
+
+    fn current_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn label(&self) -> &str {
+        self.keys().last().copied().unwrap_or("")
+    }
+}
+
+/// A change of a participant's seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedChange {
+    /// Index of the participant in the seeded list
+    pub index: usize,
+    /// Name of the participant
+    pub name: String,
+    /// The current seed
+    pub from: Option<u64>,
+    /// The new seed
+    pub to: u64,
+}
+
+/// The result of seeding.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    /// Indices of the seeded participants, the first seed first
+    pub order: Vec<usize>,
+    /// Participants whose seed changes, by new seed
+    pub changes: Vec<SeedChange>,
+    /// First round matches between players of the same region which couldn't be avoided,
+    /// as pairs of seeds
+    pub conflicts: Vec<(u64, u64)>,
+}
+impl Plan {
+    /// Returns whether the seeds don't change.
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Updates the seeds of the participants on Challonge.
+    ///
+    /// Challonge shifts the other participants when a seed is changed, so all the
+    /// participants from the first changed seed down are updated, the first seed first.
+    /// The participants must be the ones the plan was made for.
+    pub fn push(
+        &self,
+        c: &Challonge,
+        id: &TournamentId,
+        participants: &[Participant],
+    ) -> Result<(), Error> {
+        self.check(participants.len())?;
+        let first_changed = match self.changes.first() {
+            Some(change) => change.to as usize - 1,
+            None => return Ok(()),
+        };
+        for (seed, &index) in self.order.iter().enumerate().skip(first_changed) {
+            let p = &participants[index];
+            c.update_participant(id, &p.id, ParticipantCreate::new().seed(seed as u64 + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the participants in the seeded order with their seeds set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the participants aren't the ones the plan was made for.
+    pub fn seeded(&self, participants: &[ParticipantCreate]) -> Vec<ParticipantCreate> {
+        self.order
+            .iter()
+            .enumerate()
+            .map(|(seed, &index)| {
+                let mut p = participants[index].clone();
+                p.seed = Some(seed as u64 + 1);
+                p
+            })
+            .collect()
+    }
+
+    /// Adds the participants to the tournament in the seeded order, after the participants
+    /// already in it.
+    pub fn push_bulk(
+        &self,
+        c: &Challonge,
+        id: &TournamentId,
+        participants: &[ParticipantCreate],
+    ) -> Result<(), Error> {
+        self.check(participants.len())?;
+        let appended = self
+            .seeded(participants)
+            .into_iter()
+            .map(|mut p| {
+                p.seed = None;
+                p
+            })
+            .collect();
+        c.create_participant_bulk(id, appended)
+    }
+
+    fn check(&self, participants: usize) -> Result<(), Error> {
+        if participants == self.order.len() {
+            Ok(())
+        } else {
+            Err(Error::Api(
+                "The plan was made for a different number of participants",
+            ))
+        }
+    }
+}
+
+/// Writes the seed changes, one per line, like `Alice: 3 -> 1`.
+impl fmt::Display for Plan {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            match change.from {
+                Some(from) => writeln!(fmt, "{}: {} -> {}", change.name, from, change.to)?,
+                None => writeln!(fmt, "{}: new -> {}", change.name, change.to)?,
+            }
+        }
+        for (a, b) in &self.conflicts {
+            writeln!(fmt, "conflict: seeds {} and {} share a region", a, b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Orders participants by rating.
+#[derive(Debug, Clone, Default)]
+pub struct Seeder {
+    ratings: HashMap<String, f64>,
+    separate_regions: bool,
+}
+impl Seeder {
+    /// Creates a seeder without ratings.
+    pub fn new() -> Seeder {
+        Seeder::default()
+    }
+
+    /// Sets the rating of a player, by a name the participant may be rated under:
+    /// the Challonge username, email (hash for existing participants) or name.
+    pub fn rating<S: AsRef<str>>(&mut self, key: S, rating: f64) -> &mut Self {
+        self.ratings.insert(key.as_ref().to_lowercase(), rating);
+        self
+    }
+
+    /// Sets the ratings of the players.
+    pub fn ratings(&mut self, players: &[PlayerRating]) -> &mut Self {
+        for p in players {
+            self.rating(&p.player, p.rating.rating);
+        }
+        self
+    }
+
+    /// Avoids first round matches between players of the same region or team.
+    pub fn separate_regions(&mut self, separate: bool) -> &mut Self {
+        self.separate_regions = separate;
+        self
+    }
+
+    fn rating_of<T: Seedable>(&self, p: &T) -> Option<f64> {
+        p.keys()
+            .iter()
+            .filter(|k| !k.is_empty())
+            .find_map(|k| self.ratings.get(&k.to_lowercase()).copied())
+    }
+
+    /// Seeds the participants: rated ones by rating, the best first, then unrated ones
+    /// in their current order.
+    pub fn plan<T: Seedable>(&self, participants: &[T]) -> Plan {
+        let mut order: Vec<usize> = (0..participants.len()).collect();
+        let ratings: Vec<Option<f64>> = participants.iter().map(|p| self.rating_of(p)).collect();
+        order.sort_by(|&a, &b| {
+            let by_rating = match (ratings[a], ratings[b]) {
+                (Some(ra), Some(rb)) => rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal),
+                (ra, rb) => rb.is_some().cmp(&ra.is_some()),
+            };
+            let seed = |i: usize| {
+                (
+                    participants[i].current_seed().is_none(),
+                    participants[i].current_seed(),
+                )
+            };
+            by_rating.then_with(|| seed(a).cmp(&seed(b)))
+        });
+
+        let region = |order: &[usize], seed: u64| participants[order[seed as usize - 1]].region();
+        let conflict = |order: &[usize], (a, b): (u64, u64)| {
+            let (ra, rb) = (region(order, a), region(order, b));
+            !ra.is_empty() && ra.eq_ignore_ascii_case(rb)
+        };
+        let pairs = first_round(participants.len());
+        if self.separate_regions {
+            for &(a, b) in &pairs {
+                if !conflict(&order, (a, b)) {
+                    continue;
+                }
+                // Swap the lower seed with the closest one of a similar strength.
+                let mut candidates: Vec<u64> = (1..=participants.len() as u64)
+                    .filter(|&c| c != a && c != b && band(c) == band(b))
+                    .collect();
+                candidates.sort_by_key(|&c| ((c as i64 - b as i64).abs(), c));
+                for c in candidates {
+                    order.swap(b as usize - 1, c as usize - 1);
+                    let resolved = pairs
+                        .iter()
+                        .filter(|&&(x, y)| [x, y].iter().any(|s| *s == b || *s == c))
+                        .all(|&pair| !conflict(&order, pair));
+                    if resolved {
+                        break;
+                    }
+                    order.swap(b as usize - 1, c as usize - 1);
+                }
+            }
+        }
+
+        let changes = order
+            .iter()
+            .enumerate()
+            .filter_map(|(seed, &index)| {
+                let p = &participants[index];
+                let to = seed as u64 + 1;
+                if p.current_seed() == Some(to) {
+                    return None;
+                }
+                Some(SeedChange {
+                    index,
+                    name: p.label().to_owned(),
+                    from: p.current_seed(),
+                    to,
+                })
+            })
+            .collect();
+        let conflicts = pairs
+            .into_iter()
+            .filter(|&pair| conflict(&order, pair))
+            .collect();
+        Plan {
+            order,
+            changes,
+            conflicts,
+        }
+    }
+}
+
+/// Seeds of the first round matches of a standard bracket.
+fn first_round(participants: usize) -> Vec<(u64, u64)> {
+    let size = participants.next_power_of_two();
+    seed_order(size, participants, false)
+        .chunks(2)
+        .filter_map(|pair| match *pair {
+            [Some(a), Some(b)] => Some((a.min(b) as u64, a.max(b) as u64)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Seeds of the same band (1, 2, 3-4, 5-8, 9-16...) are considered of a similar strength.
+fn band(seed: u64) -> u32 {
+    64 - (seed - 1).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::participants::tests::make_participant;
+    use crate::participants::{ParticipantCreate, TeamCreate};
+    use crate::seeding::{SeedChange, Seedable, Seeder};
+    use crate::tournament::TournamentId;
+    use crate::{Challonge, Error};
+
+    #[test]
+    fn test_plan_by_rating() {
+        let participants = vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+            make_participant(4, "Dave", 4),
+        ];
+        let mut seeder = Seeder::new();
+        seeder.rating("carol", 1800.0).rating("BOB", 1700.0);
+        let plan = seeder.plan(&participants);
+        assert_eq!(plan.order, vec![2, 1, 0, 3]);
+        assert_eq!(
+            plan.changes,
+            vec![
+                SeedChange {
+                    index: 2,
+                    name: "Carol".to_owned(),
+                    from: Some(3),
+                    to: 1
+                },
+                SeedChange {
+                    index: 0,
+                    name: "Alice".to_owned(),
+                    from: Some(1),
+                    to: 3
+                },
+            ]
+        );
+        assert_eq!(plan.to_string(), "Carol: 3 -> 1\nAlice: 1 -> 3\n");
+        assert!(Seeder::new().plan(&participants).is_unchanged());
+
+        // Checked before anything is sent.
+        let c = Challonge::new("user", "key");
+        let id = TournamentId::Id(1);
+        assert!(matches!(
+            plan.push(&c, &id, &participants[..3]),
+            Err(Error::Api(_))
+        ));
+        let creates = vec![ParticipantCreate::new().name("Alice").clone()];
+        assert!(matches!(
+            plan.push_bulk(&c, &id, &creates),
+            Err(Error::Api(_))
+        ));
+    }
+
+    #[test]
+    fn test_separate_regions() {
+        let region =
+            |name: &str, region: &str| ParticipantCreate::new().name(name).misc(region).clone();
+        let participants = vec![
+            region("P1", "North"),
+            region("P2", "South"),
+            region("P3", "East"),
+            region("P4", "West"),
+            region("P5", ""),
+            region("P6", "East"),
+            region("P7", "South"),
+            region("P8", "north"),
+        ];
+        let mut seeder = Seeder::new();
+        for (i, p) in participants.iter().enumerate() {
+            seeder.rating(p.name.as_ref().unwrap(), 2000.0 - i as f64);
+        }
+        let plan = seeder.plan(&participants);
+        assert_eq!(plan.conflicts, vec![(1, 8), (2, 7), (3, 6)]);
+
+        seeder.separate_regions(true);
+        let plan = seeder.plan(&participants);
+        assert!(plan.conflicts.is_empty(), "{}", plan);
+        // P8 and P7 swap, then P6 and P5.
+        assert_eq!(plan.order, vec![0, 1, 2, 3, 5, 4, 7, 6]);
+        let seeded = plan.seeded(&participants);
+        assert_eq!(seeded[6].name.as_deref(), Some("P8"));
+        assert_eq!(seeded[6].seed, Some(7));
+        assert_eq!(plan.to_string().lines().nth(4), Some("P6: new -> 5"));
+    }
+
+    #[test]
+    fn test_region_after_roster() {
+        let mut p = make_participant(1, "Team A", 1);
+        p.misc = "members: Alice, Bob\nNorth".to_owned();
+        assert_eq!(p.region(), "North");
+        p.misc = "North".to_owned();
+        assert_eq!(p.region(), "North");
+        p.misc = "members: Alice, Bob".to_owned();
+        assert_eq!(p.region(), "");
+
+        let team = TeamCreate::new("Team B")
+            .member("Carol")
+            .misc("South")
+            .to_participant()
+            .unwrap();
+        assert_eq!(team.region(), "South");
+    }
+}
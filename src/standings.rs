@@ -0,0 +1,573 @@
+//! Offline standings of Round Robin and Swiss tournaments.
+//!
+//! Challonge computes the final ranks only when the tournament is finalized.
+//! `Standings` computes them at any moment from the completed matches, using the
+//! tournament's points, ranking order and tie-breaks.
+//! The groups of a group stage are ranked separately, see `Standings::groups`.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::matches::{Index as MatchIndex, Match, MatchState};
+use crate::participants::{GroupId, Index as ParticipantIndex, ParticipantId};
+use crate::tournament::{GamePoints, RankedBy, TieBreak, Tournament, TournamentType};
+
+/// A row of the standings table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    /// Rank of the participant, tied participants share the same rank.
+    pub rank: u64,
+
+    /// Unique participant identifier
+    pub participant_id: ParticipantId,
+
+    /// Name of the participant.
+    pub name: String,
+
+    /// Seed of the participant in the tournament.
+    pub seed: u64,
+
+    /// Number of matches won
+    pub wins: u64,
+
+    /// Number of matches lost
+    pub losses: u64,
+
+    /// Number of tie matches
+    pub ties: u64,
+
+    /// Number of games won
+    pub game_wins: u64,
+
+    /// Number of games lost
+    pub game_losses: u64,
+
+    /// Number of tie games
+    pub game_ties: u64,
+
+    /// Number of byes (Swiss rounds without a match)
+    pub byes: u64,
+
+    /// Points earned according to the tournament's points configuration
+    pub points: f64,
+
+    /// Sum of the participant's scores in all games
+    pub points_scored: i64,
+
+    /// Difference between the scored and the conceded points
+    pub points_difference: i64,
+}
+impl Standing {
+    /// Returns the percentage of games won, from 0 to 1.
+    pub fn game_win_percentage(&self) -> f64 {
+        let games = self.game_wins + self.game_losses + self.game_ties;
+        if games == 0 {
+            0f64
+        } else {
+            self.game_wins as f64 / games as f64
+        }
+    }
+}
+
+/// Ranked standings of a tournament.
+#[derive(Debug, Clone)]
+pub struct Standings(pub Vec<Standing>);
+impl Standings {
+    /// Computes the standings from the tournament's completed matches.
+    /// The matches of the group stage are not counted.
+    ///
+    /// Points are counted with `swiss_points` for Swiss and with `round_robin_points`
+    /// otherwise. Participants are ranked by the tournament's `ranked_by` (always by points
+    /// for Swiss and custom ranking), then by its `tie_breaks`, and listed by seed when still tied.
+    pub fn new(
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+    ) -> Standings {
+        let final_stage = MatchIndex(
+            matches
+                .0
+                .iter()
+                .filter(|m| m.group_id.is_none())
+                .cloned()
+                .collect(),
+        );
+        let swiss = tournament.tournament_type == TournamentType::Swiss;
+        Standings::rank(tournament, participants, &final_stage, swiss)
+    }
+
+    /// Computes the standings of every group of the group stage, ranked like a round robin.
+    ///
+    /// Group stage matches refer to the players by their `group_player_ids`, a group
+    /// consists of the participants who play in its matches.
+    pub fn groups(
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+    ) -> BTreeMap<GroupId, Standings> {
+        let mut groups: BTreeMap<GroupId, MatchIndex> = BTreeMap::new();
+        for m in &matches.0 {
+            if let Some(group_id) = m.group_id {
+                groups
+                    .entry(group_id)
+                    .or_insert_with(|| MatchIndex(Vec::new()))
+                    .0
+                    .push(m.clone());
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(group_id, group_matches)| {
+                let plays = |id: &ParticipantId| {
+                    group_matches
+                        .0
+                        .iter()
+                        .any(|m| m.player1.id == Some(*id) || m.player2.id == Some(*id))
+                };
+                let members = ParticipantIndex(
+                    participants
+                        .0
+                        .iter()
+                        .filter(|p| plays(&p.id) || p.group_player_ids.iter().any(plays))
+                        .cloned()
+                        .collect(),
+                );
+                let standings = Standings::rank(tournament, &members, &group_matches, false);
+                (group_id, standings)
+            })
+            .collect()
+    }
+
+    fn rank(
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+        swiss: bool,
+    ) -> Standings {
+        let points = if swiss {
+            &tournament.swiss_points
+        } else {
+            &tournament.round_robin_points
+        };
+        let ranked_by = if swiss {
+            RankedBy::Custom
+        } else {
+            tournament.ranked_by
+        };
+
+        let rows = tally(participants, matches, points, swiss);
+        let completed: Vec<&Match> = matches
+            .0
+            .iter()
+            .filter(|m| m.state == MatchState::Complete)
+            .collect();
+
+        let mut criteria = vec![Criterion::RankedBy(ranked_by)];
+        criteria.extend(
+            tournament
+                .tie_breaks
+                .iter()
+                .map(|tb| Criterion::TieBreak(*tb)),
+        );
+        let all: Vec<usize> = (0..rows.len()).collect();
+        let groups = rank_group(&rows, &completed, all, &criteria);
+
+        let mut ranked = Vec::with_capacity(rows.len());
+        for group in groups {
+            let rank = ranked.len() as u64 + 1;
+            for i in group {
+                let mut row = rows[i].clone();
+                row.rank = rank;
+                ranked.push(row);
+            }
+        }
+        Standings(ranked)
+    }
+
+    /// Returns the standing of the participant.
+    pub fn get(&self, participant_id: ParticipantId) -> Option<&Standing> {
+        self.0.iter().find(|s| s.participant_id == participant_id)
+    }
+}
+
+/// Counts the results of the completed matches for every participant, without ranking them.
+/// Participants without a match in a completed round get a bye if `byes` is set.
+pub(crate) fn tally(
+    participants: &ParticipantIndex,
+    matches: &MatchIndex,
+    points: &GamePoints,
+    byes: bool,
+) -> Vec<Standing> {
+    let mut rows: Vec<Standing> = participants
+        .0
+        .iter()
+        .map(|p| Standing {
+            rank: 0,
+            participant_id: p.id,
+            name: p.name.clone(),
+            seed: p.seed,
+            wins: 0,
+            losses: 0,
+            ties: 0,
+            game_wins: 0,
+            game_losses: 0,
+            game_ties: 0,
+            byes: 0,
+            points: 0f64,
+            points_scored: 0,
+            points_difference: 0,
+        })
+        .collect();
+    // Group stage matches refer to the participants by their group player ids.
+    let positions: HashMap<ParticipantId, usize> = participants
+        .0
+        .iter()
+        .enumerate()
+        .flat_map(|(i, p)| {
+            std::iter::once(p.id)
+                .chain(p.group_player_ids.iter().cloned())
+                .map(move |id| (id, i))
+        })
+        .collect();
+
+    let completed: Vec<&Match> = matches
+        .0
+        .iter()
+        .filter(|m| m.state == MatchState::Complete)
+        .collect();
+    for m in &completed {
+        let players = [m.player1.id, m.player2.id];
+        for (side, id) in players.iter().enumerate() {
+            let row = match id.and_then(|id| positions.get(&id)) {
+                Some(&i) => &mut rows[i],
+                None => continue,
+            };
+            match m.winner_id {
+                Some(w) if Some(w) == *id => row.wins += 1,
+                Some(_) => row.losses += 1,
+                None => row.ties += 1,
+            }
+            for s in &m.scores_csv.0 {
+                let (own, other) = if side == 0 { (s.0, s.1) } else { (s.1, s.0) };
+                match own.cmp(&other) {
+                    Ordering::Greater => row.game_wins += 1,
+                    Ordering::Less => row.game_losses += 1,
+                    Ordering::Equal => row.game_ties += 1,
+                }
+                row.points_scored += own;
+                row.points_difference += own - other;
+            }
+        }
+    }
+    if byes {
+        count_byes(&mut rows, &positions, matches);
+    }
+    for row in &mut rows {
+        row.points = total_points(row, points);
+    }
+    rows
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Criterion {
+    RankedBy(RankedBy),
+    TieBreak(TieBreak),
+}
+
+fn total_points(row: &Standing, points: &GamePoints) -> f64 {
+    points.match_win * row.wins as f64
+        + points.match_tie * row.ties as f64
+        + points.game_win * row.game_wins as f64
+        + points.game_tie * row.game_ties as f64
+        + points.bye.unwrap_or(0f64) * row.byes as f64
+}
+
+/// Every participant without a match in a completed Swiss round had a bye in it.
+fn count_byes(
+    rows: &mut [Standing],
+    positions: &HashMap<ParticipantId, usize>,
+    matches: &MatchIndex,
+) {
+    let rounds: BTreeSet<i64> = matches.0.iter().map(|m| m.round).collect();
+    for round in rounds {
+        let round_matches: Vec<&Match> = matches.0.iter().filter(|m| m.round == round).collect();
+        if round_matches
+            .iter()
+            .any(|m| m.state != MatchState::Complete)
+        {
+            continue;
+        }
+        let played: BTreeSet<usize> = round_matches
+            .iter()
+            .flat_map(|m| vec![m.player1.id, m.player2.id])
+            .filter_map(|id| id.and_then(|id| positions.get(&id)).cloned())
+            .collect();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if !played.contains(&i) {
+                row.byes += 1;
+            }
+        }
+    }
+}
+
+fn value(rows: &[Standing], completed: &[&Match], group: &[usize], i: usize, c: Criterion) -> f64 {
+    let row = &rows[i];
+    match c {
+        Criterion::RankedBy(RankedBy::MatchWins) => row.wins as f64 + row.ties as f64 / 2f64,
+        Criterion::RankedBy(RankedBy::GameWins) | Criterion::TieBreak(TieBreak::GameWins) => {
+            row.game_wins as f64 + row.game_ties as f64 / 2f64
+        }
+        Criterion::RankedBy(RankedBy::PointsScored)
+        | Criterion::TieBreak(TieBreak::PointsScored) => row.points_scored as f64,
+        Criterion::RankedBy(RankedBy::PointsDifference)
+        | Criterion::TieBreak(TieBreak::PointsDifference) => row.points_difference as f64,
+        Criterion::RankedBy(RankedBy::Custom) => row.points,
+        Criterion::TieBreak(TieBreak::GameWinPercentage) => row.game_win_percentage(),
+        Criterion::TieBreak(TieBreak::MatchWinsVsTied) => completed
+            .iter()
+            .filter(|m| {
+                m.winner_id == Some(row.participant_id)
+                    && group
+                        .iter()
+                        .any(|&o| o != i && m.loser_id == Some(rows[o].participant_id))
+            })
+            .count() as f64,
+    }
+}
+
+/// Orders the group by the first criterion and splits it into the groups of equal values,
+/// which are ordered by the rest of the criteria. Returns the groups of fully tied participants.
+fn rank_group(
+    rows: &[Standing],
+    completed: &[&Match],
+    mut group: Vec<usize>,
+    criteria: &[Criterion],
+) -> Vec<Vec<usize>> {
+    let (c, rest) = match criteria.split_first() {
+        Some(split) if group.len() > 1 => split,
+        _ => {
+            group.sort_by_key(|&i| rows[i].seed);
+            return vec![group];
+        }
+    };
+    let values: HashMap<usize, f64> = group
+        .iter()
+        .map(|&i| (i, value(rows, completed, &group, i, *c)))
+        .collect();
+    group.sort_by(|a, b| values[b].partial_cmp(&values[a]).unwrap_or(Ordering::Equal));
+
+    let mut groups = Vec::new();
+    let mut tied: Vec<usize> = Vec::new();
+    for i in group {
+        if let Some(&first) = tied.first() {
+            if (values[&first] - values[&i]).abs() > 1e-9 {
+                groups.extend(rank_group(rows, completed, tied, rest));
+                tied = Vec::new();
+            }
+        }
+        tied.push(i);
+    }
+    if !tied.is_empty() {
+        groups.extend(rank_group(rows, completed, tied, rest));
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::Index as MatchIndex;
+    use crate::participants::tests::make_participant;
+    use crate::participants::GroupId;
+    use crate::participants::{Index as ParticipantIndex, ParticipantId};
+    use crate::standings::Standings;
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::{RankedBy, TieBreak, TournamentType};
+
+    fn participants() -> ParticipantIndex {
+        ParticipantIndex(vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+            make_participant(4, "Dave", 4),
+        ])
+    }
+
+    fn round_robin() -> MatchIndex {
+        let m = |id, round, p1, p2, winner, scores| {
+            make_match(
+                id,
+                "",
+                round,
+                (Some(p1), None),
+                (Some(p2), None),
+                "complete",
+                winner,
+                scores,
+            )
+        };
+        MatchIndex(vec![
+            m(1, 1, 1, 2, Some(1), "3-1"),
+            m(2, 1, 3, 4, None, "2-2"),
+            m(3, 2, 1, 3, Some(3), "2-3"),
+            m(4, 2, 2, 4, Some(2), "3-2"),
+            m(5, 3, 1, 4, Some(1), "3-0"),
+            m(6, 3, 2, 3, Some(2), "3-0"),
+        ])
+    }
+
+    fn summary(s: &Standings) -> Vec<(u64, u64, u64, u64, u64)> {
+        s.0.iter()
+            .map(|r| (r.rank, r.participant_id.0, r.wins, r.losses, r.ties))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_standings() {
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::RoundRobin;
+        let s = Standings::new(&t, &participants(), &round_robin());
+
+        assert_eq!(
+            summary(&s),
+            vec![
+                (1, 1, 2, 1, 0),
+                (2, 2, 2, 1, 0),
+                (3, 3, 1, 1, 1),
+                (4, 4, 0, 2, 1)
+            ]
+        );
+        let alice = s.get(ParticipantId(1)).unwrap();
+        assert_eq!(alice.game_wins, 2);
+        assert_eq!(alice.points_scored, 8);
+        assert_eq!(alice.points_difference, 4);
+        assert!((alice.points - 2f64).abs() < f64::EPSILON);
+        let carol = s.get(ParticipantId(3)).unwrap();
+        assert_eq!(carol.game_ties, 1);
+        assert_eq!(carol.points_difference, -2);
+        assert!((carol.points - 1.5f64).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_tie_breaks() {
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::RoundRobin;
+        t.tie_breaks = Vec::new();
+        let s = Standings::new(&t, &participants(), &round_robin());
+        assert_eq!(
+            s.0.iter().map(|r| r.rank).collect::<Vec<_>>(),
+            vec![1, 1, 3, 4]
+        );
+
+        t.tie_breaks = vec![TieBreak::PointsDifference];
+        t.ranked_by = RankedBy::PointsScored;
+        let s = Standings::new(&t, &participants(), &round_robin());
+        assert_eq!(
+            s.0.iter().map(|r| r.participant_id.0).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_swiss_byes() {
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::Swiss;
+        let mut participants = participants();
+        participants.0.push(make_participant(5, "Eve", 5));
+        let s = Standings::new(&t, &participants, &round_robin());
+
+        // Eve had a bye in each of the three rounds and 1 point for each of them.
+        let eve = s.get(ParticipantId(5)).unwrap();
+        assert_eq!(eve.byes, 3);
+        assert!((eve.points - 3f64).abs() < f64::EPSILON);
+        assert_eq!(eve.rank, 1);
+    }
+
+    #[test]
+    fn test_round_robin_fixture() {
+        // The fixture is synthetic: hand-written participant and match indexes in the shape
+        // the API returns, not a captured Challonge tournament, so it doesn't prove that
+        // Challonge ranks the same way. The expected standings were worked out by hand with
+        // Challonge's default tie breaks: Alice and Bob both win 3 matches and Alice beat
+        // Bob, Carol and Dave both win 2 and Carol beat Dave.
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/round_robin.json")).unwrap();
+        let participants = ParticipantIndex::decode(fixture["participants"].clone()).unwrap();
+        let matches = MatchIndex::decode(fixture["matches"].clone()).unwrap();
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::RoundRobin;
+
+        let s = Standings::new(&t, &participants, &matches);
+        let table: Vec<(&str, u64, u64, u64)> =
+            s.0.iter()
+                .map(|r| (r.name.as_str(), r.rank, r.wins, r.losses))
+                .collect();
+        assert_eq!(
+            table,
+            vec![
+                ("Alice", 1, 3, 1),
+                ("Bob", 2, 3, 1),
+                ("Carol", 3, 2, 2),
+                ("Dave", 4, 2, 2),
+                ("Eve", 5, 0, 4),
+            ]
+        );
+        // The `final_rank`s of the fixture agree.
+        for p in &participants.0 {
+            assert_eq!(Some(s.get(p.id).unwrap().rank), p.final_rank, "{}", p.name);
+        }
+    }
+
+    #[test]
+    fn test_group_stage_standings() {
+        let mut participants = ParticipantIndex(Vec::new());
+        for id in 1..=6 {
+            let mut p = make_participant(id, &format!("Player {}", id), id);
+            p.group_player_ids = vec![ParticipantId(100 + id)];
+            participants.0.push(p);
+        }
+        let m = |id, group: Option<u64>, p1, p2, winner| {
+            let mut m = make_match(
+                id,
+                "",
+                1,
+                (Some(p1), None),
+                (Some(p2), None),
+                "complete",
+                Some(winner),
+                if winner == p1 { "2-0" } else { "0-2" },
+            );
+            m.group_id = group.map(GroupId);
+            m
+        };
+        let matches = MatchIndex(vec![
+            m(1, Some(1), 101, 102, 102),
+            m(2, Some(1), 101, 103, 101),
+            m(3, Some(1), 102, 103, 102),
+            m(4, Some(2), 104, 105, 104),
+            m(5, Some(2), 104, 106, 104),
+            m(6, Some(2), 105, 106, 106),
+            m(7, None, 2, 4, 4),
+        ]);
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::RoundRobin;
+        t.group_stages_enabled = true;
+
+        let groups = Standings::groups(&t, &participants, &matches);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            summary(&groups[&GroupId(1)]),
+            vec![(1, 2, 2, 0, 0), (2, 1, 1, 1, 0), (3, 3, 0, 2, 0)]
+        );
+        assert_eq!(
+            summary(&groups[&GroupId(2)]),
+            vec![(1, 4, 2, 0, 0), (2, 6, 1, 1, 0), (3, 5, 0, 2, 0)]
+        );
+
+        // The final stage doesn't count the group matches.
+        let s = Standings::new(&t, &participants, &matches);
+        assert_eq!(s.get(ParticipantId(4)).unwrap().wins, 1);
+        assert_eq!(s.get(ParticipantId(2)).unwrap().losses, 1);
+        assert_eq!(s.get(ParticipantId(1)).unwrap().wins, 0);
+    }
+}
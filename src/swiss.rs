@@ -0,0 +1,298 @@
+//! Local Swiss system pairings.
+//!
+//! Pairs the participants for the next round from the results of the previous rounds:
+//! participants are paired within their score groups, rematches are avoided whenever
+//! possible and a bye goes to the lowest ranked participant who hasn't had one yet.
+
+use std::collections::HashSet;
+
+use crate::matches::Index as MatchIndex;
+use crate::participants::{Index as ParticipantIndex, ParticipantId};
+use crate::standings::{tally, Standing};
+use crate::tournament::GamePoints;
+
+/// Returns the recommended number of Swiss rounds for the number of participants:
+/// enough rounds for a single undefeated participant to remain.
+pub fn recommended_rounds(participants: u64) -> u64 {
+    let mut rounds = 0;
+    while participants > 1 << rounds {
+        rounds += 1;
+    }
+    rounds
+}
+
+/// Pairings of the next Swiss round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pairings {
+    /// Number of the round the pairings are for.
+    pub round: i64,
+
+    /// Pairs of participants, the higher ranked participant is the first one.
+    pub matches: Vec<(ParticipantId, ParticipantId)>,
+
+    /// The participant who gets a bye in the round (if the number of participants is odd).
+    pub bye: Option<ParticipantId>,
+
+    /// Points the participant gets for the bye.
+    pub bye_points: f64,
+}
+impl Pairings {
+    /// Computes the pairings of the round following the rounds of the given matches.
+    ///
+    /// Only active participants who are not on the waiting list are paired.
+    /// Participants are scored by the `points`, byes in the completed rounds included.
+    pub fn new(
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+        points: &GamePoints,
+    ) -> Pairings {
+        let active: ParticipantIndex = ParticipantIndex(
+            participants
+                .0
+                .iter()
+                .filter(|p| p.active && !p.on_waiting_list)
+                .cloned()
+                .collect(),
+        );
+        let mut rows = tally(&active, matches, points, true);
+        rows.sort_by(|a, b| {
+            b.points
+                .partial_cmp(&a.points)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.seed.cmp(&b.seed))
+        });
+
+        let bye = if rows.len() % 2 == 1 {
+            let pos = rows
+                .iter()
+                .rposition(|r| r.byes == 0)
+                .unwrap_or(rows.len() - 1);
+            Some(rows.remove(pos).participant_id)
+        } else {
+            None
+        };
+
+        let played: HashSet<(ParticipantId, ParticipantId)> = matches
+            .0
+            .iter()
+            .filter_map(|m| Some((m.player1.id?, m.player2.id?)))
+            .flat_map(|(a, b)| vec![(a, b), (b, a)])
+            .collect();
+        let pairs = pair(&rows, &|a, b| played.contains(&(a, b)));
+
+        Pairings {
+            round: matches.0.iter().map(|m| m.round).max().unwrap_or(0) + 1,
+            matches: pairs,
+            bye,
+            bye_points: points.bye.unwrap_or(0f64),
+        }
+    }
+}
+
+/// Limits the number of opponents tried by the search for a pairing without rematches,
+/// which backtracks through exponentially many pairings when there is (almost) none.
+const SEARCH_LIMIT: usize = 10_000;
+
+/// Pairs the ranked participants: the highest ranked unpaired participant gets the
+/// opponent closest in score, preferring the one half of the score group below,
+/// and backtracks if the rest can't be paired without rematches.
+/// If there is no such pairing, or none is found within `SEARCH_LIMIT`, the participants
+/// are paired greedily, preferring new opponents.
+fn pair(
+    rows: &[Standing],
+    played: &dyn Fn(ParticipantId, ParticipantId) -> bool,
+) -> Vec<(ParticipantId, ParticipantId)> {
+    let mut search = Search {
+        rows,
+        played,
+        strict: true,
+        paired: vec![false; rows.len()],
+        pairs: Vec::new(),
+        budget: SEARCH_LIMIT,
+    };
+    if !search.run() {
+        search.strict = false;
+        search.paired = vec![false; rows.len()];
+        search.pairs.clear();
+        // Every opponent is allowed now, so the first one tried always completes the pairing.
+        search.run();
+    }
+    search.pairs
+}
+
+struct Search<'a> {
+    rows: &'a [Standing],
+    played: &'a dyn Fn(ParticipantId, ParticipantId) -> bool,
+    /// Whether rematches are excluded or only tried last.
+    strict: bool,
+    paired: Vec<bool>,
+    pairs: Vec<(ParticipantId, ParticipantId)>,
+    budget: usize,
+}
+impl<'a> Search<'a> {
+    fn run(&mut self) -> bool {
+        let first = match self.paired.iter().position(|&p| !p) {
+            Some(first) => first,
+            None => return true,
+        };
+        self.paired[first] = true;
+        for i in self.candidates(first) {
+            if self.strict {
+                if self.budget == 0 {
+                    break;
+                }
+                self.budget -= 1;
+            }
+            self.paired[i] = true;
+            self.pairs
+                .push((self.rows[first].participant_id, self.rows[i].participant_id));
+            if self.run() {
+                return true;
+            }
+            self.pairs.pop();
+            self.paired[i] = false;
+        }
+        self.paired[first] = false;
+        false
+    }
+
+    /// The possible opponents of the participant, best first.
+    fn candidates(&self, first: usize) -> Vec<usize> {
+        let rows = self.rows;
+        let rest: Vec<usize> = (first + 1..rows.len())
+            .filter(|&i| !self.paired[i])
+            .collect();
+        let group = rest
+            .iter()
+            .take_while(|&&i| (rows[i].points - rows[first].points).abs() < 1e-9)
+            .count()
+            + 1;
+        let ideal = (group / 2).max(1) - 1;
+        let rematch = |i: usize| (self.played)(rows[first].participant_id, rows[i].participant_id);
+
+        let mut candidates: Vec<(usize, usize)> = rest
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, i)| !self.strict || !rematch(i))
+            .collect();
+        candidates.sort_by(|&(a_pos, a), &(b_pos, b)| {
+            let score = |i: usize| (rows[i].points - rows[first].points).abs();
+            rematch(a)
+                .cmp(&rematch(b))
+                .then(
+                    score(a)
+                        .partial_cmp(&score(b))
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+                .then(
+                    (a_pos as i64 - ideal as i64)
+                        .abs()
+                        .cmp(&(b_pos as i64 - ideal as i64).abs()),
+                )
+                .then(a_pos.cmp(&b_pos))
+        });
+        candidates.into_iter().map(|(_, i)| i).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::{Index as MatchIndex, Match};
+    use crate::participants::tests::make_participant;
+    use crate::participants::{Index as ParticipantIndex, ParticipantId};
+    use crate::swiss::{recommended_rounds, Pairings};
+    use crate::tournament::GamePoints;
+
+    fn participants(count: u64) -> ParticipantIndex {
+        ParticipantIndex(
+            (1..=count)
+                .map(|i| make_participant(i, &format!("Participant #{}", i), i))
+                .collect(),
+        )
+    }
+
+    fn result(id: u64, round: i64, winner: u64, loser: u64) -> Match {
+        make_match(
+            id,
+            "",
+            round,
+            (Some(winner), None),
+            (Some(loser), None),
+            "complete",
+            Some(winner),
+            "1-0",
+        )
+    }
+
+    fn ids(pairings: &Pairings) -> Vec<(u64, u64)> {
+        pairings.matches.iter().map(|(a, b)| (a.0, b.0)).collect()
+    }
+
+    #[test]
+    fn test_recommended_rounds() {
+        let rounds: Vec<u64> = [0, 1, 2, 3, 4, 5, 8, 9, 16, 17, 64]
+            .iter()
+            .map(|&n| recommended_rounds(n))
+            .collect();
+        assert_eq!(rounds, vec![0, 0, 1, 2, 2, 3, 3, 4, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_first_round() {
+        let points = GamePoints::new(1.0, 0.5, 0.0, 0.0, Some(1.0));
+        let p = Pairings::new(&participants(8), &MatchIndex(Vec::new()), &points);
+        assert_eq!(p.round, 1);
+        assert_eq!(ids(&p), vec![(1, 5), (2, 6), (3, 7), (4, 8)]);
+        assert_eq!(p.bye, None);
+    }
+
+    #[test]
+    fn test_score_groups_and_rematches() {
+        let points = GamePoints::new(1.0, 0.5, 0.0, 0.0, Some(1.0));
+        let matches = MatchIndex(vec![result(1, 1, 1, 3), result(2, 1, 2, 4)]);
+        let p = Pairings::new(&participants(4), &matches, &points);
+        assert_eq!(p.round, 2);
+        // Winners meet winners and losers meet losers.
+        assert_eq!(ids(&p), vec![(1, 2), (3, 4)]);
+
+        let matches = MatchIndex(vec![
+            result(1, 1, 1, 3),
+            result(2, 1, 2, 4),
+            result(3, 2, 1, 2),
+            result(4, 2, 3, 4),
+        ]);
+        let p = Pairings::new(&participants(4), &matches, &points);
+        // 1 (2 pts) can't meet 2 or 3 again, so 4 (0 pts) is the only option left.
+        assert_eq!(ids(&p), vec![(1, 4), (2, 3)]);
+    }
+
+    #[test]
+    fn test_byes() {
+        let points = GamePoints::new(1.0, 0.5, 0.0, 0.0, Some(1.0));
+        let p = Pairings::new(&participants(5), &MatchIndex(Vec::new()), &points);
+        assert_eq!(p.bye, Some(ParticipantId(5)));
+        assert!((p.bye_points - 1.0).abs() < f64::EPSILON);
+
+        // 5 had a bye in round 1, so the next lowest ranked participant gets it.
+        let matches = MatchIndex(vec![result(1, 1, 1, 3), result(2, 1, 2, 4)]);
+        let p = Pairings::new(&participants(5), &matches, &points);
+        assert_eq!(p.bye, Some(ParticipantId(4)));
+        assert_eq!(ids(&p), vec![(1, 2), (5, 3)]);
+    }
+
+    #[test]
+    fn test_rematches_unavoidable() {
+        // 20 has played everyone else, so one rematch can't be avoided, which the
+        // search for a pairing without rematches only finds out at its deepest level.
+        let points = GamePoints::new(1.0, 0.5, 0.0, 0.0, Some(1.0));
+        let matches = MatchIndex((1..20).map(|i| result(i, i as i64, i, 20)).collect());
+        let p = Pairings::new(&participants(20), &matches, &points);
+
+        assert_eq!(p.round, 20);
+        assert_eq!(p.bye, None);
+        let mut paired: Vec<u64> = ids(&p).iter().flat_map(|&(a, b)| vec![a, b]).collect();
+        paired.sort_unstable();
+        assert_eq!(paired, (1..=20).collect::<Vec<_>>());
+    }
+}
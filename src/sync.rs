@@ -0,0 +1,765 @@
+//! Local SQLite mirror of tournaments.
+//!
+//! `Mirror` keeps `Tournament`, `Participant`, `Match` and `Attachment` records in a SQLite
+//! database, one table for each, keyed by their Challonge ids. Records are upserted, and
+//! only rewritten when their `updated_at` differs from the stored one. `Mirror::sync`
+//! skips a tournament entirely when its `updated_at` hasn't moved since the last sync,
+//! and deletes the participants and matches which are gone from Challonge.
+//!
+//! Players are matched across tournaments by their Challonge username or, for
+//! participants without one, by name (both case-insensitively).
+//!
+//! Timestamps are stored as RFC 3339 strings. The connection is available for ad-hoc
+//! queries through `Mirror::connection`.
+
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::attachments::{Attachment, AttachmentId};
+use crate::error::Error;
+use crate::matches::{Match, MatchId, MatchScore, MatchScores};
+use crate::participants::{Participant, ParticipantId};
+use crate::tournament::{Tournament, TournamentId, TournamentIncludes};
+use crate::Challonge;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tournaments (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL,
+    name TEXT NOT NULL,
+    tournament_type TEXT NOT NULL,
+    state TEXT NOT NULL,
+    game_name TEXT NOT NULL,
+    participants_count INTEGER NOT NULL,
+    started_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS participants (
+    id INTEGER PRIMARY KEY,
+    tournament_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    challonge_username TEXT NOT NULL,
+    email_hash TEXT NOT NULL,
+    misc TEXT NOT NULL,
+    seed INTEGER NOT NULL,
+    final_rank INTEGER,
+    active INTEGER NOT NULL,
+    on_waiting_list INTEGER NOT NULL,
+    checked_in INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS participants_tournament ON participants (tournament_id);
+CREATE TABLE IF NOT EXISTS matches (
+    id INTEGER PRIMARY KEY,
+    tournament_id INTEGER NOT NULL,
+    identifier TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    player1_id INTEGER,
+    player2_id INTEGER,
+    winner_id INTEGER,
+    loser_id INTEGER,
+    scores_csv TEXT NOT NULL,
+    location TEXT,
+    suggested_play_order INTEGER,
+    started_at TEXT,
+    completed_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS matches_tournament ON matches (tournament_id);
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY,
+    match_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    url TEXT,
+    description TEXT,
+    original_file_name TEXT,
+    asset_url TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS attachments_match ON attachments (match_id);
+";
+
+/// A condition on the participant `p` being the player `?N`.
+fn is_player(p: &str, n: u8) -> String {
+    format!(
+        "(CASE WHEN {p}.challonge_username = '' THEN lower({p}.name) \
+         ELSE lower({p}.challonge_username) END = lower(?{n}))",
+        p = p,
+        n = n
+    )
+}
+
+/// Numbers of records written by a sync.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Tournaments written
+    pub tournaments: usize,
+    /// Participants written
+    pub participants: usize,
+    /// Matches written
+    pub matches: usize,
+    /// Attachments written
+    pub attachments: usize,
+    /// Participants, matches and attachments deleted
+    pub deleted: usize,
+}
+
+/// A match between two players, from the point of view of the first one.
+#[derive(Debug, Clone)]
+pub struct HeadToHeadMatch {
+    /// The tournament of the match
+    pub tournament_id: u64,
+    /// Name of the tournament
+    pub tournament: String,
+    /// The match
+    pub match_id: MatchId,
+    /// Round of the match
+    pub round: i64,
+    /// Scores, the first player's first
+    pub scores: MatchScores,
+    /// Whether the first player won, none for a tie
+    pub won: Option<bool>,
+}
+
+/// Completed matches between two players.
+#[derive(Debug, Clone, Default)]
+pub struct HeadToHead {
+    /// Matches won by the first player
+    pub wins: u64,
+    /// Matches lost by the first player
+    pub losses: u64,
+    /// Tied matches
+    pub ties: u64,
+    /// The matches, by tournament and match id
+    pub matches: Vec<HeadToHeadMatch>,
+}
+
+/// A player's participation in a tournament.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// The tournament
+    pub tournament_id: u64,
+    /// Name of the tournament
+    pub tournament: String,
+    /// When the tournament started
+    pub started_at: Option<DateTime<FixedOffset>>,
+    /// The player's participant in the tournament
+    pub participant_id: ParticipantId,
+    /// The player's seed
+    pub seed: u64,
+    /// The player's final rank
+    pub final_rank: Option<u64>,
+    /// Completed matches won
+    pub wins: u64,
+    /// Completed matches lost
+    pub losses: u64,
+}
+
+/// A SQLite database mirroring tournaments.
+pub struct Mirror {
+    conn: Connection,
+}
+impl Mirror {
+    /// Opens (or creates) the database at the path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Mirror, Error> {
+        Mirror::with_connection(Connection::open(path)?)
+    }
+
+    /// Creates a database in memory.
+    pub fn open_in_memory() -> Result<Mirror, Error> {
+        Mirror::with_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses the connection, creating the tables if they don't exist yet.
+    pub fn with_connection(conn: Connection) -> Result<Mirror, Error> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Mirror { conn })
+    }
+
+    /// The database connection.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Fetches the tournament with its participants and matches in one request, and the
+    /// attachments of the changed matches, and stores what changed since the last sync, all in
+    /// one transaction. Records gone from Challonge are deleted.
+    pub fn sync(&mut self, c: &Challonge, id: &TournamentId) -> Result<SyncReport, Error> {
+        let tournament = c.get_tournament(id, &TournamentIncludes::All)?;
+        let tid = numeric_id(&tournament.id)?;
+        if self.stored_updated_at("tournaments", tid)? == Some(tournament.updated_at) {
+            return Ok(SyncReport::default());
+        }
+        let (participants, matches) = match (&tournament.participants, &tournament.matches) {
+            (Some(p), Some(m)) => (p, m),
+            _ => {
+                return Err(Error::Api(
+                    "The participants and matches weren't included with the tournament",
+                ))
+            }
+        };
+        // The attachments of the changed matches, refetched in full.
+        let mut attachments = Vec::new();
+        for m in &matches.0 {
+            if self.stored_updated_at("matches", m.id.0)? != Some(m.updated_at) {
+                let fetched = if m.attachment_count > 0 {
+                    c.attachments_index(id, &m.id)?.0
+                } else {
+                    Vec::new()
+                };
+                attachments.push((m.id, fetched));
+            }
+        }
+
+        let tx = self.conn.transaction()?;
+        let mut report = SyncReport::default();
+        {
+            let mirror = MirrorRef(&tx);
+            report.tournaments += mirror.store_tournament(&tournament)? as usize;
+            for p in &participants.0 {
+                report.participants += mirror.store_participant(tid, p)? as usize;
+            }
+            for m in &matches.0 {
+                report.matches += mirror.store_match(tid, m)? as usize;
+            }
+            for (match_id, fetched) in &attachments {
+                for a in fetched {
+                    report.attachments += mirror.store_attachment(a)? as usize;
+                }
+                let ids: Vec<AttachmentId> = fetched.iter().map(|a| a.id).collect();
+                report.deleted += mirror.prune_attachments(match_id, &ids)?;
+            }
+            let participant_ids: Vec<ParticipantId> = participants.0.iter().map(|p| p.id).collect();
+            let match_ids: Vec<MatchId> = matches.0.iter().map(|m| m.id).collect();
+            report.deleted += mirror.prune(tid, &participant_ids, &match_ids)?;
+        }
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Stores the tournament, returns whether it was written.
+    pub fn store_tournament(&self, tournament: &Tournament) -> Result<bool, Error> {
+        MirrorRef(&self.conn).store_tournament(tournament)
+    }
+
+    /// Stores the participant of the tournament, returns whether it was written.
+    pub fn store_participant(&self, tournament_id: u64, p: &Participant) -> Result<bool, Error> {
+        MirrorRef(&self.conn).store_participant(tournament_id, p)
+    }
+
+    /// Stores the match of the tournament, returns whether it was written.
+    pub fn store_match(&self, tournament_id: u64, m: &Match) -> Result<bool, Error> {
+        MirrorRef(&self.conn).store_match(tournament_id, m)
+    }
+
+    /// Stores the attachment, returns whether it was written.
+    pub fn store_attachment(&self, a: &Attachment) -> Result<bool, Error> {
+        MirrorRef(&self.conn).store_attachment(a)
+    }
+
+    /// Deletes the participants and matches of the tournament which aren't listed, along
+    /// with the attachments of the deleted matches. Returns the number of deleted records.
+    pub fn prune(
+        &self,
+        tournament_id: u64,
+        participants: &[ParticipantId],
+        matches: &[MatchId],
+    ) -> Result<usize, Error> {
+        MirrorRef(&self.conn).prune(tournament_id, participants, matches)
+    }
+
+    /// Deletes the attachments of the match which aren't listed. Returns the number of
+    /// deleted attachments.
+    pub fn prune_attachments(
+        &self,
+        match_id: &MatchId,
+        attachments: &[AttachmentId],
+    ) -> Result<usize, Error> {
+        MirrorRef(&self.conn).prune_attachments(match_id, attachments)
+    }
+
+    fn stored_updated_at(
+        &self,
+        table: &str,
+        id: u64,
+    ) -> Result<Option<DateTime<FixedOffset>>, Error> {
+        let updated_at: Option<String> = self
+            .conn
+            .query_row(
+                &format!("SELECT updated_at FROM {} WHERE id = ?1", table),
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(updated_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok()))
+    }
+
+    /// Completed matches between the two players across all stored tournaments.
+    pub fn head_to_head(&self, player: &str, opponent: &str) -> Result<HeadToHead, Error> {
+        let sql = format!(
+            "SELECT m.id, m.tournament_id, COALESCE(t.name, ''), m.round, m.scores_csv,
+                    m.winner_id, {p1_is_player}, m.player1_id, m.player2_id
+             FROM matches m
+             JOIN participants p1 ON p1.id = m.player1_id
+             JOIN participants p2 ON p2.id = m.player2_id
+             LEFT JOIN tournaments t ON t.id = m.tournament_id
+             WHERE m.state = 'complete'
+               AND (({p1_is_player} AND {p2_is_opponent}) OR ({p1_is_opponent} AND {p2_is_player}))
+             ORDER BY m.tournament_id, m.id",
+            p1_is_player = is_player("p1", 1),
+            p2_is_player = is_player("p2", 1),
+            p1_is_opponent = is_player("p1", 2),
+            p2_is_opponent = is_player("p2", 2),
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![player, opponent], |row| {
+            let first: bool = row.get(6)?;
+            let winner: Option<u64> = row.get(5)?;
+            let own_id: u64 = row.get(if first { 7 } else { 8 })?;
+            let scores = MatchScores::decode(row.get(4)?);
+            Ok(HeadToHeadMatch {
+                match_id: MatchId(row.get(0)?),
+                tournament_id: row.get(1)?,
+                tournament: row.get(2)?,
+                round: row.get(3)?,
+                scores: if first {
+                    scores
+                } else {
+                    MatchScores(scores.0.iter().map(|s| MatchScore(s.1, s.0)).collect())
+                },
+                won: winner.map(|w| w == own_id),
+            })
+        })?;
+
+        let mut h2h = HeadToHead::default();
+        for m in rows {
+            let m = m?;
+            match m.won {
+                Some(true) => h2h.wins += 1,
+                Some(false) => h2h.losses += 1,
+                None => h2h.ties += 1,
+            }
+            h2h.matches.push(m);
+        }
+        Ok(h2h)
+    }
+
+    /// The player's tournaments, oldest first.
+    pub fn player_history(&self, player: &str) -> Result<Vec<HistoryEntry>, Error> {
+        let sql = format!(
+            "SELECT t.id, t.name, t.started_at, p.id, p.seed, p.final_rank,
+                    (SELECT COUNT(*) FROM matches m
+                     WHERE m.tournament_id = t.id AND m.state = 'complete' AND m.winner_id = p.id),
+                    (SELECT COUNT(*) FROM matches m
+                     WHERE m.tournament_id = t.id AND m.state = 'complete' AND m.loser_id = p.id),
+                    t.created_at
+             FROM participants p
+             JOIN tournaments t ON t.id = p.tournament_id
+             WHERE {}",
+            is_player("p", 1)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![player], |row| {
+            let started_at: Option<String> = row.get(2)?;
+            let created_at: String = row.get(8)?;
+            Ok((
+                DateTime::parse_from_rfc3339(&created_at).ok(),
+                HistoryEntry {
+                    tournament_id: row.get(0)?,
+                    tournament: row.get(1)?,
+                    started_at: started_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok()),
+                    participant_id: ParticipantId(row.get(3)?),
+                    seed: row.get(4)?,
+                    final_rank: row.get(5)?,
+                    wins: row.get(6)?,
+                    losses: row.get(7)?,
+                },
+            ))
+        })?;
+        let mut history = rows.collect::<Result<Vec<_>, _>>()?;
+        history.sort_by_key(|(created_at, e)| (e.started_at.or(*created_at), e.tournament_id));
+        Ok(history.into_iter().map(|(_, e)| e).collect())
+    }
+}
+
+/// Writes records through a connection or a transaction.
+struct MirrorRef<'a>(&'a Connection);
+impl MirrorRef<'_> {
+    fn store_tournament(&self, t: &Tournament) -> Result<bool, Error> {
+        let written = self.0.execute(
+            "INSERT INTO tournaments (id, url, name, tournament_type, state, game_name,
+                 participants_count, started_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (id) DO UPDATE SET url = excluded.url, name = excluded.name,
+                 tournament_type = excluded.tournament_type, state = excluded.state,
+                 game_name = excluded.game_name,
+                 participants_count = excluded.participants_count,
+                 started_at = excluded.started_at, created_at = excluded.created_at,
+                 updated_at = excluded.updated_at
+             WHERE tournaments.updated_at IS NOT excluded.updated_at",
+            params![
+                numeric_id(&t.id)?,
+                t.url,
+                t.name,
+                t.tournament_type.to_get_param(),
+                t.state.to_string(),
+                t.game_name,
+                t.participants_count,
+                t.started_at.map(|d| d.to_rfc3339()),
+                t.created_at.to_rfc3339(),
+                t.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(written > 0)
+    }
+
+    fn store_participant(&self, tournament_id: u64, p: &Participant) -> Result<bool, Error> {
+        let written = self.0.execute(
+            "INSERT INTO participants (id, tournament_id, name, challonge_username, email_hash,
+                 misc, seed, final_rank, active, on_waiting_list, checked_in, created_at,
+                 updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             ON CONFLICT (id) DO UPDATE SET tournament_id = excluded.tournament_id,
+                 name = excluded.name, challonge_username = excluded.challonge_username,
+                 email_hash = excluded.email_hash, misc = excluded.misc, seed = excluded.seed,
+                 final_rank = excluded.final_rank, active = excluded.active,
+                 on_waiting_list = excluded.on_waiting_list, checked_in = excluded.checked_in,
+                 created_at = excluded.created_at, updated_at = excluded.updated_at
+             WHERE participants.updated_at IS NOT excluded.updated_at",
+            params![
+                p.id.0,
+                tournament_id,
+                p.name,
+                p.challonge_username,
+                p.email_hash,
+                p.misc,
+                p.seed,
+                p.final_rank,
+                p.active,
+                p.on_waiting_list,
+                p.checked_in,
+                p.created_at.to_rfc3339(),
+                p.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(written > 0)
+    }
+
+    fn store_match(&self, tournament_id: u64, m: &Match) -> Result<bool, Error> {
+        let written = self.0.execute(
+            "INSERT INTO matches (id, tournament_id, identifier, round, state, player1_id,
+                 player2_id, winner_id, loser_id, scores_csv, location, suggested_play_order,
+                 started_at, completed_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+             ON CONFLICT (id) DO UPDATE SET tournament_id = excluded.tournament_id,
+                 identifier = excluded.identifier, round = excluded.round,
+                 state = excluded.state, player1_id = excluded.player1_id,
+                 player2_id = excluded.player2_id, winner_id = excluded.winner_id,
+                 loser_id = excluded.loser_id, scores_csv = excluded.scores_csv,
+                 location = excluded.location,
+                 suggested_play_order = excluded.suggested_play_order,
+                 started_at = excluded.started_at, completed_at = excluded.completed_at,
+                 created_at = excluded.created_at, updated_at = excluded.updated_at
+             WHERE matches.updated_at IS NOT excluded.updated_at",
+            params![
+                m.id.0,
+                tournament_id,
+                m.identifier,
+                m.round,
+                m.state.to_string(),
+                m.player1.id.map(|id| id.0),
+                m.player2.id.map(|id| id.0),
+                m.winner_id.map(|id| id.0),
+                m.loser_id.map(|id| id.0),
+                m.scores_csv.to_string(),
+                m.location,
+                m.suggested_play_order,
+                m.started_at.map(|d| d.to_rfc3339()),
+                m.completed_at.map(|d| d.to_rfc3339()),
+                m.created_at.to_rfc3339(),
+                m.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(written > 0)
+    }
+
+    fn store_attachment(&self, a: &Attachment) -> Result<bool, Error> {
+        let written = self.0.execute(
+            "INSERT INTO attachments (id, match_id, user_id, url, description,
+                 original_file_name, asset_url, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT (id) DO UPDATE SET match_id = excluded.match_id,
+                 user_id = excluded.user_id, url = excluded.url,
+                 description = excluded.description,
+                 original_file_name = excluded.original_file_name,
+                 asset_url = excluded.asset_url, created_at = excluded.created_at,
+                 updated_at = excluded.updated_at
+             WHERE attachments.updated_at IS NOT excluded.updated_at",
+            params![
+                a.id.0,
+                a.match_id.0,
+                a.user_id,
+                a.url,
+                a.description,
+                a.original_file_name,
+                a.asset.url,
+                a.created_at.to_rfc3339(),
+                a.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(written > 0)
+    }
+
+    fn prune(
+        &self,
+        tournament_id: u64,
+        participants: &[ParticipantId],
+        matches: &[MatchId],
+    ) -> Result<usize, Error> {
+        let matches = id_list(matches.iter().map(|id| id.0));
+        let participants = id_list(participants.iter().map(|id| id.0));
+        let mut deleted = self.0.execute(
+            &format!(
+                "DELETE FROM attachments WHERE match_id IN
+                     (SELECT id FROM matches WHERE tournament_id = ?1 AND id NOT IN ({}))",
+                matches
+            ),
+            params![tournament_id],
+        )?;
+        deleted += self.0.execute(
+            &format!(
+                "DELETE FROM matches WHERE tournament_id = ?1 AND id NOT IN ({})",
+                matches
+            ),
+            params![tournament_id],
+        )?;
+        deleted += self.0.execute(
+            &format!(
+                "DELETE FROM participants WHERE tournament_id = ?1 AND id NOT IN ({})",
+                participants
+            ),
+            params![tournament_id],
+        )?;
+        Ok(deleted)
+    }
+
+    fn prune_attachments(
+        &self,
+        match_id: &MatchId,
+        attachments: &[AttachmentId],
+    ) -> Result<usize, Error> {
+        Ok(self.0.execute(
+            &format!(
+                "DELETE FROM attachments WHERE match_id = ?1 AND id NOT IN ({})",
+                id_list(attachments.iter().map(|id| id.0))
+            ),
+            params![match_id.0],
+        )?)
+    }
+}
+
+/// A comma separated list of ids for an `IN (...)` clause.
+fn id_list<I: IntoIterator<Item = u64>>(ids: I) -> String {
+    ids.into_iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn numeric_id(id: &TournamentId) -> Result<u64, Error> {
+    match *id {
+        TournamentId::Id(id) => Ok(id),
+        TournamentId::Url(..) => Err(Error::Api("Only numeric tournament ids can be stored")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::MatchScore;
+    use crate::participants::tests::make_participant;
+    use crate::sync::Mirror;
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::TournamentId;
+    use chrono::Duration;
+
+    fn mirror() -> Mirror {
+        let mirror = Mirror::open_in_memory().unwrap();
+        for (tid, offset) in &[(1u64, 0i64), (2, 10), (3, 20)] {
+            let mut t = sample_tournament();
+            t.id = TournamentId::Id(*tid);
+            t.name = format!("Weekly #{}", tid);
+            t.started_at = t.started_at.map(|s| s - Duration::days(*offset));
+            assert!(mirror.store_tournament(&t).unwrap());
+        }
+        // Participant ids are per tournament: 10 * tournament + player.
+        for tid in 1..=3 {
+            let mut alice = make_participant(tid * 10 + 1, "Alice", 1);
+            alice.challonge_username = "alice_c".to_owned();
+            alice.name = format!("Alice (team {})", tid);
+            let bob = make_participant(tid * 10 + 2, "bob", 2);
+            mirror.store_participant(tid, &alice).unwrap();
+            mirror.store_participant(tid, &bob).unwrap();
+        }
+        // (id, player1, player2, winner, scores, state)
+        type Row<'a> = (u64, u64, u64, Option<u64>, &'a str, &'a str);
+        let results: &[Row] = &[
+            (1, 11, 12, Some(11), "2-1", "complete"),
+            (2, 22, 21, Some(22), "2-0", "complete"),
+            (3, 31, 32, Some(31), "3-2", "complete"),
+            (4, 31, 32, None, "1-1", "complete"),
+            (5, 32, 31, None, "", "open"),
+        ];
+        for &(id, p1, p2, winner, scores, state) in results {
+            let tid = p1 / 10;
+            let m = make_match(
+                id,
+                "A",
+                1,
+                (Some(p1), None),
+                (Some(p2), None),
+                state,
+                winner,
+                scores,
+            );
+            assert!(mirror.store_match(tid, &m).unwrap());
+        }
+        mirror
+    }
+
+    #[test]
+    fn test_upsert_by_updated_at() {
+        let mirror = mirror();
+        let mut t = sample_tournament();
+        t.id = TournamentId::Id(1);
+        t.name = "Weekly #1".to_owned();
+        assert!(!mirror.store_tournament(&t).unwrap());
+        t.name = "Renamed".to_owned();
+        t.updated_at += Duration::minutes(1);
+        assert!(mirror.store_tournament(&t).unwrap());
+        let name: String = mirror
+            .connection()
+            .query_row("SELECT name FROM tournaments WHERE id = 1", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(name, "Renamed");
+
+        let mut m = make_match(
+            1,
+            "A",
+            1,
+            (Some(11), None),
+            (Some(12), None),
+            "complete",
+            Some(11),
+            "2-1",
+        );
+        assert!(!mirror.store_match(1, &m).unwrap());
+        m.updated_at += Duration::minutes(1);
+        assert!(mirror.store_match(1, &m).unwrap());
+        let count: u64 = mirror
+            .connection()
+            .query_row("SELECT COUNT(*) FROM matches", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 5);
+
+        let mut t = sample_tournament();
+        t.id = TournamentId::Url(String::new(), "weekly".to_owned());
+        assert!(mirror.store_tournament(&t).is_err());
+    }
+
+    #[test]
+    fn test_prune() {
+        use crate::attachments::{Attachment, AttachmentId};
+        use crate::matches::MatchId;
+        use crate::participants::ParticipantId;
+
+        let mirror = mirror();
+        for &(id, match_id) in &[(1, 3), (2, 1), (3, 3)] {
+            let a = Attachment::decode(serde_json::json!({"match_attachment": {
+                "id": id, "match_id": match_id, "user_id": 1, "description": "vod",
+                "url": null, "original_file_name": null,
+                "created_at": "2016-07-02T13:24:09.899-04:00",
+                "updated_at": "2016-07-02T13:24:09.899-04:00",
+                "asset_file_name": null, "asset_content_type": null,
+                "asset_file_size": null, "asset_url": null
+            }}))
+            .unwrap();
+            mirror.store_attachment(&a).unwrap();
+        }
+        let count = |table: &str| -> u64 {
+            mirror
+                .connection()
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+                .unwrap()
+        };
+
+        // Bob and the matches 4 and 5 are gone from tournament 3.
+        let deleted = mirror
+            .prune(3, &[ParticipantId(31)], &[MatchId(3)])
+            .unwrap();
+        assert_eq!(deleted, 3);
+        assert_eq!(count("participants"), 5);
+        assert_eq!(count("matches"), 3);
+        assert_eq!(count("attachments"), 3);
+
+        // The attachment 3 was removed from the refetched match 3.
+        let deleted = mirror
+            .prune_attachments(&MatchId(3), &[AttachmentId(1)])
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(count("attachments"), 2);
+
+        // The other tournaments are untouched, an empty list clears the tournament.
+        assert_eq!(mirror.prune(3, &[], &[]).unwrap(), 3);
+        assert_eq!(count("participants"), 4);
+        assert_eq!(count("matches"), 2);
+        assert_eq!(count("attachments"), 1);
+    }
+
+    #[test]
+    fn test_head_to_head() {
+        let mirror = mirror();
+        let h2h = mirror.head_to_head("ALICE_C", "Bob").unwrap();
+        assert_eq!((h2h.wins, h2h.losses, h2h.ties), (2, 1, 1));
+        let ids: Vec<u64> = h2h.matches.iter().map(|m| m.match_id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(h2h.matches[1].tournament, "Weekly #2");
+        assert_eq!(h2h.matches[1].won, Some(false));
+        assert_eq!(h2h.matches[1].scores.0, vec![MatchScore(0, 2)]);
+
+        let h2h = mirror.head_to_head("bob", "alice_c").unwrap();
+        assert_eq!((h2h.wins, h2h.losses, h2h.ties), (1, 2, 1));
+        assert_eq!(h2h.matches[0].scores.0, vec![MatchScore(1, 2)]);
+
+        // Alice has a username, so her name doesn't identify her.
+        assert!(mirror
+            .head_to_head("Alice (team 1)", "bob")
+            .unwrap()
+            .matches
+            .is_empty());
+    }
+
+    #[test]
+    fn test_player_history() {
+        let mirror = mirror();
+        let history = mirror.player_history("alice_c").unwrap();
+        let summary: Vec<(u64, u64, u64)> = history
+            .iter()
+            .map(|e| (e.tournament_id, e.wins, e.losses))
+            .collect();
+        // Tournament 3 started first.
+        assert_eq!(summary, vec![(3, 1, 0), (2, 0, 1), (1, 1, 0)]);
+        assert_eq!(history[0].participant_id.0, 31);
+        assert_eq!(history[0].seed, 1);
+        assert!(mirror.player_history("nobody").unwrap().is_empty());
+    }
+}
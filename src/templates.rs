@@ -0,0 +1,622 @@
+//! Named tournament templates.
+//!
+//! A `Template` keeps the settings of a tournament, and optionally a list of participants with
+//! their seeds, under a name, so a recurring event can be created again with one call. Templates
+//! are saved as JSON, or as TOML with the `toml-templates` feature; `Templates` keeps them in a
+//! directory, one file per template named after it.
+//!
+//! Keys missing from a template file take the `TournamentCreate::new` defaults, so hand-written
+//! templates only need the settings which differ. The optional settings (game, sign-up cap,
+//! start time, check-in duration and grand finals) are left unset when missing.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+
+use crate::error::Error;
+use crate::participants::{Index as ParticipantIndex, ParticipantCreate};
+use crate::tournament::{
+    GamePoints, RankedBy, TieBreak, Tournament, TournamentCreate, TournamentType,
+};
+use crate::util::{decode_array, into_map};
+use crate::Challonge;
+
+/// A format of template files.
+///
+/// The formats depend on the enabled features, so matches on it need a wildcard arm.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Format {
+    /// JSON, `.json` files
+    Json,
+
+    /// TOML, `.toml` files
+    #[cfg(feature = "toml-templates")]
+    Toml,
+}
+impl Format {
+    /// All the supported formats.
+    pub const ALL: &'static [Format] = &[
+        Format::Json,
+        #[cfg(feature = "toml-templates")]
+        Format::Toml,
+    ];
+
+    /// The file extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            #[cfg(feature = "toml-templates")]
+            Format::Toml => "toml",
+        }
+    }
+
+    /// The format of a file, by its extension.
+    pub fn of<P: AsRef<Path>>(path: P) -> Result<Format, Error> {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str());
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|f| extension == Some(f.extension()))
+            .ok_or(Error::Api("Unsupported template format"))
+    }
+}
+
+/// The settings and participants of a tournament, saved under a name.
+#[derive(Debug, Clone)]
+pub struct Template {
+    /// Name of the template
+    pub name: String,
+
+    /// Settings of the tournament; `url` and `name` are replaced when it is created
+    pub tournament: TournamentCreate,
+
+    /// Participants to add to the tournament, may be empty
+    pub participants: Vec<ParticipantCreate>,
+}
+impl Template {
+    /// Creates a template with the settings and no participants.
+    pub fn new<S: Into<String>>(name: S, tournament: TournamentCreate) -> Template {
+        Template {
+            name: name.into(),
+            tournament,
+            participants: Vec::new(),
+        }
+    }
+
+    /// Creates a template from an existing tournament and its participants, by seed. The start
+    /// time of the tournament isn't kept (see `TournamentCreate::copy_of`).
+    pub fn of<S: Into<String>>(
+        name: S,
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+    ) -> Template {
+        let mut sorted: Vec<_> = participants.0.iter().collect();
+        sorted.sort_by_key(|p| p.seed);
+        Template {
+            name: name.into(),
+            tournament: TournamentCreate::copy_of(tournament),
+            participants: sorted.into_iter().map(ParticipantCreate::from).collect(),
+        }
+    }
+
+    /// Creates a tournament from the template, under the url and name, and adds the
+    /// participants of the template to it. If they can't be added, the new tournament is
+    /// deleted again.
+    pub fn create<U: Into<String>, N: Into<String>>(
+        &self,
+        c: &Challonge,
+        url: U,
+        name: N,
+    ) -> Result<Tournament, Error> {
+        let mut tc = self.tournament.clone();
+        tc.url(url).name(name);
+        c.create_tournament_with(&tc, self.participants.clone())
+    }
+
+    /// Encodes the template to JSON. Absent values are left out.
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "tournament": encode_tournament(&self.tournament),
+            "participants": self.participants.iter().map(encode_participant).collect::<Vec<_>>(),
+        });
+        strip_nulls(&mut value);
+        value
+    }
+
+    /// Decodes a template from JSON.
+    pub fn from_json(value: Value) -> Result<Template, Error> {
+        let mut map = into_map(value)?;
+        let name = string(&mut map, "name")?
+            .ok_or_else(|| Error::Decode("Template has no name", Value::Object(map.clone())))?;
+        let tournament = match map.remove("tournament") {
+            Some(t) => decode_tournament(t)?,
+            None => TournamentCreate::new(),
+        };
+        let participants = match map.remove("participants") {
+            Some(ps) => decode_array(ps, decode_participant)?,
+            None => Vec::new(),
+        };
+        Ok(Template {
+            name,
+            tournament,
+            participants,
+        })
+    }
+
+    /// Encodes the template to TOML.
+    #[cfg(feature = "toml-templates")]
+    pub fn to_toml(&self) -> Result<String, Error> {
+        // Going through `toml::Value` puts plain values before tables, as TOML requires.
+        let value = toml::Value::try_from(self.to_json())?;
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    /// Decodes a template from TOML.
+    #[cfg(feature = "toml-templates")]
+    pub fn from_toml(s: &str) -> Result<Template, Error> {
+        Template::from_json(toml::from_str(s)?)
+    }
+
+    /// Encodes the template in the format.
+    pub fn encode(&self, format: Format) -> Result<String, Error> {
+        match format {
+            Format::Json => Ok(serde_json::to_string_pretty(&self.to_json())?),
+            #[cfg(feature = "toml-templates")]
+            Format::Toml => self.to_toml(),
+        }
+    }
+
+    /// Decodes a template in the format.
+    pub fn decode(s: &str, format: Format) -> Result<Template, Error> {
+        match format {
+            Format::Json => Template::from_json(serde_json::from_str(s)?),
+            #[cfg(feature = "toml-templates")]
+            Format::Toml => Template::from_toml(s),
+        }
+    }
+
+    /// Saves the template to a file, in the format of its extension.
+    ///
+    /// The template is written to a temporary file next to it first, so an existing file is
+    /// either replaced or left as it was.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let format = Format::of(path)?;
+        let encoded = self.encode(format)?;
+        let tmp = path.with_extension(format!("{}.tmp", format.extension()));
+        let written = fs::write(&tmp, encoded).and_then(|_| fs::rename(&tmp, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        Ok(written?)
+    }
+
+    /// Loads a template from a file, in the format of its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Template, Error> {
+        let format = Format::of(&path)?;
+        Template::decode(&fs::read_to_string(path)?, format)
+    }
+}
+
+/// A directory of templates, each in a file named after the template.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    dir: PathBuf,
+    format: Format,
+}
+impl Templates {
+    /// Uses the directory, saving templates as JSON.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Templates {
+        Templates {
+            dir: dir.into(),
+            format: Format::Json,
+        }
+    }
+
+    /// Sets the format of saved templates.
+    pub fn format(&mut self, format: Format) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    // A closure rather than a char array pattern, which older compilers don't accept.
+    #[allow(clippy::manual_pattern_char_comparison)]
+    fn path(&self, name: &str, format: Format) -> Result<PathBuf, Error> {
+        if name.is_empty() || name.starts_with('.') || name.contains(|c| c == '/' || c == '\\') {
+            return Err(Error::Api("Invalid template name"));
+        }
+        Ok(self.dir.join(format!("{}.{}", name, format.extension())))
+    }
+
+    /// Saves the template, replacing a template with the same name.
+    /// Returns the path of the file.
+    pub fn save(&self, template: &Template) -> Result<PathBuf, Error> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(&template.name, self.format)?;
+        template.save(&path)?;
+        // The template may have been saved in another format before.
+        for &format in Format::ALL {
+            let other = self.path(&template.name, format)?;
+            if other != path && other.is_file() {
+                fs::remove_file(other)?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Loads the template with the name, in any supported format.
+    pub fn load(&self, name: &str) -> Result<Template, Error> {
+        for &format in Format::ALL {
+            let path = self.path(name, format)?;
+            if path.is_file() {
+                return Template::load(path);
+            }
+        }
+        Err(Error::Api("No such template"))
+    }
+
+    /// Removes the template with the name, if there is one.
+    pub fn remove(&self, name: &str) -> Result<(), Error> {
+        for &format in Format::ALL {
+            let path = self.path(name, format)?;
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the templates in the directory, sorted.
+    pub fn names(&self) -> Result<Vec<String>, Error> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if Format::of(&path).is_err() {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+fn encode_points(points: &GamePoints) -> Value {
+    json!({
+        "match_win": points.match_win,
+        "match_tie": points.match_tie,
+        "game_win": points.game_win,
+        "game_tie": points.game_tie,
+        "bye": points.bye,
+    })
+}
+
+fn encode_tournament(t: &TournamentCreate) -> Value {
+    json!({
+        "name": t.name,
+        "tournament_type": t.tournament_type.to_string(),
+        "url": t.url,
+        "subdomain": t.subdomain,
+        "description": t.description,
+        "open_signup": t.open_signup,
+        "hold_third_place_match": t.hold_third_place_match,
+        "swiss_points": encode_points(&t.swiss_points),
+        "swiss_rounds": t.swiss_rounds,
+        "ranked_by": t.ranked_by.to_string(),
+        "round_robin_points": encode_points(&t.round_robin_points),
+        "tie_breaks": t.tie_breaks.iter().map(TieBreak::to_string).collect::<Vec<_>>(),
+        "accept_attachments": t.accept_attachments,
+        "hide_forum": t.hide_forum,
+        "teams": t.teams,
+        "team_convertable": t.team_convertable,
+        "show_rounds": t.show_rounds,
+        "private": t.private,
+        "game_name": t.game_name,
+        "notify_users_when_matches_open": t.notify_users_when_matches_open,
+        "notify_users_when_the_tournament_ends": t.notify_users_when_the_tournament_ends,
+        "sequential_pairings": t.sequential_pairings,
+        "signup_cap": t.signup_cap,
+        "start_at": t.start_at.map(|s| s.to_rfc3339()),
+        "check_in_duration": t.check_in_duration,
+        "grand_finals_modifier": t.grand_finals_modifier,
+    })
+}
+
+fn encode_participant(p: &ParticipantCreate) -> Value {
+    json!({
+        "name": p.name,
+        "challonge_username": p.challonge_username,
+        "email": p.email,
+        "invite_name_or_email": p.invite_name_or_email,
+        "seed": p.seed,
+        "misc": p.misc,
+    })
+}
+
+/// TOML has no `null`, absent values are left out instead.
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn string(map: &mut Map<String, Value>, key: &str) -> Result<Option<String>, Error> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(v) => Err(Error::Decode("Expected a string", v)),
+    }
+}
+
+fn boolean(map: &mut Map<String, Value>, key: &str) -> Result<Option<bool>, Error> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(b)),
+        Some(v) => Err(Error::Decode("Expected a boolean", v)),
+    }
+}
+
+fn number(map: &mut Map<String, Value>, key: &str) -> Result<Option<u64>, Error> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => match v.as_u64() {
+            Some(n) => Ok(Some(n)),
+            None => Err(Error::Decode("Expected a number", v)),
+        },
+    }
+}
+
+fn float(map: &mut Map<String, Value>, key: &str) -> Result<Option<f64>, Error> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => match v.as_f64() {
+            Some(n) => Ok(Some(n)),
+            None => Err(Error::Decode("Expected a number", v)),
+        },
+    }
+}
+
+fn parsed<T: FromStr>(
+    map: &mut Map<String, Value>,
+    key: &str,
+    what: &'static str,
+) -> Result<Option<T>, Error> {
+    match string(map, key)? {
+        None => Ok(None),
+        Some(s) => match s.parse() {
+            Ok(t) => Ok(Some(t)),
+            Err(_) => Err(Error::Decode(what, Value::String(s))),
+        },
+    }
+}
+
+fn decode_points(value: Value, mut points: GamePoints) -> Result<GamePoints, Error> {
+    let mut map = into_map(value)?;
+    if let Some(v) = float(&mut map, "match_win")? {
+        points.match_win = v;
+    }
+    if let Some(v) = float(&mut map, "match_tie")? {
+        points.match_tie = v;
+    }
+    if let Some(v) = float(&mut map, "game_win")? {
+        points.game_win = v;
+    }
+    if let Some(v) = float(&mut map, "game_tie")? {
+        points.game_tie = v;
+    }
+    points.bye = float(&mut map, "bye")?;
+    Ok(points)
+}
+
+fn decode_tournament(value: Value) -> Result<TournamentCreate, Error> {
+    let mut map = into_map(value)?;
+    let mut t = TournamentCreate::new();
+    if let Some(v) = string(&mut map, "name")? {
+        t.name = v;
+    }
+    if let Some(v) =
+        parsed::<TournamentType>(&mut map, "tournament_type", "Unknown tournament type")?
+    {
+        t.tournament_type = v;
+    }
+    if let Some(v) = string(&mut map, "url")? {
+        t.url = v;
+    }
+    if let Some(v) = string(&mut map, "subdomain")? {
+        t.subdomain = v;
+    }
+    if let Some(v) = string(&mut map, "description")? {
+        t.description = v;
+    }
+    if let Some(v) = boolean(&mut map, "open_signup")? {
+        t.open_signup = v;
+    }
+    if let Some(v) = boolean(&mut map, "hold_third_place_match")? {
+        t.hold_third_place_match = v;
+    }
+    if let Some(v) = map.remove("swiss_points") {
+        t.swiss_points = decode_points(v, t.swiss_points)?;
+    }
+    if let Some(v) = number(&mut map, "swiss_rounds")? {
+        t.swiss_rounds = v;
+    }
+    if let Some(v) = parsed::<RankedBy>(&mut map, "ranked_by", "Unknown ranking")? {
+        t.ranked_by = v;
+    }
+    if let Some(v) = map.remove("round_robin_points") {
+        t.round_robin_points = decode_points(v, t.round_robin_points)?;
+    }
+    if let Some(v) = map.remove("tie_breaks") {
+        t.tie_breaks = decode_array(v, |tb| match tb.as_str().map(TieBreak::from_str) {
+            Some(Ok(tb)) => Ok(tb),
+            _ => Err(Error::Decode("Unknown tie break", tb)),
+        })?;
+    }
+    if let Some(v) = boolean(&mut map, "accept_attachments")? {
+        t.accept_attachments = v;
+    }
+    if let Some(v) = boolean(&mut map, "hide_forum")? {
+        t.hide_forum = v;
+    }
+    if let Some(v) = boolean(&mut map, "teams")? {
+        t.teams = v;
+    }
+    if let Some(v) = boolean(&mut map, "team_convertable")? {
+        t.team_convertable = v;
+    }
+    if let Some(v) = boolean(&mut map, "show_rounds")? {
+        t.show_rounds = v;
+    }
+    if let Some(v) = boolean(&mut map, "private")? {
+        t.private = v;
+    }
+    t.game_name = string(&mut map, "game_name")?;
+    if let Some(v) = boolean(&mut map, "notify_users_when_matches_open")? {
+        t.notify_users_when_matches_open = v;
+    }
+    if let Some(v) = boolean(&mut map, "notify_users_when_the_tournament_ends")? {
+        t.notify_users_when_the_tournament_ends = v;
+    }
+    if let Some(v) = boolean(&mut map, "sequential_pairings")? {
+        t.sequential_pairings = v;
+    }
+    t.signup_cap = number(&mut map, "signup_cap")?;
+    if let Some(v) = string(&mut map, "start_at")? {
+        match DateTime::parse_from_rfc3339(&v) {
+            Ok(start_at) => t.start_at = Some(start_at.with_timezone(&Utc)),
+            Err(_) => return Err(Error::Decode("Invalid start time", Value::String(v))),
+        }
+    }
+    t.check_in_duration = number(&mut map, "check_in_duration")?;
+    t.grand_finals_modifier = string(&mut map, "grand_finals_modifier")?;
+    Ok(t)
+}
+
+fn decode_participant(value: Value) -> Result<ParticipantCreate, Error> {
+    let mut map = into_map(value)?;
+    Ok(ParticipantCreate {
+        name: string(&mut map, "name")?,
+        challonge_username: string(&mut map, "challonge_username")?,
+        email: string(&mut map, "email")?,
+        invite_name_or_email: string(&mut map, "invite_name_or_email")?,
+        seed: number(&mut map, "seed")?,
+        misc: string(&mut map, "misc")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::participants::tests::make_participant;
+    use crate::participants::Index as ParticipantIndex;
+    use crate::templates::{Format, Template, Templates};
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::{TournamentCreate, TournamentType};
+
+    fn weekly() -> Template {
+        let mut t = sample_tournament();
+        t.tournament_type = TournamentType::DoubleElimination;
+        t.signup_cap = Some(32);
+        t.start_at = Some("2015-01-20T18:00:00-05:00".parse().unwrap());
+        t.check_in_duration = Some(30);
+        t.game_name = "Tekken 8".to_owned();
+        let participants = ParticipantIndex(vec![
+            make_participant(2, "Bob", 2),
+            make_participant(1, "Alice", 1),
+        ]);
+        Template::of("weekly", &t, &participants)
+    }
+
+    fn assert_same(a: &Template, b: &Template) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.tournament, b.tournament);
+        assert_eq!(
+            format!("{:?}", a.participants),
+            format!("{:?}", b.participants)
+        );
+    }
+
+    #[test]
+    fn test_template_of_tournament() {
+        let template = weekly();
+        let tc = &template.tournament;
+        assert_eq!(tc.name, "Sample Tournament 1");
+        assert_eq!(tc.tournament_type, TournamentType::DoubleElimination);
+        assert_eq!(tc.signup_cap, Some(32));
+        assert_eq!(tc.tie_breaks, sample_tournament().tie_breaks);
+        assert_eq!(tc.game_name.as_deref(), Some("Tekken 8"));
+        assert_eq!(tc.swiss_points.bye, Some(1.0));
+        assert_eq!(tc.start_at, None);
+        assert_eq!(tc.check_in_duration, Some(30));
+        let names: Vec<_> = template
+            .participants
+            .iter()
+            .map(|p| (p.name.as_deref().unwrap(), p.seed.unwrap()))
+            .collect();
+        assert_eq!(names, vec![("Alice", 1), ("Bob", 2)]);
+
+        let json = template.to_json();
+        assert!(json["tournament"].get("grand_finals_modifier").is_none());
+        assert_same(&Template::from_json(json).unwrap(), &template);
+
+        // Only the settings which differ from the defaults need to be written.
+        let partial: serde_json::Value = serde_json::from_str(
+            r#"{"name": "quick", "tournament": {"tournament_type": "swiss", "swiss_rounds": 5}}"#,
+        )
+        .unwrap();
+        let quick = Template::from_json(partial).unwrap();
+        assert_eq!(quick.tournament.tournament_type, TournamentType::Swiss);
+        assert_eq!(quick.tournament.swiss_rounds, 5);
+        assert_eq!(
+            quick.tournament.ranked_by,
+            TournamentCreate::new().ranked_by
+        );
+        assert_eq!(quick.tournament.signup_cap, None);
+        assert!(quick.participants.is_empty());
+    }
+
+    #[test]
+    fn test_templates_directory() {
+        let dir = std::env::temp_dir().join(format!("challonge-templates-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut templates = Templates::new(&dir);
+        assert!(templates.names().unwrap().is_empty());
+
+        let template = weekly();
+        let path = templates.save(&template).unwrap();
+        assert_eq!(path, dir.join("weekly.json"));
+        assert_same(&templates.load("weekly").unwrap(), &template);
+        assert!(templates.load("monthly").is_err());
+        assert!(templates.load("../weekly").is_err());
+
+        let monthly = Template::new("monthly", TournamentCreate::new());
+        for &format in Format::ALL {
+            templates.format(format);
+            templates.save(&monthly).unwrap();
+            assert_same(&templates.load("monthly").unwrap(), &monthly);
+            templates.save(&template).unwrap();
+            assert_same(&templates.load("weekly").unwrap(), &template);
+        }
+        assert_eq!(templates.names().unwrap(), vec!["monthly", "weekly"]);
+        // One file per template, no temporary files left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        templates.remove("monthly").unwrap();
+        assert_eq!(templates.names().unwrap(), vec!["weekly"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
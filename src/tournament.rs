@@ -2,11 +2,14 @@
 
 use chrono::*;
 use serde_json::Value;
+use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
 use crate::error::Error;
-use crate::util::{decode_array, into_map, remove};
+use crate::matches::Index as MatchIndex;
+use crate::participants::Index as ParticipantIndex;
+use crate::util::{decode_array, decode_time, into_map, remove, remove_or_null};
 
 /// Tournament includes.
 #[derive(Debug, Clone)]
@@ -22,7 +25,7 @@ pub enum TournamentIncludes {
 }
 
 /// Tournament ranking order.
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RankedBy {
     /// Rank by number of matches won
     MatchWins,
@@ -62,8 +65,65 @@ impl fmt::Display for RankedBy {
     }
 }
 
+impl FromStr for RankedBy {
+    type Err = ();
+    fn from_str(s: &str) -> Result<RankedBy, ()> {
+        match s {
+            "match wins" => Ok(RankedBy::MatchWins),
+            "game wins" => Ok(RankedBy::GameWins),
+            "points scored" => Ok(RankedBy::PointsScored),
+            "points difference" => Ok(RankedBy::PointsDifference),
+            "custom" => Ok(RankedBy::Custom),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A rule to break ties between participants with equal ranking (Round Robin and Swiss).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TieBreak {
+    /// Number of matches won against the other tied participants
+    MatchWinsVsTied,
+
+    /// Number of games won
+    GameWins,
+
+    /// Percentage of games won
+    GameWinPercentage,
+
+    /// Points scored
+    PointsScored,
+
+    /// Difference in points
+    PointsDifference,
+}
+impl fmt::Display for TieBreak {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TieBreak::MatchWinsVsTied => fmt.write_str("match wins vs tied"),
+            TieBreak::GameWins => fmt.write_str("game wins"),
+            TieBreak::GameWinPercentage => fmt.write_str("game win percentage"),
+            TieBreak::PointsScored => fmt.write_str("points scored"),
+            TieBreak::PointsDifference => fmt.write_str("points difference"),
+        }
+    }
+}
+impl FromStr for TieBreak {
+    type Err = ();
+    fn from_str(s: &str) -> Result<TieBreak, ()> {
+        match s {
+            "match wins vs tied" => Ok(TieBreak::MatchWinsVsTied),
+            "game wins" => Ok(TieBreak::GameWins),
+            "game win percentage" => Ok(TieBreak::GameWinPercentage),
+            "points scored" => Ok(TieBreak::PointsScored),
+            "points difference" => Ok(TieBreak::PointsDifference),
+            _ => Err(()),
+        }
+    }
+}
+
 /// Tournament ID is an integer value or pair of strings (subdomain and tournament url)
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TournamentId {
     /// Subdomain and Tournament url
     Url(String, String),
@@ -89,6 +149,82 @@ impl fmt::Display for TournamentId {
     }
 }
 
+impl FromStr for TournamentId {
+    type Err = Error;
+
+    /// Parses a tournament id from a numeric id (`"2669881"`), a tournament url
+    /// optionally prefixed by a subdomain (`"mysub-weekly42"`) or a full Challonge url
+    /// (`"https://mysub.challonge.com/weekly42"`, `"challonge.com/weekly42"`).
+    fn from_str(s: &str) -> Result<TournamentId, Error> {
+        let s = s.trim();
+        if s.contains('/') {
+            let url = if s.contains("://") {
+                reqwest::Url::parse(s)
+            } else {
+                reqwest::Url::parse(&format!("https://{}", s))
+            };
+            return match url {
+                Ok(url) => TournamentId::try_from(&url),
+                Err(_) => Err(Error::Api("Invalid tournament url")),
+            };
+        }
+        if let Ok(id) = s.parse::<u64>() {
+            return Ok(TournamentId::Id(id));
+        }
+        // Tournament urls consist of letters, numbers and underscores only,
+        // so the last hyphen separates the subdomain from the url.
+        let (subdomain, url) = match s.rfind('-') {
+            Some(pos) => (&s[..pos], &s[pos + 1..]),
+            None => ("", s),
+        };
+        if !is_valid_url(url) || (subdomain.is_empty() && url.len() != s.len()) {
+            return Err(Error::Api("Invalid tournament id"));
+        }
+        Ok(TournamentId::Url(subdomain.to_owned(), url.to_owned()))
+    }
+}
+impl<'a> TryFrom<&'a reqwest::Url> for TournamentId {
+    type Error = Error;
+
+    /// Extracts a tournament id from a link to the tournament web page, for example
+    /// `https://mysub.challonge.com/weekly42` or `https://challonge.com/ru/weekly42/participants`.
+    fn try_from(url: &'a reqwest::Url) -> Result<TournamentId, Error> {
+        let host = url.host_str().unwrap_or("").to_lowercase();
+        let subdomain = match host.strip_suffix("challonge.com") {
+            Some("") | Some("www.") => "",
+            Some(sub) if sub.ends_with('.') => &sub[..sub.len() - 1],
+            _ => return Err(Error::Api("Not a Challonge url")),
+        };
+        let mut segments = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .peekable();
+        if let Some(segment) = segments.peek() {
+            if LOCALES.contains(segment) {
+                segments.next();
+            }
+        }
+        match segments.next() {
+            Some(tournament_url) if is_valid_url(tournament_url) => Ok(TournamentId::Url(
+                subdomain.to_owned(),
+                tournament_url.to_owned(),
+            )),
+            _ => Err(Error::Api("No tournament url in the link")),
+        }
+    }
+}
+
+/// Locale prefixes Challonge puts in front of the tournament url in links.
+const LOCALES: &[&str] = &[
+    "en", "ru", "es", "pt", "de", "fr", "it", "ja", "ko", "pl", "sv", "tr", "nl", "zh_CN", "zh_TW",
+];
+
+fn is_valid_url(url: &str) -> bool {
+    !url.is_empty() && url.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 /// Game points definition.
 #[derive(Debug, Clone, PartialEq)]
 pub struct GamePoints {
@@ -127,36 +263,36 @@ impl GamePoints {
 
     /// Decode `GamePoints` from JSON.
     pub fn decode(
-        mut map: &mut serde_json::Map<String, Value>,
+        map: &mut serde_json::Map<String, Value>,
         prefix: &str,
     ) -> Result<GamePoints, Error> {
         let mut bye = None;
-        if let Ok(bye_pts) = remove(&mut map, &format!("{}pts_for_bye", prefix)) {
+        if let Ok(bye_pts) = remove(map, &format!("{}pts_for_bye", prefix)) {
             if let Ok(b) = bye_pts.as_str().unwrap_or("").to_owned().parse::<f64>() {
                 bye = Some(b);
             }
         }
 
         Ok(GamePoints {
-            match_win: remove(&mut map, &format!("{}pts_for_match_win", prefix))?
+            match_win: remove(map, &format!("{}pts_for_match_win", prefix))?
                 .as_str()
                 .unwrap_or("")
                 .to_owned()
                 .parse::<f64>()
                 .unwrap_or(0f64),
-            match_tie: remove(&mut map, &format!("{}pts_for_match_tie", prefix))?
+            match_tie: remove(map, &format!("{}pts_for_match_tie", prefix))?
                 .as_str()
                 .unwrap_or("")
                 .to_owned()
                 .parse::<f64>()
                 .unwrap_or(0f64),
-            game_win: remove(&mut map, &format!("{}pts_for_game_win", prefix))?
+            game_win: remove(map, &format!("{}pts_for_game_win", prefix))?
                 .as_str()
                 .unwrap_or("")
                 .to_owned()
                 .parse::<f64>()
                 .unwrap_or(0f64),
-            game_tie: remove(&mut map, &format!("{}pts_for_game_tie", prefix))?
+            game_tie: remove(map, &format!("{}pts_for_game_tie", prefix))?
                 .as_str()
                 .unwrap_or("")
                 .to_owned()
@@ -179,7 +315,7 @@ impl Default for GamePoints {
 }
 
 /// Structure for creating a tournament.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TournamentCreate {
     /// Your event's name/title (Max: 60 characters)
     pub name: String,
@@ -214,6 +350,22 @@ pub struct TournamentCreate {
     /// Only for Round Robin system
     pub round_robin_points: GamePoints,
 
+    /// Rules to break ties in the ranking, in the order of application (Round Robin and Swiss).
+    /// Empty means Challonge's default rules.
+    pub tie_breaks: Vec<TieBreak>,
+
+    /// Allow match attachment uploads (default: false)
+    pub accept_attachments: bool,
+
+    /// Hide the forum tab on your Challonge page (default: false)
+    pub hide_forum: bool,
+
+    /// Participants are teams, see `participants::TeamCreate` (default: false)
+    pub teams: bool,
+
+    /// The tournament can be converted into a team tournament (default: false)
+    pub team_convertable: bool,
+
     /// Single &amp; Double Elimination only - Label each round above the bracket (default: false)
     pub show_rounds: bool,
 
@@ -233,15 +385,15 @@ pub struct TournamentCreate {
     /// First round matches are filled in top to bottom, then qualifying matches (if applicable). (default: false)
     pub sequential_pairings: bool,
 
-    /// Maximum number of participants in the bracket.
+    /// Maximum number of participants in the bracket, `None` for no limit.
     /// A waiting list (attribute on Participant) will capture participants once the cap is reached.
-    pub signup_cap: u64,
+    pub signup_cap: Option<u64>,
 
     /// the planned or anticipated start time for the tournament (Used with check_in_duration to determine participant check-in window). Timezone defaults to Eastern.
     pub start_at: Option<DateTime<Utc>>,
 
     /// Length of the participant check-in window in minutes.
-    pub check_in_duration: u64,
+    pub check_in_duration: Option<u64>,
 
     /// This option only affects double elimination. null/blank (default) - give the winners bracket finalist two chances to beat the losers bracket finalist, 'single match' - create only one grand finals match, 'skip' - don't create a finals match between winners and losers bracket finalists
     pub grand_finals_modifier: Option<String>,
@@ -261,15 +413,20 @@ impl TournamentCreate {
             swiss_rounds: 0,
             ranked_by: RankedBy::PointsScored,
             round_robin_points: GamePoints::default(),
+            tie_breaks: Vec::new(),
+            accept_attachments: false,
+            hide_forum: false,
+            teams: false,
+            team_convertable: false,
             show_rounds: false,
             private: false,
             game_name: None,
             notify_users_when_matches_open: true,
             notify_users_when_the_tournament_ends: true,
             sequential_pairings: false,
-            signup_cap: 4,
+            signup_cap: Some(4),
             start_at: None,
-            check_in_duration: 60,
+            check_in_duration: Some(60),
             grand_finals_modifier: None,
         }
     }
@@ -285,15 +442,30 @@ impl TournamentCreate {
     builder!(swiss_rounds, u64);
     builder!(ranked_by, RankedBy);
     builder!(round_robin_points, GamePoints);
+    builder!(tie_breaks, Vec<TieBreak>);
+    builder!(accept_attachments, bool);
+    builder!(hide_forum, bool);
+    builder!(teams, bool);
+    builder!(team_convertable, bool);
     builder!(show_rounds, bool);
     builder!(private, bool);
     builder_so!(game_name);
     builder!(notify_users_when_matches_open, bool);
     builder!(notify_users_when_the_tournament_ends, bool);
     builder!(sequential_pairings, bool);
-    builder!(signup_cap, u64);
-    builder!(check_in_duration, u64);
+    builder_o!(signup_cap, u64);
+    builder_o!(check_in_duration, u64);
     builder!(grand_finals_modifier, Option<String>);
+
+    /// Takes the settings of an existing tournament to create another one like it.
+    ///
+    /// Unlike `From<&Tournament>`, the start time is left unset: the new tournament gets its
+    /// own schedule. The check-in duration is kept and applies once a start time is set.
+    pub fn copy_of(t: &Tournament) -> TournamentCreate {
+        let mut tc = TournamentCreate::from(t);
+        tc.start_at = None;
+        tc
+    }
 }
 
 impl Default for TournamentCreate {
@@ -302,6 +474,242 @@ impl Default for TournamentCreate {
     }
 }
 
+/// Takes all the settings of an existing tournament, to create a copy of it or to update it.
+///
+/// Every setting `TournamentCreate` has is taken from the tournament; the start time keeps
+/// its instant but is converted to UTC.
+impl<'a> From<&'a Tournament> for TournamentCreate {
+    fn from(t: &'a Tournament) -> TournamentCreate {
+        TournamentCreate {
+            name: t.name.clone(),
+            tournament_type: t.tournament_type.clone(),
+            url: t.url.clone(),
+            subdomain: t.subdomain.clone().unwrap_or_default(),
+            description: t.description.clone(),
+            open_signup: t.open_signup,
+            hold_third_place_match: t.hold_third_place_match,
+            swiss_points: t.swiss_points.clone(),
+            swiss_rounds: t.swiss_rounds,
+            ranked_by: t.ranked_by,
+            round_robin_points: t.round_robin_points.clone(),
+            tie_breaks: t.tie_breaks.clone(),
+            accept_attachments: t.accept_attachments,
+            hide_forum: t.hide_forum,
+            teams: t.teams,
+            team_convertable: t.team_convertable,
+            show_rounds: t.show_rounds,
+            private: t.private,
+            game_name: Some(t.game_name.clone()).filter(|g| !g.is_empty()),
+            notify_users_when_matches_open: t.notify_users_when_matches_open,
+            notify_users_when_the_tournament_ends: t.notify_users_when_the_tournament_ends,
+            sequential_pairings: t.sequential_pairings,
+            signup_cap: t.signup_cap,
+            start_at: t.start_at.map(|s| s.with_timezone(&Utc)),
+            check_in_duration: t.check_in_duration,
+            grand_finals_modifier: t.grand_finals_modifier.clone(),
+        }
+    }
+}
+
+/// The settings of `TournamentCreate`, for the `TournamentUpdate` field lists.
+macro_rules! tournament_update_fields {
+    ($apply:ident) => {
+        $apply!(
+            name,
+            tournament_type,
+            url,
+            subdomain,
+            description,
+            open_signup,
+            hold_third_place_match,
+            swiss_points,
+            swiss_rounds,
+            ranked_by,
+            round_robin_points,
+            tie_breaks,
+            accept_attachments,
+            hide_forum,
+            teams,
+            team_convertable,
+            show_rounds,
+            private,
+            game_name,
+            notify_users_when_matches_open,
+            notify_users_when_the_tournament_ends,
+            sequential_pairings,
+            signup_cap,
+            start_at,
+            check_in_duration,
+            grand_finals_modifier
+        )
+    };
+}
+
+/// Structure for updating some attributes of a tournament.
+///
+/// Only the attributes which are set are sent, the others stay as they are. The attributes
+/// which may be absent from a tournament (`game_name`, `signup_cap`, ...) are set to `None`
+/// to clear them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TournamentUpdate {
+    /// Your event's name/title (Max: 60 characters)
+    pub name: Option<String>,
+
+    /// Type of a tournament
+    pub tournament_type: Option<TournamentType>,
+
+    /// challonge.com/url (letters, numbers, and underscores only)
+    pub url: Option<String>,
+
+    /// subdomain.challonge.com/url (Requires write access to the specified subdomain)
+    pub subdomain: Option<String>,
+
+    /// Description/instructions to be displayed above the bracket
+    pub description: Option<String>,
+
+    /// Have Challonge host a sign-up page
+    pub open_signup: Option<bool>,
+
+    /// Single Elimination only
+    pub hold_third_place_match: Option<bool>,
+
+    /// Only for Swiss system
+    pub swiss_points: Option<GamePoints>,
+
+    /// Number of rounds in swiss system
+    pub swiss_rounds: Option<u64>,
+
+    /// Tournament ranking type
+    pub ranked_by: Option<RankedBy>,
+
+    /// Only for Round Robin system
+    pub round_robin_points: Option<GamePoints>,
+
+    /// Rules to break ties in the ranking, in the order of application
+    pub tie_breaks: Option<Vec<TieBreak>>,
+
+    /// Allow match attachment uploads
+    pub accept_attachments: Option<bool>,
+
+    /// Hide the forum tab on your Challonge page
+    pub hide_forum: Option<bool>,
+
+    /// Participants are teams
+    pub teams: Option<bool>,
+
+    /// The tournament can be converted into a team tournament
+    pub team_convertable: Option<bool>,
+
+    /// Label each round above the bracket
+    pub show_rounds: Option<bool>,
+
+    /// Hide this tournament from the public browsable index and your profile
+    pub private: Option<bool>,
+
+    /// Name of the game to which this tournament belongs to
+    pub game_name: Option<Option<String>>,
+
+    /// Email registered Challonge participants when matches open up for them
+    pub notify_users_when_matches_open: Option<bool>,
+
+    /// Email registered Challonge participants the results when this tournament ends
+    pub notify_users_when_the_tournament_ends: Option<bool>,
+
+    /// Make pairings by going straight down the list of participants
+    pub sequential_pairings: Option<bool>,
+
+    /// Maximum number of participants in the bracket
+    pub signup_cap: Option<Option<u64>>,
+
+    /// The planned start time of the tournament
+    pub start_at: Option<Option<DateTime<Utc>>>,
+
+    /// Length of the participant check-in window in minutes
+    pub check_in_duration: Option<Option<u64>>,
+
+    /// Grand finals of a double elimination: `single match`, `skip` or `None` for the default
+    pub grand_finals_modifier: Option<Option<String>>,
+}
+impl TournamentUpdate {
+    /// Creates an update which changes nothing.
+    pub fn new() -> TournamentUpdate {
+        TournamentUpdate::default()
+    }
+
+    /// The update turning the `before` settings into the `after` ones: only the attributes
+    /// which differ are set.
+    pub fn changes(before: &TournamentCreate, after: &TournamentCreate) -> TournamentUpdate {
+        let mut update = TournamentUpdate::new();
+        macro_rules! diff {
+            ($($field:ident),*) => {
+                $(if before.$field != after.$field {
+                    update.$field = Some(after.$field.clone());
+                })*
+            };
+        }
+        tournament_update_fields!(diff);
+        update
+    }
+
+    /// Returns whether the update changes nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == TournamentUpdate::new()
+    }
+
+    /// Applies the update to the settings.
+    pub fn apply(&self, tournament: &mut TournamentCreate) {
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(if let Some(value) = self.$field.as_ref() {
+                    tournament.$field = value.clone();
+                })*
+            };
+        }
+        tournament_update_fields!(apply);
+    }
+
+    builder_so!(name);
+    builder_o!(tournament_type, TournamentType);
+    builder_so!(url);
+    builder_so!(subdomain);
+    builder_so!(description);
+    builder_o!(open_signup, bool);
+    builder_o!(hold_third_place_match, bool);
+    builder_o!(swiss_points, GamePoints);
+    builder_o!(swiss_rounds, u64);
+    builder_o!(ranked_by, RankedBy);
+    builder_o!(round_robin_points, GamePoints);
+    builder_o!(tie_breaks, Vec<TieBreak>);
+    builder_o!(accept_attachments, bool);
+    builder_o!(hide_forum, bool);
+    builder_o!(teams, bool);
+    builder_o!(team_convertable, bool);
+    builder_o!(show_rounds, bool);
+    builder_o!(private, bool);
+    builder_o!(game_name, Option<String>);
+    builder_o!(notify_users_when_matches_open, bool);
+    builder_o!(notify_users_when_the_tournament_ends, bool);
+    builder_o!(sequential_pairings, bool);
+    builder_o!(signup_cap, Option<u64>);
+    builder_o!(start_at, Option<DateTime<Utc>>);
+    builder_o!(check_in_duration, Option<u64>);
+    builder_o!(grand_finals_modifier, Option<String>);
+}
+
+/// Sets every attribute, to overwrite all the settings of a tournament.
+impl<'a> From<&'a TournamentCreate> for TournamentUpdate {
+    fn from(t: &'a TournamentCreate) -> TournamentUpdate {
+        let mut update = TournamentUpdate::new();
+        macro_rules! set {
+            ($($field:ident),*) => {
+                $(update.$field = Some(t.$field.clone());)*
+            };
+        }
+        tournament_update_fields!(set);
+        update
+    }
+}
+
 /// Challonge `Tournament` definition.
 #[derive(Debug, Clone)]
 pub struct Tournament {
@@ -377,7 +785,10 @@ pub struct Tournament {
 
     /// ???
     pub quick_advance: bool,
-    // <ranked-by>match wins</ranked-by>
+
+    /// Tournament ranking type
+    pub ranked_by: RankedBy,
+
     /// Tournament will require score agreement from all of participants of the match
     pub require_score_agreement: bool,
 
@@ -389,23 +800,32 @@ pub struct Tournament {
 
     /// Show rounds on the web page
     pub show_rounds: bool,
-    // <signup-cap nil="true"/>
-    // <start-at nil="true"/>
-    //
+    /// Maximum number of participants, the others are put on the waiting list
+    pub signup_cap: Option<u64>,
+
+    /// The planned start time of the tournament
+    pub start_at: Option<DateTime<FixedOffset>>,
+
+    /// Length of the participant check-in window before `start_at`, in minutes
+    pub check_in_duration: Option<u64>,
+
     /// Time when the tournament was started
     pub started_at: Option<DateTime<FixedOffset>>, //2015-01-19T16:57:17-05:00</started-at>
-    // <started-checking-in-at nil="true"/>
-    // <state>underway</state>
+
+    /// Time when the check-in started
+    pub started_checking_in_at: Option<DateTime<FixedOffset>>,
+    /// Current state of the tournament
+    pub state: TournamentStatus,
+
     /// Number of rounds in swiss system
     pub swiss_rounds: u64,
 
     /// The tournament works with teams
     pub teams: bool,
-    // <tie-breaks type="array">
-    // <tie-break>match wins vs tied</tie-break>
-    // <tie-break>game wins</tie-break>
-    // <tie-break>points scored</tie-break>
-    // </tie-breaks>
+
+    /// Rules to break ties in the ranking, in the order of application
+    pub tie_breaks: Vec<TieBreak>,
+
     /// A type of the tournament
     pub tournament_type: TournamentType,
 
@@ -417,7 +837,13 @@ pub struct Tournament {
 
     /// ???
     pub description_source: String,
-    // <subdomain nil="true"/>
+
+    /// Subdomain the tournament is hosted on, if any
+    pub subdomain: Option<String>,
+
+    /// How the grand finals of a double elimination are played, `None` for the default
+    pub grand_finals_modifier: Option<String>,
+
     /// Full url to the web page of the tournament in challonge system
     pub full_challonge_url: String,
 
@@ -439,11 +865,17 @@ pub struct Tournament {
     /// Participants can be swapped in brackets
     pub participants_swappable: bool,
 
-    /// ???
+    /// The tournament can be converted into a team tournament
     pub team_convertable: bool,
 
     /// Are the group stages were started already
     pub group_stages_were_started: bool,
+
+    /// Participants of the tournament, if they were included (see `TournamentIncludes`)
+    pub participants: Option<ParticipantIndex>,
+
+    /// Matches of the tournament, if they were included (see `TournamentIncludes`)
+    pub matches: Option<MatchIndex>,
 }
 impl Tournament {
     /// Decodes `Tournament` from JSON.
@@ -514,6 +946,10 @@ impl Tournament {
             progress_meter: remove(&mut tv, "progress_meter")?.as_u64().unwrap_or(0),
             swiss_points: GamePoints::decode(&mut tv, "").unwrap(),
             quick_advance: remove(&mut tv, "quick_advance")?.as_bool().unwrap_or(false),
+            ranked_by: RankedBy::from_str(
+                remove_or_null(&mut tv, "ranked_by").as_str().unwrap_or(""),
+            )
+            .unwrap_or(RankedBy::MatchWins),
             require_score_agreement: remove(&mut tv, "require_score_agreement")?
                 .as_bool()
                 .unwrap_or(false),
@@ -522,9 +958,22 @@ impl Tournament {
                 .as_bool()
                 .unwrap_or(false),
             show_rounds: remove(&mut tv, "show_rounds")?.as_bool().unwrap_or(false),
+            signup_cap: remove_or_null(&mut tv, "signup_cap").as_u64(),
+            start_at: decode_time(remove_or_null(&mut tv, "start_at")),
+            check_in_duration: remove_or_null(&mut tv, "check_in_duration").as_u64(),
             started_at,
+            started_checking_in_at: decode_time(remove_or_null(&mut tv, "started_checking_in_at")),
+            state: decode_status(remove_or_null(&mut tv, "state")),
             swiss_rounds: remove(&mut tv, "swiss_rounds")?.as_u64().unwrap_or(0),
             teams: remove(&mut tv, "teams")?.as_bool().unwrap_or(false),
+            tie_breaks: remove_or_null(&mut tv, "tie_breaks")
+                .as_array()
+                .map(|tbs| {
+                    tbs.iter()
+                        .filter_map(|tb| TieBreak::from_str(tb.as_str().unwrap_or("")).ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
             tournament_type: TournamentType::from_str(
                 remove(&mut tv, "tournament_type")?.as_str().unwrap_or(""),
             )
@@ -538,6 +987,12 @@ impl Tournament {
                 .as_str()
                 .unwrap_or("")
                 .to_string(),
+            subdomain: remove_or_null(&mut tv, "subdomain")
+                .as_str()
+                .map(String::from),
+            grand_finals_modifier: remove_or_null(&mut tv, "grand_finals_modifier")
+                .as_str()
+                .map(String::from),
             full_challonge_url: remove(&mut tv, "full_challonge_url")?
                 .as_str()
                 .unwrap_or("")
@@ -568,6 +1023,14 @@ impl Tournament {
             group_stages_were_started: remove(&mut tv, "group_stages_were_started")?
                 .as_bool()
                 .unwrap_or(false),
+            participants: match remove_or_null(&mut tv, "participants") {
+                Value::Null => None,
+                v => Some(ParticipantIndex::decode(v)?),
+            },
+            matches: match remove_or_null(&mut tv, "matches") {
+                Value::Null => None,
+                v => Some(MatchIndex::decode(v)?),
+            },
         })
     }
 }
@@ -669,13 +1132,84 @@ impl fmt::Display for TournamentState {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::tournament::{Tournament, TournamentId, TournamentType};
+/// State of a tournament as reported by Challonge.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TournamentStatus {
+    /// The tournament is not started yet
+    Pending,
 
-    #[test]
-    fn test_tournament_parse() {
-        let string = r#"{
+    /// Participants are checking in
+    CheckingIn,
+
+    /// The check-in is over, the tournament is not started yet
+    CheckedIn,
+
+    /// The group stage is in progress
+    GroupStagesUnderway,
+
+    /// The group stage is over, the final stage is not started yet
+    GroupStagesFinalized,
+
+    /// The tournament is in progress
+    Underway,
+
+    /// All matches are completed, the tournament waits to be finalized
+    AwaitingReview,
+
+    /// The tournament is finalized
+    Complete,
+
+    /// A state unknown to this crate, with its name as sent by Challonge (empty if missing)
+    Other(String),
+}
+impl fmt::Display for TournamentStatus {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TournamentStatus::Pending => fmt.write_str("pending"),
+            TournamentStatus::CheckingIn => fmt.write_str("checking_in"),
+            TournamentStatus::CheckedIn => fmt.write_str("checked_in"),
+            TournamentStatus::GroupStagesUnderway => fmt.write_str("group_stages_underway"),
+            TournamentStatus::GroupStagesFinalized => fmt.write_str("group_stages_finalized"),
+            TournamentStatus::Underway => fmt.write_str("underway"),
+            TournamentStatus::AwaitingReview => fmt.write_str("awaiting_review"),
+            TournamentStatus::Complete => fmt.write_str("complete"),
+            TournamentStatus::Other(ref s) => fmt.write_str(s),
+        }
+    }
+}
+impl FromStr for TournamentStatus {
+    type Err = ();
+    fn from_str(s: &str) -> Result<TournamentStatus, ()> {
+        match s {
+            "pending" => Ok(TournamentStatus::Pending),
+            "checking_in" => Ok(TournamentStatus::CheckingIn),
+            "checked_in" => Ok(TournamentStatus::CheckedIn),
+            "group_stages_underway" => Ok(TournamentStatus::GroupStagesUnderway),
+            "group_stages_finalized" => Ok(TournamentStatus::GroupStagesFinalized),
+            "underway" => Ok(TournamentStatus::Underway),
+            "awaiting_review" => Ok(TournamentStatus::AwaitingReview),
+            "complete" => Ok(TournamentStatus::Complete),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Decodes a state, keeping the states unknown to this crate as `TournamentStatus::Other`.
+fn decode_status(value: Value) -> TournamentStatus {
+    let s = value.as_str().unwrap_or("");
+    TournamentStatus::from_str(s).unwrap_or_else(|_| TournamentStatus::Other(s.to_owned()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::tournament::{
+        RankedBy, TieBreak, Tournament, TournamentCreate, TournamentId, TournamentStatus,
+        TournamentType, TournamentUpdate,
+    };
+    use std::convert::TryFrom;
+
+    /// A sample tournament from the Challonge API documentation.
+    pub const SAMPLE_TOURNAMENT: &str = r#"{
           "tournament": {
             "accept_attachments": false,
             "allow_participant_match_reporting": true,
@@ -746,6 +1280,30 @@ mod tests {
             "group_stages_were_started": false
           }
         }"#;
+
+    pub fn sample_tournament() -> Tournament {
+        Tournament::decode(serde_json::from_str(SAMPLE_TOURNAMENT).unwrap()).unwrap()
+    }
+
+    /// The sample tournament with all the optional settings set.
+    pub fn full_tournament_json() -> serde_json::Value {
+        let mut value: serde_json::Value = serde_json::from_str(SAMPLE_TOURNAMENT).unwrap();
+        let t = &mut value["tournament"];
+        t["subdomain"] = "mysub".into();
+        t["signup_cap"] = 16.into();
+        t["start_at"] = "2015-01-20T18:00:00-05:00".into();
+        t["check_in_duration"] = 30.into();
+        t["grand_finals_modifier"] = "single match".into();
+        t["accept_attachments"] = true.into();
+        t["hide_forum"] = true.into();
+        t["teams"] = true.into();
+        t["team_convertable"] = true.into();
+        value
+    }
+
+    #[test]
+    fn test_tournament_parse() {
+        let string = SAMPLE_TOURNAMENT;
         let json_r = serde_json::from_str(string);
         assert!(json_r.is_ok());
         let json = json_r.unwrap();
@@ -784,6 +1342,7 @@ mod tests {
             assert!(t.swiss_points.match_tie < 0.5f64 + f64::EPSILON);
             assert_eq!(t.swiss_points.match_win as u64, 1);
             assert!(!t.quick_advance);
+            assert_eq!(t.ranked_by, RankedBy::MatchWins);
             assert!(!t.require_score_agreement);
             assert_eq!(t.round_robin_points.game_tie as u64, 0);
             assert_eq!(t.round_robin_points.game_win as u64, 0);
@@ -793,9 +1352,22 @@ mod tests {
             assert!(!t.sequential_pairings);
             assert!(t.show_rounds);
             // assert_eq!(t.started_at, DateTime<);
+            assert_eq!(t.signup_cap, None);
+            assert_eq!(t.start_at, None);
+            assert_eq!(t.check_in_duration, None);
+            assert_eq!(t.started_checking_in_at, None);
             assert_eq!(t.swiss_rounds, 0);
             assert!(!t.teams);
+            assert_eq!(
+                t.tie_breaks,
+                vec![
+                    TieBreak::MatchWinsVsTied,
+                    TieBreak::GameWins,
+                    TieBreak::PointsScored
+                ]
+            );
             assert_eq!(t.tournament_type, TournamentType::SingleElimination);
+            assert_eq!(t.state, TournamentStatus::Underway);
             // assert_eq!(t.updated_at, DateTime<);
             assert_eq!(t.url, "sample_tournament_1");
             assert_eq!(t.description_source, "sample description source");
@@ -818,4 +1390,177 @@ mod tests {
             unreachable!();
         }
     }
+
+    #[test]
+    fn test_tournament_state_and_includes_parse() {
+        let mut json: serde_json::Value = serde_json::from_str(SAMPLE_TOURNAMENT).unwrap();
+        let t = Tournament::decode(json.clone()).unwrap();
+        assert!(t.participants.is_none());
+        assert!(t.matches.is_none());
+
+        json["tournament"]["state"] = "group_stages_underway".into();
+        json["tournament"]["participants"] = serde_json::json!([
+            crate::participants::tests::participant_json(1, "Alice", 1),
+            crate::participants::tests::participant_json(2, "Bob", 2),
+        ]);
+        json["tournament"]["matches"] = serde_json::json!([]);
+        let t = Tournament::decode(json.clone()).unwrap();
+        assert_eq!(t.state, TournamentStatus::GroupStagesUnderway);
+        assert_eq!(t.participants.unwrap().0.len(), 2);
+        assert!(t.matches.unwrap().0.is_empty());
+
+        json["tournament"]["state"] = "group_stages_finalized".into();
+        let t = Tournament::decode(json.clone()).unwrap();
+        assert_eq!(t.state, TournamentStatus::GroupStagesFinalized);
+        assert_eq!(t.state.to_string(), "group_stages_finalized");
+
+        json["tournament"]["state"] = "paused".into();
+        let t = Tournament::decode(json.clone()).unwrap();
+        assert_eq!(t.state, TournamentStatus::Other("paused".to_owned()));
+        assert_eq!(t.state.to_string(), "paused");
+        assert!("paused".parse::<TournamentStatus>().is_err());
+
+        json["tournament"].as_object_mut().unwrap().remove("state");
+        let t = Tournament::decode(json).unwrap();
+        assert_eq!(t.state, TournamentStatus::Other(String::new()));
+    }
+
+    #[test]
+    fn test_tournament_id_parse() {
+        let cases = [
+            ("2669881", TournamentId::Id(2669881)),
+            (
+                "weekly42",
+                TournamentId::Url(String::new(), "weekly42".to_owned()),
+            ),
+            (
+                "mysub-weekly42",
+                TournamentId::Url("mysub".to_owned(), "weekly42".to_owned()),
+            ),
+            (
+                "my-sub-weekly_42",
+                TournamentId::Url("my-sub".to_owned(), "weekly_42".to_owned()),
+            ),
+            (
+                "https://mysub.challonge.com/weekly42",
+                TournamentId::Url("mysub".to_owned(), "weekly42".to_owned()),
+            ),
+            (
+                "challonge.com/weekly42",
+                TournamentId::Url(String::new(), "weekly42".to_owned()),
+            ),
+            (
+                "http://www.challonge.com/ru/weekly42/participants",
+                TournamentId::Url(String::new(), "weekly42".to_owned()),
+            ),
+        ];
+        for (string, id) in cases.iter() {
+            assert_eq!(&string.parse::<TournamentId>().unwrap(), id);
+        }
+
+        for string in &["", "-weekly42", "weekly-", "https://example.com/weekly42"] {
+            assert!(string.parse::<TournamentId>().is_err());
+        }
+        let url = reqwest::Url::parse("https://challonge.com/").unwrap();
+        assert!(TournamentId::try_from(&url).is_err());
+    }
+
+    #[test]
+    fn test_tournament_id_round_trip() {
+        let ids = [
+            TournamentId::Id(1086875),
+            TournamentId::Url(String::new(), "sample_tournament_1".to_owned()),
+            TournamentId::Url("mysub".to_owned(), "weekly42".to_owned()),
+        ];
+        for id in ids.iter() {
+            assert_eq!(&id.to_string().parse::<TournamentId>().unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn test_tournament_create_from_tournament() {
+        let samples = [
+            sample_tournament(),
+            Tournament::decode(full_tournament_json()).unwrap(),
+        ];
+        for t in samples.iter() {
+            let tc = TournamentCreate::from(t);
+            assert_eq!(tc.name, t.name);
+            assert_eq!(tc.tournament_type, t.tournament_type);
+            assert_eq!(tc.url, t.url);
+            assert_eq!(
+                Some(tc.subdomain.clone()).filter(|s| !s.is_empty()),
+                t.subdomain
+            );
+            assert_eq!(tc.description, t.description);
+            assert_eq!(tc.open_signup, t.open_signup);
+            assert_eq!(tc.hold_third_place_match, t.hold_third_place_match);
+            assert_eq!(tc.swiss_points, t.swiss_points);
+            assert_eq!(tc.swiss_rounds, t.swiss_rounds);
+            assert_eq!(tc.ranked_by, t.ranked_by);
+            assert_eq!(tc.round_robin_points, t.round_robin_points);
+            assert_eq!(tc.tie_breaks, t.tie_breaks);
+            assert_eq!(tc.accept_attachments, t.accept_attachments);
+            assert_eq!(tc.hide_forum, t.hide_forum);
+            assert_eq!(tc.teams, t.teams);
+            assert_eq!(tc.team_convertable, t.team_convertable);
+            assert_eq!(tc.show_rounds, t.show_rounds);
+            assert_eq!(tc.private, t.private);
+            assert_eq!(tc.game_name.as_deref(), Some(t.game_name.as_str()));
+            assert_eq!(
+                tc.notify_users_when_matches_open,
+                t.notify_users_when_matches_open
+            );
+            assert_eq!(
+                tc.notify_users_when_the_tournament_ends,
+                t.notify_users_when_the_tournament_ends
+            );
+            assert_eq!(tc.sequential_pairings, t.sequential_pairings);
+            assert_eq!(tc.signup_cap, t.signup_cap);
+            assert_eq!(tc.start_at, t.start_at.map(|s| s.into()));
+            assert_eq!(tc.check_in_duration, t.check_in_duration);
+            assert_eq!(tc.grand_finals_modifier, t.grand_finals_modifier);
+
+            let copy = TournamentCreate::copy_of(t);
+            assert_eq!(copy.start_at, None);
+            assert_eq!(copy.check_in_duration, t.check_in_duration);
+            assert_eq!(
+                TournamentCreate {
+                    start_at: tc.start_at,
+                    ..copy
+                },
+                tc
+            );
+        }
+        assert!(samples[1].start_at.is_some());
+        assert_eq!(samples[1].subdomain.as_deref(), Some("mysub"));
+        assert_eq!(samples[1].signup_cap, Some(16));
+    }
+
+    #[test]
+    fn test_tournament_update_changes() {
+        let t = Tournament::decode(full_tournament_json()).unwrap();
+        let before = TournamentCreate::from(&t);
+        assert!(TournamentUpdate::changes(&before, &before).is_empty());
+
+        let mut after = before.clone();
+        after.swiss_rounds(5).game_name("Squash");
+        after.signup_cap = None;
+        let update = TournamentUpdate::changes(&before, &after);
+        let mut expected = TournamentUpdate::new();
+        expected
+            .swiss_rounds(5)
+            .game_name(Some("Squash".to_owned()))
+            .signup_cap(None);
+        assert_eq!(update, expected);
+
+        // Everything the update doesn't touch survives.
+        let mut applied = before.clone();
+        update.apply(&mut applied);
+        assert_eq!(applied, after);
+
+        let mut overwritten = TournamentCreate::new();
+        TournamentUpdate::from(&after).apply(&mut overwritten);
+        assert_eq!(overwritten, after);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::error::Error;
+use chrono::{DateTime, FixedOffset};
 use serde_json::Value;
 
 pub fn decode_array<T, F: Fn(Value) -> Result<T, Error>>(
@@ -22,3 +23,14 @@ pub fn remove(map: &mut serde_json::Map<String, Value>, key: &str) -> Result<Val
     map.remove(key)
         .ok_or_else(|| Error::Decode("Unexpected absent key", Value::String(key.into())))
 }
+
+/// Removes a key which may be absent in older API responses, treating it as `null`.
+pub fn remove_or_null(map: &mut serde_json::Map<String, Value>, key: &str) -> Value {
+    map.remove(key).unwrap_or(Value::Null)
+}
+
+pub fn decode_time(value: Value) -> Option<DateTime<FixedOffset>> {
+    value
+        .as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+}
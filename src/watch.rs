@@ -0,0 +1,361 @@
+//! Polling of a tournament for changes.
+//!
+//! Challonge has no push API, so `Watcher` polls the tournament and compares what it gets
+//! with the previous poll, turning the differences into `Event`s. Every poll fetches the
+//! tournament along with its participants and matches in a single request; they are only
+//! compared when its `updated_at` has moved, and only the records whose `updated_at` changed.
+
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::error::Error;
+use crate::matches::{Index as MatchIndex, Match, MatchId, MatchScores, MatchState};
+use crate::participants::{Index as ParticipantIndex, Participant, ParticipantId};
+use crate::tournament::{Tournament, TournamentId, TournamentIncludes, TournamentStatus};
+use crate::Challonge;
+
+/// A change of the watched tournament.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The tournament moved to another state.
+    TournamentStateChanged {
+        /// The previous state
+        from: TournamentStatus,
+        /// The new state
+        to: TournamentStatus,
+    },
+
+    /// A participant was added to the tournament.
+    ParticipantAdded(Participant),
+
+    /// A participant was removed from the tournament.
+    ParticipantRemoved(Participant),
+
+    /// A participant checked in.
+    ParticipantCheckedIn(Participant),
+
+    /// Both players of the match are known and it can be played.
+    MatchOpened(Match),
+
+    /// The match was marked as being played.
+    MatchUnderway(Match),
+
+    /// The match was completed (or its result was changed).
+    MatchCompleted {
+        /// The match
+        m: Match,
+        /// The winner, none for a tie
+        winner: Option<ParticipantId>,
+        /// Scores of the match
+        scores: MatchScores,
+    },
+}
+
+/// Polls a tournament and reports its changes.
+///
+/// The first poll only remembers the current state of the tournament, events are reported
+/// for the changes since the previous poll.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    id: TournamentId,
+    interval: Duration,
+    updated_at: Option<DateTime<FixedOffset>>,
+    state: Option<TournamentStatus>,
+    participants: BTreeMap<ParticipantId, Participant>,
+    matches: BTreeMap<MatchId, Match>,
+}
+impl Watcher {
+    /// Creates a watcher of the tournament polling it every 30 seconds.
+    pub fn new(id: TournamentId) -> Watcher {
+        Watcher {
+            id,
+            interval: Duration::from_secs(30),
+            updated_at: None,
+            state: None,
+            participants: BTreeMap::new(),
+            matches: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the time between the polls.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Polls the tournament once and returns the changes since the previous poll.
+    pub fn poll(&mut self, c: &Challonge) -> Result<Vec<Event>, Error> {
+        let tournament = c.get_tournament(&self.id, &TournamentIncludes::All)?;
+        if self.updated_at == Some(tournament.updated_at) {
+            return Ok(Vec::new());
+        }
+        let participants = tournament
+            .participants
+            .clone()
+            .unwrap_or_else(|| ParticipantIndex(Vec::new()));
+        let matches = tournament
+            .matches
+            .clone()
+            .unwrap_or_else(|| MatchIndex(Vec::new()));
+        Ok(self.update(&tournament, &participants, &matches))
+    }
+
+    /// Polls the tournament every interval, calling `f` with every change,
+    /// until `f` returns false or a request fails.
+    pub fn run<F: FnMut(Event) -> bool>(&mut self, c: &Challonge, mut f: F) -> Result<(), Error> {
+        loop {
+            for event in self.poll(c)? {
+                if !f(event) {
+                    return Ok(());
+                }
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    /// Compares the fetched tournament with the previous one and remembers it.
+    pub fn update(
+        &mut self,
+        tournament: &Tournament,
+        participants: &ParticipantIndex,
+        matches: &MatchIndex,
+    ) -> Vec<Event> {
+        let first = self.updated_at.is_none();
+        let mut events = Vec::new();
+
+        if let Some(ref from) = self.state {
+            if *from != tournament.state {
+                events.push(Event::TournamentStateChanged {
+                    from: from.clone(),
+                    to: tournament.state.clone(),
+                });
+            }
+        }
+
+        let mut removed = std::mem::take(&mut self.participants);
+        for p in &participants.0 {
+            match removed.remove(&p.id) {
+                None => events.push(Event::ParticipantAdded(p.clone())),
+                Some(old) => {
+                    if old.updated_at != p.updated_at && p.checked_in && !old.checked_in {
+                        events.push(Event::ParticipantCheckedIn(p.clone()));
+                    }
+                }
+            }
+            self.participants.insert(p.id, p.clone());
+        }
+        events.extend(removed.into_values().map(Event::ParticipantRemoved));
+
+        let mut sorted: Vec<&Match> = matches.0.iter().collect();
+        sorted.sort_by_key(|m| m.id);
+        for m in sorted {
+            let old = self.matches.insert(m.id, m.clone());
+            match old {
+                Some(ref old) if old.updated_at == m.updated_at => continue,
+                _ => {}
+            }
+            match m.state {
+                MatchState::Open => {
+                    if !matches!(old, Some(ref o) if o.state == MatchState::Open) {
+                        events.push(Event::MatchOpened(m.clone()));
+                    }
+                    if m.underway_at.is_some()
+                        && !matches!(old, Some(ref o) if o.underway_at.is_some())
+                    {
+                        events.push(Event::MatchUnderway(m.clone()));
+                    }
+                }
+                MatchState::Complete => {
+                    let unchanged = matches!(old, Some(ref o) if o.state == MatchState::Complete
+                        && o.winner_id == m.winner_id
+                        && o.scores_csv == m.scores_csv);
+                    if !unchanged {
+                        events.push(Event::MatchCompleted {
+                            m: m.clone(),
+                            winner: m.winner_id,
+                            scores: m.scores_csv.clone(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.updated_at = Some(tournament.updated_at);
+        self.state = Some(tournament.state.clone());
+        if first {
+            Vec::new()
+        } else {
+            events
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bracket::tests::make_match;
+    use crate::matches::{Index as MatchIndex, Match};
+    use crate::participants::tests::make_participant;
+    use crate::participants::{Index as ParticipantIndex, ParticipantId};
+    use crate::tournament::tests::sample_tournament;
+    use crate::tournament::{TournamentId, TournamentStatus};
+    use crate::watch::{Event, Watcher};
+    use chrono::{DateTime, Duration};
+
+    fn touched(mut m: Match, minutes: i64) -> Match {
+        m.updated_at += Duration::minutes(minutes);
+        m
+    }
+
+    fn names(events: &[Event]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| match e {
+                Event::TournamentStateChanged { from, to } => format!("state {} -> {}", from, to),
+                Event::ParticipantAdded(p) => format!("added {}", p.id),
+                Event::ParticipantRemoved(p) => format!("removed {}", p.id),
+                Event::ParticipantCheckedIn(p) => format!("checked in {}", p.id),
+                Event::MatchOpened(m) => format!("opened {}", m.id),
+                Event::MatchUnderway(m) => format!("underway {}", m.id),
+                Event::MatchCompleted { m, winner, scores } => format!(
+                    "completed {} by {} {}",
+                    m.id,
+                    winner.map_or(0, |w| w.0),
+                    scores
+                ),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_watcher_events() {
+        let mut t = sample_tournament();
+        t.state = TournamentStatus::Pending;
+        let participants = ParticipantIndex(vec![
+            make_participant(1, "Alice", 1),
+            make_participant(2, "Bob", 2),
+            make_participant(3, "Carol", 3),
+        ]);
+        let mut w = Watcher::new(TournamentId::Id(1086875));
+        assert!(w
+            .update(&t, &participants, &MatchIndex(Vec::new()))
+            .is_empty());
+
+        // Check-in, a removal and an addition.
+        let mut checked_in = participants.0.clone();
+        checked_in[0].checked_in = true;
+        checked_in[0].updated_at =
+            DateTime::parse_from_rfc3339("2015-01-20T10:00:00-05:00").unwrap();
+        checked_in.remove(2);
+        checked_in.push(make_participant(4, "Dave", 3));
+        let participants = ParticipantIndex(checked_in);
+        let events = w.update(&t, &participants, &MatchIndex(Vec::new()));
+        assert_eq!(names(&events), vec!["checked in 1", "added 4", "removed 3"]);
+
+        // The tournament starts.
+        t.state = TournamentStatus::Underway;
+        let open = make_match(
+            1,
+            "A",
+            1,
+            (Some(1), None),
+            (Some(2), None),
+            "open",
+            None,
+            "",
+        );
+        let pending = make_match(
+            2,
+            "B",
+            2,
+            (None, Some((1, false))),
+            (Some(4), None),
+            "pending",
+            None,
+            "",
+        );
+        let events = w.update(
+            &t,
+            &participants,
+            &MatchIndex(vec![open.clone(), pending.clone()]),
+        );
+        assert_eq!(
+            names(&events),
+            vec!["state pending -> underway", "opened 1"]
+        );
+
+        // Nothing changed.
+        let events = w.update(
+            &t,
+            &participants,
+            &MatchIndex(vec![open.clone(), pending.clone()]),
+        );
+        assert!(events.is_empty());
+
+        let mut underway = touched(open.clone(), 1);
+        underway.underway_at = Some(underway.updated_at);
+        let events = w.update(
+            &t,
+            &participants,
+            &MatchIndex(vec![underway, pending.clone()]),
+        );
+        assert_eq!(names(&events), vec!["underway 1"]);
+
+        let done = touched(
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(2), None),
+                "complete",
+                Some(1),
+                "3-1",
+            ),
+            2,
+        );
+        let next = touched(
+            make_match(
+                2,
+                "B",
+                2,
+                (Some(1), Some((1, false))),
+                (Some(4), None),
+                "open",
+                None,
+                "",
+            ),
+            2,
+        );
+        let events = w.update(
+            &t,
+            &participants,
+            &MatchIndex(vec![done.clone(), next.clone()]),
+        );
+        assert_eq!(names(&events), vec!["completed 1 by 1 3-1", "opened 2"]);
+
+        // The result is corrected.
+        let corrected = touched(
+            make_match(
+                1,
+                "A",
+                1,
+                (Some(1), None),
+                (Some(2), None),
+                "complete",
+                Some(2),
+                "1-3",
+            ),
+            3,
+        );
+        let events = w.update(&t, &participants, &MatchIndex(vec![corrected, next]));
+        assert_eq!(names(&events), vec!["completed 1 by 2 1-3"]);
+        assert_eq!(
+            w.participants.keys().cloned().collect::<Vec<_>>(),
+            vec![ParticipantId(1), ParticipantId(2), ParticipantId(4)]
+        );
+    }
+}
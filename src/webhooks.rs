@@ -0,0 +1,381 @@
+//! Challonge webhook notifications.
+//!
+//! A notification is a JSON object which wraps the changed record the same way the REST API
+//! responses do (`{"tournament": {...}}`, `{"participant": {...}}` or `{"match": {...}}`),
+//! along with the name of the `event`. `Notification::parse` decodes it with the same
+//! decoders as the API responses.
+//!
+//! When the webhook has a secret, the body is signed with it: the `X-Challonge-Signature`
+//! header carries the hex encoded HMAC-SHA256 of the body. Check it with `verify_signature`
+//! (`webhooks` feature) before trusting the notification. Webhooks configured with a shared
+//! secret in the URL or a header instead can be checked with `verify_secret`.
+//!
+//! With the `webhooks-server` feature, `Listener` is a small embedded HTTP server which
+//! checks the signature and passes the notifications to a callback. It doesn't look for
+//! a shared secret in the URL or headers.
+
+#[cfg(feature = "webhooks")]
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+#[cfg(feature = "webhooks")]
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::matches::Match;
+use crate::participants::Participant;
+use crate::tournament::Tournament;
+use crate::util::{into_map, remove_or_null};
+
+/// The header with the signature of the notification body.
+pub const SIGNATURE_HEADER: &str = "X-Challonge-Signature";
+
+/// The header with the event name, used when the body has no `event` key.
+pub const EVENT_HEADER: &str = "X-Challonge-Event";
+
+/// The record a notification is about.
+// Boxing the tournament would make the payloads awkward to match on for little gain.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Payload {
+    /// A tournament was created or changed
+    Tournament(Tournament),
+
+    /// A participant was added, changed or removed
+    Participant(Participant),
+
+    /// A match was changed
+    Match(Match),
+}
+
+/// A webhook notification.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// Name of the event (for example `match.completed`), empty if unknown
+    pub event: String,
+
+    /// The changed record
+    pub payload: Payload,
+}
+impl Notification {
+    /// Decodes `Notification` from JSON.
+    pub fn decode(value: Value) -> Result<Notification, Error> {
+        let mut map = into_map(value)?;
+        let event = remove_or_null(&mut map, "event")
+            .as_str()
+            .unwrap_or("")
+            .to_owned();
+        let payload = if map.contains_key("tournament") {
+            Payload::Tournament(Tournament::decode(Value::Object(map))?)
+        } else if map.contains_key("participant") {
+            Payload::Participant(Participant::decode(Value::Object(map))?)
+        } else if map.contains_key("match") {
+            Payload::Match(Match::decode(Value::Object(map))?)
+        } else {
+            return Err(Error::Decode("Unknown webhook payload", Value::Object(map)));
+        };
+        Ok(Notification { event, payload })
+    }
+
+    /// Decodes `Notification` from a request body.
+    pub fn parse(body: &[u8]) -> Result<Notification, Error> {
+        Notification::decode(serde_json::from_slice(body)?)
+    }
+}
+
+#[cfg(feature = "webhooks")]
+fn mac(secret: &str, body: &[u8]) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+    mac
+}
+
+/// Returns the signature of the body: the hex encoded HMAC-SHA256 with the secret as key.
+#[cfg(feature = "webhooks")]
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    mac(secret, body)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Checks the signature of the body, with or without a `sha256=` prefix.
+/// The comparison takes the same time wherever the signatures differ.
+#[cfg(feature = "webhooks")]
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let signature = signature.trim();
+    let hex = signature.strip_prefix("sha256=").unwrap_or(signature);
+    match decode_hex(hex) {
+        Some(bytes) => mac(secret, body).verify_slice(&bytes).is_ok(),
+        None => false,
+    }
+}
+
+/// Compares a shared secret sent with the notification to the expected one
+/// in time independent of where they differ.
+pub fn verify_secret(secret: &str, provided: &str) -> bool {
+    secret.len() == provided.len()
+        && secret
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(feature = "webhooks")]
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|p| p.len() == 2)
+                .and_then(|p| u8::from_str_radix(p, 16).ok())
+        })
+        .collect()
+}
+
+#[cfg(feature = "webhooks-server")]
+pub use self::server::Listener;
+
+#[cfg(feature = "webhooks-server")]
+mod server {
+    use std::io::{self, Read};
+    use std::net::SocketAddr;
+
+    use tiny_http::{Method, Request, Response, Server};
+
+    use super::{verify_signature, Notification, EVENT_HEADER, SIGNATURE_HEADER};
+    use crate::error::Error;
+
+    /// Bodies larger than this are rejected.
+    const MAX_BODY: u64 = 1024 * 1024;
+
+    /// An embedded HTTP server receiving webhook notifications.
+    ///
+    /// Every `POST` request is checked against the secret (if set) and decoded; invalid ones
+    /// are answered with `401` or `400` and skipped, valid ones with `200`.
+    pub struct Listener {
+        server: Server,
+        secret: Option<String>,
+    }
+    impl Listener {
+        /// Starts listening on the address, for example `0.0.0.0:8080`.
+        // `io::Error::other` needs Rust 1.74.
+        #[allow(clippy::io_other_error)]
+        pub fn bind(addr: &str) -> Result<Listener, Error> {
+            let server = Server::http(addr)
+                .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::Other, e)))?;
+            Ok(Listener {
+                server,
+                secret: None,
+            })
+        }
+
+        /// Requires the notifications to be signed with the secret.
+        pub fn secret<S: Into<String>>(&mut self, secret: S) -> &mut Self {
+            self.secret = Some(secret.into());
+            self
+        }
+
+        /// The address the listener is bound to.
+        pub fn local_addr(&self) -> Option<SocketAddr> {
+            self.server.server_addr().to_ip()
+        }
+
+        /// Waits for the next valid notification.
+        pub fn next(&self) -> Result<Notification, Error> {
+            loop {
+                let request = self.server.recv()?;
+                // A client going away must not stop the listener.
+                match self.handle(request) {
+                    Ok(Some(n)) => return Ok(n),
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Couldn't handle a webhook request: {}", e),
+                }
+            }
+        }
+
+        /// Calls `f` with every received notification until it returns false.
+        pub fn run<F: FnMut(Notification) -> bool>(&self, mut f: F) -> Result<(), Error> {
+            while f(self.next()?) {}
+            Ok(())
+        }
+
+        fn handle(&self, mut request: Request) -> io::Result<Option<Notification>> {
+            if *request.method() != Method::Post {
+                request.respond(Response::empty(405))?;
+                return Ok(None);
+            }
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv(name))
+                    .map(|h| h.value.as_str().to_owned())
+            };
+            let signature = header(SIGNATURE_HEADER);
+            let event = header(EVENT_HEADER);
+
+            let mut body = Vec::new();
+            request
+                .as_reader()
+                .take(MAX_BODY + 1)
+                .read_to_end(&mut body)?;
+            if body.len() as u64 > MAX_BODY {
+                request.respond(Response::empty(413))?;
+                return Ok(None);
+            }
+
+            if let Some(ref secret) = self.secret {
+                let signed = matches!(signature, Some(ref s) if verify_signature(secret, &body, s));
+                if !signed {
+                    log::warn!("Rejected a webhook notification with a wrong signature");
+                    request.respond(Response::empty(401))?;
+                    return Ok(None);
+                }
+            }
+
+            match Notification::parse(&body) {
+                Ok(mut n) => {
+                    if n.event.is_empty() {
+                        n.event = event.unwrap_or_default();
+                    }
+                    // The notification is valid whether or not the answer reaches the client.
+                    if let Err(e) = request.respond(Response::empty(200)) {
+                        log::warn!("Couldn't answer a webhook request: {}", e);
+                    }
+                    Ok(Some(n))
+                }
+                Err(e) => {
+                    log::warn!("Couldn't decode a webhook notification: {:?}", e);
+                    request.respond(Response::empty(400))?;
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::participants::tests::participant_json;
+    use crate::tournament::tests::SAMPLE_TOURNAMENT;
+    use crate::webhooks::{verify_secret, Notification, Payload};
+
+    fn tournament_body() -> Vec<u8> {
+        let mut json: serde_json::Value = serde_json::from_str(SAMPLE_TOURNAMENT).unwrap();
+        json["event"] = "tournament.started".into();
+        serde_json::to_vec(&json).unwrap()
+    }
+
+    #[test]
+    fn test_notification_parse() {
+        let n = Notification::parse(&tournament_body()).unwrap();
+        assert_eq!(n.event, "tournament.started");
+        match n.payload {
+            Payload::Tournament(t) => assert_eq!(t.name, "Sample Tournament 1"),
+            p => panic!("unexpected payload {:?}", p),
+        }
+
+        let n = Notification::decode(participant_json(7, "Alice", 1)).unwrap();
+        assert!(n.event.is_empty());
+        match n.payload {
+            Payload::Participant(p) => assert_eq!(p.name, "Alice"),
+            p => panic!("unexpected payload {:?}", p),
+        }
+
+        assert!(Notification::parse(br#"{"event": "team.created", "team": {}}"#).is_err());
+        assert!(Notification::parse(b"not json").is_err());
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[test]
+    fn test_signatures() {
+        use crate::webhooks::{sign, verify_signature};
+
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let body = tournament_body();
+        let signature = sign("secret", &body);
+        assert!(verify_signature("secret", &body, &signature));
+        assert!(verify_signature(
+            "secret",
+            &body,
+            &format!("sha256={}", signature.to_uppercase())
+        ));
+        assert!(!verify_signature("other", &body, &signature));
+        assert!(!verify_signature("secret", b"{}", &signature));
+        assert!(!verify_signature("secret", &body, "zz"));
+    }
+
+    #[test]
+    fn test_secrets() {
+        assert!(verify_secret("secret", "secret"));
+        assert!(!verify_secret("secret", "secreT"));
+        assert!(!verify_secret("secret", "secret2"));
+    }
+
+    #[cfg(feature = "webhooks-server")]
+    #[test]
+    fn test_listener() {
+        use crate::webhooks::{sign, Listener, SIGNATURE_HEADER};
+        use std::io::{Read, Write};
+        use std::net::{SocketAddr, TcpStream};
+        use std::thread;
+
+        fn post(addr: SocketAddr, body: &[u8], signature: &str) -> String {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "POST /webhook HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                 Content-Type: application/json\r\n{}: {}\r\nContent-Length: {}\r\n\r\n",
+                SIGNATURE_HEADER,
+                signature,
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response.lines().next().unwrap_or("").to_owned()
+        }
+
+        let mut listener = Listener::bind("127.0.0.1:0").unwrap();
+        listener.secret("secret");
+        let addr = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let body = tournament_body();
+            vec![
+                post(addr, &body, "0000"),
+                post(addr, b"{}", &sign("secret", b"{}")),
+                post(addr, &body, &sign("secret", &body)),
+            ]
+        });
+
+        let mut received = Vec::new();
+        listener
+            .run(|n| {
+                received.push(n);
+                false
+            })
+            .unwrap();
+        let statuses = client.join().unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                "HTTP/1.1 401 Unauthorized",
+                "HTTP/1.1 400 Bad Request",
+                "HTTP/1.1 200 OK"
+            ]
+        );
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].event, "tournament.started");
+    }
+}